@@ -0,0 +1,213 @@
+use std::{collections::HashMap, fmt::Display};
+
+const ENTRY_POINTS: [&str; 2] = ["Sys.init", "Main.main"];
+
+/// Link-time consistency check over every vm module making up the program, user classes
+/// and jack-os alike, run before the vm program is translated to asm.
+///
+/// Each `call X.f n` must resolve to exactly one `function X.f k`: otherwise the call
+/// ends up as an undefined asm label, which the assembler turns into a RAM variable.
+pub fn check(vm_modules: &[(String, Vec<String>)]) -> Result<(), Vec<LinkError>> {
+    /* function name => modules defining it */
+    let mut definitions: HashMap<&str, Vec<&str>> = HashMap::new();
+    /* (callee, caller, module) */
+    let mut calls: Vec<(&str, &str, &str)> = vec![];
+
+    for (module, vm) in vm_modules {
+        let mut current_fn: &str = "";
+
+        for vm_command in vm {
+            let tokens: Vec<&str> = vm_command.split(' ').collect();
+
+            match tokens[..] {
+                ["function", name, ..] => {
+                    definitions.entry(name).or_default().push(module.as_str());
+                    current_fn = name;
+                }
+                ["call", name, ..] => calls.push((name, current_fn, module)),
+                _ => {}
+            }
+        }
+    }
+
+    let mut errors: Vec<LinkError> = vec![];
+
+    ENTRY_POINTS.iter().filter(|name| !definitions.contains_key(*name)).for_each(|name| {
+        errors.push(LinkError::MissingEntryPoint(name.to_string()));
+    });
+
+    let mut duplicates: Vec<(&&str, &Vec<&str>)> =
+        definitions.iter().filter(|(_, modules)| modules.len() > 1).collect();
+    duplicates.sort();
+    duplicates.into_iter().for_each(|(name, modules)| {
+        errors.push(LinkError::DuplicateFunction {
+            name: name.to_string(),
+            modules: modules.iter().map(|m| m.to_string()).collect(),
+        });
+    });
+
+    calls.into_iter().filter(|(callee, _, _)| !definitions.contains_key(callee)).for_each(
+        |(callee, caller, module)| {
+            errors.push(LinkError::UndefinedFunction {
+                name: callee.to_string(),
+                caller: caller.to_string(),
+                module: module.to_string(),
+            });
+        },
+    );
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+/// Makes `Sys.init` run the static initializers of every class right before calling
+/// `Main.main`, once the OS is initialized: classes are initialized in alphabetical order.
+pub fn add_static_initializers(vm_modules: &mut [(String, Vec<String>)]) {
+    let suffix = format!(".{}", jack_to_vm::STATIC_INITIALIZER);
+    let mut initializers: Vec<String> = vm_modules
+        .iter()
+        .flat_map(|(_, vm)| vm.iter())
+        .filter_map(|vm_command| match vm_command.split(' ').collect::<Vec<&str>>()[..] {
+            ["function", name, ..] if name.ends_with(&suffix) => Some(name.to_string()),
+            _ => None,
+        })
+        .collect();
+    initializers.sort();
+
+    for (_, vm) in vm_modules.iter_mut() {
+        let mut current_fn = "";
+        let main_call = vm.iter().position(|vm_command| {
+            if let Some(name) = vm_command.strip_prefix("function ") {
+                current_fn = name.split(' ').next().unwrap_or_default();
+            }
+            current_fn == "Sys.init" && vm_command == "call Main.main 0"
+        });
+
+        if let Some(position) = main_call {
+            let calls = initializers
+                .iter()
+                .flat_map(|name| [format!("call {name} 0"), "pop temp 0".to_string()]);
+            vm.splice(position..position, calls);
+            return;
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LinkError {
+    UndefinedFunction { name: String, caller: String, module: String },
+    DuplicateFunction { name: String, modules: Vec<String> },
+    MissingEntryPoint(String),
+}
+
+impl Display for LinkError {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UndefinedFunction { name, caller, module } => {
+                write!(f, "{module}: {caller} calls undefined function {name}")
+            }
+            Self::DuplicateFunction { name, modules } => {
+                write!(f, "function {name} is defined more than once: {}", modules.join(", "))
+            }
+            Self::MissingEntryPoint(name) => write!(f, "entry point {name} is not defined"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(name: &str, vm: Vec<&str>) -> (String, Vec<String>) {
+        (name.to_string(), vm.into_iter().map(|l| l.to_string()).collect())
+    }
+
+    fn entry_points() -> Vec<(String, Vec<String>)> {
+        vec![
+            module("Sys.vm", vec!["function Sys.init 0", "call Main.main 0", "return"]),
+            module("Main.vm", vec!["function Main.main 0", "push constant 0", "return"]),
+        ]
+    }
+
+    #[test]
+    fn resolved_program_links() {
+        let mut modules = entry_points();
+        modules.push(module(
+            "Math.vm",
+            vec!["function Math.abs 0", "push argument 0", "call Main.main 0", "return"],
+        ));
+
+        assert_eq!(Ok(()), check(&modules));
+    }
+
+    #[test]
+    fn detect_undefined_function() {
+        let mut modules = entry_points();
+        modules.push(module(
+            "Game.vm",
+            vec!["function Game.run 0", "call Game.sep 0", "push constant 0", "return"],
+        ));
+
+        assert_eq!(
+            Err(vec![LinkError::UndefinedFunction {
+                name: "Game.sep".into(),
+                caller: "Game.run".into(),
+                module: "Game.vm".into(),
+            }]),
+            check(&modules)
+        );
+    }
+
+    #[test]
+    fn detect_duplicate_function() {
+        let mut modules = entry_points();
+        modules.push(module("Other.vm", vec!["function Main.main 0", "return"]));
+
+        assert_eq!(
+            Err(vec![LinkError::DuplicateFunction {
+                name: "Main.main".into(),
+                modules: vec!["Main.vm".into(), "Other.vm".into()],
+            }]),
+            check(&modules)
+        );
+    }
+
+    #[test]
+    fn run_static_initializers_before_main() {
+        let mut modules = entry_points();
+        modules
+            .push(module("Game.vm", vec!["function Game.$clinit 0", "push constant 0", "return"]));
+        modules.push(module(
+            "Board.vm",
+            vec!["function Board.$clinit 0", "push constant 0", "return"],
+        ));
+
+        add_static_initializers(&mut modules);
+
+        assert_eq!(
+            vec![
+                "function Sys.init 0",
+                "call Board.$clinit 0",
+                "pop temp 0",
+                "call Game.$clinit 0",
+                "pop temp 0",
+                "call Main.main 0",
+                "return",
+            ],
+            modules[0].1
+        );
+        assert_eq!(Ok(()), check(&modules));
+    }
+
+    #[test]
+    fn detect_missing_entry_points() {
+        let modules = vec![module("Game.vm", vec!["function Game.run 0", "return"])];
+
+        assert_eq!(
+            Err(vec![
+                LinkError::MissingEntryPoint("Sys.init".into()),
+                LinkError::MissingEntryPoint("Main.main".into()),
+            ]),
+            check(&modules)
+        );
+    }
+}
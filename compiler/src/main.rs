@@ -1,9 +1,78 @@
-use std::{env, fs, path::PathBuf};
+use std::{env, fs, path::PathBuf, process::ExitCode};
 
-fn main() {
+mod linker;
+
+fn main() -> ExitCode {
     let program_path = env::args().nth(1).expect("No program path provided!");
     let program_pathbuf = &PathBuf::from(program_path.to_string());
-    let vm_program = jack_to_vm::compile(program_pathbuf);
+    let debug = env::args().any(|arg| arg == "--debug");
+    let jack_os_path = PathBuf::from("compiler/jack-os");
+    let jack_options = jack_to_vm::Options {
+        debug,
+        libraries: vec![jack_os_path.clone()],
+        strict: env::args().any(|arg| arg == "--strict"),
+    };
+    let inline_threshold = match flag_value("--inline") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(threshold) => threshold,
+            Err(_) => {
+                eprintln!("--inline expects a number of vm commands, found {value}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => 0,
+    };
+
+    /* --lint enables every rule and --lint=a,b the listed ones, on top of the default ones
+     * which only --no-lint=a,b turns off */
+    let (enabled, disabled) = match (rules_flag("--lint"), rules_flag("--no-lint")) {
+        (Ok(enabled), Ok(disabled)) => (enabled, disabled.unwrap_or_default()),
+        (Err(name), _) | (_, Err(name)) => {
+            eprintln!("Unknown lint rule {name}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let enabled = match enabled {
+        Some(rules) => rules,
+        None if env::args().any(|arg| arg == "--lint") => jack_to_vm::Rule::ALL.to_vec(),
+        None => vec![],
+    };
+    let mut lint_rules: Vec<jack_to_vm::Rule> = vec![];
+    for rule in jack_to_vm::Rule::DEFAULT.into_iter().chain(enabled) {
+        if !lint_rules.contains(&rule) && !disabled.contains(&rule) {
+            lint_rules.push(rule);
+        }
+    }
+    if !lint_rules.is_empty() {
+        match jack_to_vm::lint(program_pathbuf, &lint_rules) {
+            Ok(lints) => lints.iter().for_each(|lint| eprintln!("{lint}")),
+            Err(errors) => {
+                errors.iter().for_each(|err| eprintln!("{err}"));
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    /* written before compiling, parse trees being valid regardless of semantic errors */
+    if env::args().any(|arg| arg == "--with-xml") {
+        match jack_to_vm::to_xml(program_pathbuf) {
+            Ok(xml_files) => xml_files.iter().for_each(|(name, xml)| {
+                fs::write(name, xml.join("\n")).expect("Writing .xml output failed");
+            }),
+            Err(errors) => {
+                errors.iter().for_each(|err| eprintln!("{err}"));
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let vm_program = match jack_to_vm::compile(program_pathbuf, &jack_options) {
+        Ok(vm_program) => vm_program,
+        Err(errors) => {
+            errors.iter().for_each(|err| eprintln!("{err}"));
+            return ExitCode::FAILURE;
+        }
+    };
 
     if env::args().any(|arg| arg == "--with-vm") {
         vm_program.iter().for_each(|(name, vm)| {
@@ -12,21 +81,49 @@ fn main() {
         });
     }
 
-    let mut ordered_vm = vec![];
-    let vm_os = jack_to_vm::compile(&PathBuf::from("compiler/jack-os"));
+    let mut ordered_vm: Vec<(String, Vec<String>)> = vec![];
+    /* the OS and the user program resolve each other's calls */
+    let os_options =
+        jack_to_vm::Options { debug, libraries: vec![program_pathbuf.clone()], strict: false };
+    let vm_os = match jack_to_vm::compile(&jack_os_path, &os_options) {
+        Ok(vm_os) => vm_os,
+        Err(errors) => {
+            errors.iter().for_each(|err| eprintln!("{err}"));
+            return ExitCode::FAILURE;
+        }
+    };
 
-    ordered_vm.push(vm_os.get("compiler/jack-os/Sys.jack").unwrap().clone());
-    ordered_vm.push(vm_os.get("compiler/jack-os/Memory.jack").unwrap().clone());
-    ordered_vm.push(vm_os.get("compiler/jack-os/Array.jack").unwrap().clone());
-    ordered_vm.push(vm_os.get("compiler/jack-os/Output.jack").unwrap().clone());
-    ordered_vm.push(vm_os.get("compiler/jack-os/Math.jack").unwrap().clone());
-    ordered_vm.push(vm_os.get("compiler/jack-os/Screen.jack").unwrap().clone());
-    ordered_vm.push(vm_os.get("compiler/jack-os/String.jack").unwrap().clone());
-    ordered_vm.push(vm_os.get("compiler/jack-os/Keyboard.jack").unwrap().clone());
+    ["Sys", "Memory", "Array", "Output", "Math", "Screen", "String", "Keyboard"].iter().for_each(
+        |class| {
+            let name = format!("compiler/jack-os/{class}.jack");
+            let vm = vm_os.get(&name).unwrap().clone();
+            ordered_vm.push((name, vm));
+        },
+    );
+
+    vm_program.into_iter().for_each(|class| ordered_vm.push(class));
+    linker::add_static_initializers(&mut ordered_vm);
 
-    vm_program.into_values().for_each(|class| ordered_vm.push(class));
+    if let Err(errors) = linker::check(&ordered_vm) {
+        errors.iter().for_each(|err| eprintln!("{err}"));
+        return ExitCode::FAILURE;
+    }
 
-    let asm_program = vm_translator::compile(ordered_vm);
+    let asm_program = match vm_translator::compile(
+        ordered_vm.into_iter().map(|(_, vm)| vm).collect(),
+        &vm_translator::Options {
+            debug,
+            inline_threshold,
+            no_inline: flag_value("--no-inline")
+                .map_or(vec![], |names| names.split(',').map(|n| n.to_string()).collect()),
+        },
+    ) {
+        Ok(asm) => asm,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
     let current_dir = env::current_dir().unwrap();
     let output_path = format!("{}/source", current_dir.to_str().unwrap());
 
@@ -37,9 +134,35 @@ fn main() {
 
     match hack_assembler::assembler::compile(asm_program) {
         Ok(hack) => match fs::write(format!("{}.hack", output_path), hack.join("\n")) {
-            Ok(_) => println!("Compilation to hack successful"),
-            Err(err) => eprintln!("{err}"),
+            Ok(_) => {
+                println!("Compilation to hack successful");
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                ExitCode::FAILURE
+            }
         },
-        Err(err) => eprintln!("{err}"),
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
     }
 }
+
+/* --flag=value */
+fn flag_value(flag: &str) -> Option<String> {
+    env::args().find_map(|arg| arg.strip_prefix(&format!("{flag}=")).map(|v| v.to_string()))
+}
+
+/* Lint rules listed by `flag=a,b`, or the first name which is not one */
+fn rules_flag(flag: &str) -> Result<Option<Vec<jack_to_vm::Rule>>, String> {
+    flag_value(flag)
+        .map(|names| {
+            names
+                .split(',')
+                .map(|name| jack_to_vm::Rule::try_from(name).map_err(|_| name.to_string()))
+                .collect()
+        })
+        .transpose()
+}
@@ -0,0 +1,232 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{self, Command, Output},
+};
+
+/* Runs the compiler over the given classes from a directory of its own, holding a copy
+ * of the jack-os where the compiler expects it: `source.hack` is written there too */
+fn compile(test: &str, classes: &[(&str, &str)], args: &[&str]) -> (Output, PathBuf) {
+    let dir = env::temp_dir().join(format!("jack-compiler-{test}-{}", process::id()));
+    let os_dir = dir.join("compiler/jack-os");
+    let program_dir = dir.join("program");
+    fs::create_dir_all(&os_dir).unwrap();
+    fs::create_dir_all(&program_dir).unwrap();
+
+    let jack_os = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("jack-os");
+    for entry in fs::read_dir(jack_os).unwrap() {
+        let path = entry.unwrap().path();
+        fs::copy(&path, os_dir.join(path.file_name().unwrap())).unwrap();
+    }
+    classes.iter().for_each(|(file, source)| fs::write(program_dir.join(file), source).unwrap());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_compiler"))
+        .arg("program")
+        .args(args)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+
+    (output, dir)
+}
+
+/* Exit status and error output of a compilation, its directory removed */
+fn compile_errors(test: &str, classes: &[(&str, &str)], args: &[&str]) -> (bool, String) {
+    let (output, dir) = compile(test, classes, args);
+    fs::remove_dir_all(dir).unwrap();
+
+    (output.status.success(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn fail_on_parse_errors() {
+    let (success, stderr) = compile_errors(
+        "parse",
+        &[("Main.jack", "class Main { function void main() { return } }")],
+        &[],
+    );
+
+    assert!(!success);
+    assert!(stderr.contains("Main.jack:1:44: expected a term, found `}`"), "{stderr}");
+}
+
+#[test]
+fn fail_on_unknown_lint_rules() {
+    let (success, stderr) = compile_errors(
+        "lint",
+        &[("Main.jack", "class Main { function void main() { return; } }")],
+        &["--lint=unknown"],
+    );
+
+    assert!(!success);
+    assert!(stderr.contains("Unknown lint rule unknown"), "{stderr}");
+}
+
+#[test]
+fn keep_default_lint_rules_along_with_picked_ones() {
+    let main = "class Main {
+    function void main() {
+        var int x;
+        let x = 1 + 2 * 3;
+        return;
+    }
+}";
+    let precedence = "Main.jack:4:13: warning: `+` applies before `*`";
+
+    let (success, stderr) =
+        compile_errors("lint-picked", &[("Main.jack", main)], &["--lint=unused-variable"]);
+    assert!(success, "{stderr}");
+    assert!(
+        stderr.contains("Main.jack:3:17: warning: local variable `x` is never read"),
+        "{stderr}"
+    );
+    assert!(stderr.contains(precedence), "{stderr}");
+
+    let (success, stderr) = compile_errors(
+        "lint-disabled",
+        &[("Main.jack", main)],
+        &["--lint=unused-variable", "--no-lint=operator-precedence"],
+    );
+    assert!(success, "{stderr}");
+    assert!(!stderr.contains(precedence), "{stderr}");
+}
+
+#[test]
+fn fail_on_link_errors() {
+    let (success, stderr) = compile_errors(
+        "link",
+        &[
+            ("Main.jack", "class Main { function void main() { do Math.abs(1); return; } }"),
+            ("Math.jack", "class Math { function int abs(int x) { return x; } }"),
+        ],
+        &[],
+    );
+
+    assert!(!success);
+    assert!(stderr.contains("function Math.abs is defined more than once"), "{stderr}");
+}
+
+#[test]
+fn fail_on_semantic_errors() {
+    let (success, stderr) = compile_errors(
+        "semantic",
+        &[("Main.jack", "class Main { function void main() { let x = 1; return; } }")],
+        &[],
+    );
+
+    assert!(!success);
+    assert!(stderr.contains("Main.jack:1:41: undeclared variable `x`"), "{stderr}");
+}
+
+#[test]
+fn fail_on_type_errors_in_strict_mode() {
+    let main = "class Main { function void main() { var Main m; let m = 5; return; } }";
+
+    let (success, stderr) = compile_errors("strict", &[("Main.jack", main)], &["--strict"]);
+    assert!(!success);
+    assert!(stderr.contains("cannot assign int to `m` of type Main"), "{stderr}");
+}
+
+/* Runs the Hack program until `RAM[address]` holds `value`, returning the RAM then,
+ * or gives up after `max_steps` instructions */
+fn run_until(rom: &[u16], address: usize, value: u16, max_steps: usize) -> Option<Vec<u16>> {
+    let mut ram = vec![0u16; 32768];
+    let (mut a, mut d, mut pc) = (0u16, 0u16, 0usize);
+
+    for _ in 0..max_steps {
+        if ram[address] == value {
+            return Some(ram);
+        }
+
+        let instruction = rom[pc];
+        if instruction & 0x8000 == 0 {
+            a = instruction;
+            pc += 1;
+            continue;
+        }
+
+        let x = d;
+        let y = if instruction & 0x1000 != 0 { ram[a as usize & 0x7FFF] } else { a };
+        let out = match (instruction >> 6) & 0x3F {
+            0b101010 => 0,
+            0b111111 => 1,
+            0b111010 => 0xFFFF,
+            0b001100 => x,
+            0b110000 => y,
+            0b001101 => !x,
+            0b110001 => !y,
+            0b001111 => x.wrapping_neg(),
+            0b110011 => y.wrapping_neg(),
+            0b011111 => x.wrapping_add(1),
+            0b110111 => y.wrapping_add(1),
+            0b001110 => x.wrapping_sub(1),
+            0b110010 => y.wrapping_sub(1),
+            0b000010 => x.wrapping_add(y),
+            0b010011 => x.wrapping_sub(y),
+            0b000111 => y.wrapping_sub(x),
+            0b000000 => x & y,
+            0b010101 => x | y,
+            comp => panic!("invalid comp bits {comp:06b} at {pc}"),
+        };
+
+        /* M and the jump target are those of the A register before the instruction */
+        let target = a as usize;
+        if instruction & 0b001000 != 0 {
+            ram[target & 0x7FFF] = out;
+        }
+        if instruction & 0b010000 != 0 {
+            d = out;
+        }
+        if instruction & 0b100000 != 0 {
+            a = out;
+        }
+
+        let signed = out as i16;
+        let jump = (instruction & 0b100 != 0 && signed < 0)
+            || (instruction & 0b010 != 0 && signed == 0)
+            || (instruction & 0b001 != 0 && signed > 0);
+        pc = if jump { target } else { pc + 1 };
+    }
+
+    None
+}
+
+#[test]
+fn run_debug_builds_past_os_initialization() {
+    let main = "class Main {
+    function void main() {
+        var Array a;
+        let a = Array.new(2);
+        let a[1] = 7;
+        do Memory.poke(8000, a[1] * 12 / 2);
+        do Memory.poke(7999, 12345);
+        return;
+    }
+}";
+
+    let (output, dir) = compile("debug", &[("Main.jack", main)], &["--debug"]);
+    let hack = fs::read_to_string(dir.join("source.hack"));
+    fs::remove_dir_all(dir).unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let rom: Vec<u16> =
+        hack.unwrap().lines().map(|line| u16::from_str_radix(line, 2).unwrap()).collect();
+    let ram = run_until(&rom, 7999, 12345, 20_000_000).expect("Main.main was never reached");
+    assert_eq!(42, ram[8000]);
+}
+
+#[test]
+fn fail_on_invalid_inline_thresholds() {
+    let main = "class Main { function void main() { return; } }";
+
+    for threshold in ["abc", "-1"] {
+        let flag = format!("--inline={threshold}");
+        let (success, stderr) = compile_errors("inline", &[("Main.jack", main)], &[&flag]);
+        assert!(!success);
+        assert!(
+            stderr
+                .contains(&format!("--inline expects a number of vm commands, found {threshold}")),
+            "{stderr}"
+        );
+    }
+}
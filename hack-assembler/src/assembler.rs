@@ -2,6 +2,9 @@ use crate::instruction::asm::Instruction;
 use crate::instruction::hack::Hackable;
 use std::{collections::HashMap, fs};
 
+/* Instructions the Hack ROM holds, addressed by 15 bits */
+const ROM_SIZE: usize = 32768;
+
 pub fn compile(asm: Vec<String>) -> Result<Vec<String>, String> {
     Assembler::new(asm).compile()
 }
@@ -67,6 +70,13 @@ impl Assembler {
 
     fn compile(self: &Self) -> Result<Vec<String>, String> {
         let asm_without_variables = self.pre_process()?;
+        if asm_without_variables.len() > ROM_SIZE {
+            return Err(format!(
+                "The program takes {} instructions, more than the {} of the Hack ROM",
+                asm_without_variables.len(),
+                ROM_SIZE
+            ));
+        }
         let mut hack_out: Vec<String> = vec![];
 
         for op in asm_without_variables {
@@ -324,6 +334,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn programs_must_fit_the_rom() {
+        let asm = vec!["D=0".to_string(); ROM_SIZE + 1];
+
+        assert_eq!(
+            "The program takes 32769 instructions, more than the 32768 of the Hack ROM",
+            Assembler::new(asm).compile().unwrap_err()
+        );
+        assert!(Assembler::new(vec!["D=0".to_string(); ROM_SIZE]).compile().is_ok());
+    }
+
     #[test]
     fn compilation_works() {
         let asm = vec![
@@ -0,0 +1,323 @@
+use std::fmt::Display;
+
+/// JSON values exchanged with the client, objects keeping their keys in insertion order
+#[derive(Debug, PartialEq, Clone)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+const NULL: Json = Json::Null;
+
+impl Json {
+    pub fn parse(input: &str) -> Result<Json, String> {
+        let mut parser = Parser { chars: input.chars().collect(), index: 0 };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+
+        match parser.current() {
+            None => Ok(value),
+            Some(c) => Err(format!("unexpected `{c}` after the json value")),
+        }
+    }
+
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    /* Missing keys and non objects read as null, so that lookups can be chained */
+    pub fn get(self: &Self, key: &str) -> &Json {
+        match self {
+            Json::Object(fields) => {
+                fields.iter().find(|(k, _)| k == key).map_or(&NULL, |(_, value)| value)
+            }
+            _ => &NULL,
+        }
+    }
+
+    pub fn as_str(self: &Self) -> Option<&str> {
+        match self {
+            Json::String(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(self: &Self) -> Option<usize> {
+        match self {
+            Json::Number(val) if *val >= 0.0 => Some(*val as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(self: &Self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(value: Vec<Json>) -> Self {
+        Json::Array(value)
+    }
+}
+
+impl Display for Json {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(val) => write!(f, "{val}"),
+            Json::Number(val) if val.fract() == 0.0 => write!(f, "{}", *val as i64),
+            Json::Number(val) => write!(f, "{val}"),
+            Json::String(val) => write_string(f, val),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, val: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in val.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+}
+
+impl Parser {
+    fn parse_value(self: &mut Self) -> Result<Json, String> {
+        self.skip_whitespace();
+
+        match self.current() {
+            Some('n') => self.parse_literal("null", Json::Null),
+            Some('t') => self.parse_literal("true", Json::Bool(true)),
+            Some('f') => self.parse_literal("false", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.parse_string()?)),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) => Err(format!("unexpected `{c}`")),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn parse_literal(self: &mut Self, literal: &str, value: Json) -> Result<Json, String> {
+        for expected in literal.chars() {
+            if self.current() != Some(expected) {
+                return Err(format!("expected `{literal}`"));
+            }
+            self.index += 1;
+        }
+
+        Ok(value)
+    }
+
+    fn parse_number(self: &mut Self) -> Result<Json, String> {
+        let start = self.index;
+
+        while let Some(c) = self.current()
+            && (c.is_ascii_digit() || ['-', '+', '.', 'e', 'E'].contains(&c))
+        {
+            self.index += 1;
+        }
+
+        let text: String = self.chars[start..self.index].iter().collect();
+        text.parse().map(Json::Number).map_err(|_| format!("invalid number `{text}`"))
+    }
+
+    fn parse_string(self: &mut Self) -> Result<String, String> {
+        self.index += 1;
+        let mut value = String::new();
+
+        loop {
+            let c = self.current().ok_or("unterminated string")?;
+            self.index += 1;
+
+            match c {
+                '"' => return Ok(value),
+                '\\' => {
+                    let escaped = self.current().ok_or("unterminated string")?;
+                    self.index += 1;
+
+                    match escaped {
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        'b' => value.push('\u{8}'),
+                        'f' => value.push('\u{c}'),
+                        'u' => {
+                            let hex: String = self.chars.iter().skip(self.index).take(4).collect();
+                            let code = u32::from_str_radix(&hex, 16)
+                                .map_err(|_| format!("invalid unicode escape `{hex}`"))?;
+                            value.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                            self.index += 4;
+                        }
+                        c => value.push(c),
+                    }
+                }
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn parse_array(self: &mut Self) -> Result<Json, String> {
+        self.index += 1;
+        let mut values: Vec<Json> = vec![];
+
+        self.skip_whitespace();
+        if self.current() == Some(']') {
+            self.index += 1;
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+
+            match self.current() {
+                Some(',') => self.index += 1,
+                Some(']') => {
+                    self.index += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err("expected `,` or `]`".to_string()),
+            }
+        }
+    }
+
+    fn parse_object(self: &mut Self) -> Result<Json, String> {
+        self.index += 1;
+        let mut fields: Vec<(String, Json)> = vec![];
+
+        self.skip_whitespace();
+        if self.current() == Some('}') {
+            self.index += 1;
+            return Ok(Json::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.current() != Some('"') {
+                return Err("expected a key".to_string());
+            }
+            let key = self.parse_string()?;
+
+            self.skip_whitespace();
+            if self.current() != Some(':') {
+                return Err("expected `:`".to_string());
+            }
+            self.index += 1;
+
+            fields.push((key, self.parse_value()?));
+            self.skip_whitespace();
+
+            match self.current() {
+                Some(',') => self.index += 1,
+                Some('}') => {
+                    self.index += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err("expected `,` or `}`".to_string()),
+            }
+        }
+    }
+
+    fn skip_whitespace(self: &mut Self) {
+        while let Some(c) = self.current()
+            && c.is_whitespace()
+        {
+            self.index += 1;
+        }
+    }
+
+    fn current(self: &Self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    #[test]
+    fn parse_and_print_back() {
+        let input = r#"{"id": 1, "params": {"text": "a \"b\"\n", "list": [true, null, -2.5]}}"#;
+        let json = Json::parse(input).unwrap();
+
+        assert_eq!(Some(1), json.get("id").as_usize());
+        assert_eq!(Some("a \"b\"\n"), json.get("params").get("text").as_str());
+        assert_eq!(&Json::Null, json.get("params").get("missing").get("key"));
+        assert_eq!(
+            r#"{"id":1,"params":{"text":"a \"b\"\n","list":[true,null,-2.5]}}"#,
+            json.to_string()
+        );
+    }
+
+    #[test]
+    fn report_malformed_input() {
+        assert_eq!(Err("expected `,` or `]`".to_string()), Json::parse("[1 2]"));
+        assert_eq!(Err("unterminated string".to_string()), Json::parse(r#"{"a": "b"#));
+        assert_eq!(Err("unexpected `}` after the json value".to_string()), Json::parse("{}}"));
+    }
+}
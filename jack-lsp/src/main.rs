@@ -0,0 +1,69 @@
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+mod json;
+mod server;
+
+use json::Json;
+
+/* The OS classes are indexed from the compiler crate unless --jack-os=DIR is given */
+const JACK_OS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../compiler/jack-os");
+
+fn main() {
+    let os_path = env::args()
+        .find_map(|arg| arg.strip_prefix("--jack-os=").map(PathBuf::from))
+        .unwrap_or(PathBuf::from(JACK_OS_PATH));
+    let mut server = server::Server::new(os_path);
+
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+
+    while let Some(content) = read_message(&mut stdin) {
+        let message = match Json::parse(&content) {
+            Ok(message) => message,
+            Err(err) => {
+                eprintln!("Invalid message: {err}");
+                continue;
+            }
+        };
+
+        for response in server.handle(&message) {
+            let body = response.to_string();
+            write!(stdout, "Content-Length: {}\r\n\r\n{body}", body.len())
+                .and_then(|_| stdout.flush())
+                .expect("Writing to stdout failed");
+        }
+
+        if server.exited {
+            break;
+        }
+    }
+}
+
+/* `Content-Length: N` header, other headers, an empty line, then N bytes of json */
+fn read_message(input: &mut impl BufRead) -> Option<String> {
+    let mut content_length: Option<usize> = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse().ok();
+        }
+    }
+
+    let mut content = vec![0; content_length?];
+    input.read_exact(&mut content).ok()?;
+
+    String::from_utf8(content).ok()
+}
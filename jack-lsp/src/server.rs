@@ -0,0 +1,836 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use jack_to_vm::grammar::*;
+use jack_to_vm::{ClassSymbols, ClassTable, Kind, RoutineSymbols, Span, Token, TokenKind};
+
+use super::json::Json;
+
+/// Language server state: the documents opened by the client, and the derivation tree
+/// each of them last parsed to, so that completions keep working while a line is being typed.
+///
+/// Symbols resolve across the classes of the directory of the document, open documents
+/// taking precedence over their saved content, and across the OS classes.
+pub struct Server {
+    os_path: PathBuf,
+    documents: HashMap<String, String>,
+    parsed: HashMap<String, Class>,
+    pub exited: bool,
+}
+
+/* A class of the index along with where it is declared */
+struct IndexedClass {
+    path: String,
+    name_span: Span,
+    class: Class,
+}
+
+/* What an identifier resolves to */
+struct Symbol {
+    signature: String,
+    path: String,
+    span: Span,
+    name: String,
+}
+
+impl Server {
+    pub fn new(os_path: PathBuf) -> Self {
+        Self { os_path, documents: HashMap::new(), parsed: HashMap::new(), exited: false }
+    }
+
+    /// Incoming message -> outgoing responses and notifications
+    pub fn handle(self: &mut Self, message: &Json) -> Vec<Json> {
+        let params = message.get("params");
+        let method = message.get("method").as_str().unwrap_or_default();
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or_default();
+        let path = uri_to_path(uri);
+
+        let result = match method {
+            "initialize" => capabilities(),
+            "shutdown" => Json::Null,
+            "textDocument/definition" => self.definition(&path, params.get("position")),
+            "textDocument/hover" => self.hover(&path, params.get("position")),
+            "textDocument/completion" => self.completion(&path, params.get("position")),
+            "textDocument/documentSymbol" => self.document_symbols(&path),
+            "exit" => {
+                self.exited = true;
+                return vec![];
+            }
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").get("text").as_str().unwrap_or_default();
+                self.update(&path, text.to_string());
+                return vec![self.diagnostics(uri, &path)];
+            }
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges").as_array();
+                /* full synchronization: the last change holds the whole text */
+                if let Some(text) =
+                    changes.and_then(|c| c.last()).and_then(|c| c.get("text").as_str())
+                {
+                    self.update(&path, text.to_string());
+                }
+                return vec![];
+            }
+            "textDocument/didSave" => {
+                if let Some(text) = params.get("text").as_str() {
+                    self.update(&path, text.to_string());
+                }
+                return vec![self.diagnostics(uri, &path)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&path);
+                self.parsed.remove(&path);
+                return vec![];
+            }
+            _ => match message.get("id") {
+                Json::Null => return vec![],
+                id => {
+                    let error = Json::object(vec![
+                        ("code", Json::Number(-32601.0)),
+                        ("message", format!("unsupported method {method}").into()),
+                    ]);
+                    return vec![Json::object(vec![
+                        ("jsonrpc", "2.0".into()),
+                        ("id", id.clone()),
+                        ("error", error),
+                    ])];
+                }
+            },
+        };
+
+        vec![Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("id", message.get("id").clone()),
+            ("result", result),
+        ])]
+    }
+
+    fn update(self: &mut Self, path: &str, text: String) {
+        if let Ok(class) = jack_to_vm::parse(&text, path) {
+            self.parsed.insert(path.to_string(), class);
+        }
+        self.documents.insert(path.to_string(), text);
+    }
+
+    /* Parse errors, or name resolution and call errors once the class parses */
+    fn diagnostics(self: &Self, uri: &str, path: &str) -> Json {
+        let text = self.documents.get(path).map_or("", |text| text.as_str());
+
+        let errors: Vec<(Span, String)> = match jack_to_vm::parse(text, path) {
+            Err(errors) => errors
+                .into_iter()
+                .map(|err| (err.span, format!("expected {}, found {}", err.expected, err.found)))
+                .collect(),
+            Ok(class) => {
+                let mut class_table = ClassTable::default();
+                self.index(path).values().for_each(|indexed| class_table.add_class(&indexed.class));
+                class_table.add_class(&class);
+
+                match jack_to_vm::check(&[class], &class_table, &jack_to_vm::Options::default()) {
+                    Ok(()) => vec![],
+                    Err(errors) => errors.into_iter().map(|err| (err.span, err.message)).collect(),
+                }
+            }
+        };
+
+        let diagnostics = errors
+            .into_iter()
+            .map(|(span, message)| {
+                Json::object(vec![
+                    ("range", range(&span, 1)),
+                    ("severity", 1.into()),
+                    ("source", "jack".into()),
+                    ("message", message.into()),
+                ])
+            })
+            .collect();
+
+        Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            (
+                "params",
+                Json::object(vec![("uri", uri.into()), ("diagnostics", Json::Array(diagnostics))]),
+            ),
+        ])
+    }
+
+    fn definition(self: &Self, path: &str, position: &Json) -> Json {
+        match self.resolve(path, position) {
+            Some(symbol) => Json::object(vec![
+                ("uri", path_to_uri(&symbol.path).into()),
+                ("range", range(&symbol.span, symbol.name.len())),
+            ]),
+            None => Json::Null,
+        }
+    }
+
+    fn hover(self: &Self, path: &str, position: &Json) -> Json {
+        match self.resolve(path, position) {
+            Some(symbol) => Json::object(vec![(
+                "contents",
+                Json::object(vec![
+                    ("kind", "markdown".into()),
+                    ("value", format!("```jack\n{}\n```", symbol.signature).into()),
+                ]),
+            )]),
+            None => Json::Null,
+        }
+    }
+
+    /* Subroutines of the class, or of the class of the variable, before the `.` being typed */
+    fn completion(self: &Self, path: &str, position: &Json) -> Json {
+        let (Some(line), Some(character)) =
+            (position.get("line").as_usize(), position.get("character").as_usize())
+        else {
+            return Json::Array(vec![]);
+        };
+        let text = self.documents.get(path).map_or("", |text| text.as_str());
+        let before: String =
+            text.lines().nth(line).unwrap_or_default().chars().take(character).collect();
+
+        /* `callee.prefix` right before the cursor */
+        let before = before.trim_end_matches(|c: char| c.is_ascii_alphanumeric() || c == '_');
+        let Some(before) = before.strip_suffix('.') else {
+            return Json::Array(vec![]);
+        };
+        let callee: String = before
+            .chars()
+            .rev()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect::<Vec<char>>()
+            .into_iter()
+            .rev()
+            .collect();
+
+        let index = self.index(path);
+        let (class_name, on_object) = match self.variable(path, &callee, line + 1) {
+            Some((_, JackType::Class(class_name), _)) => (class_name, true),
+            Some(_) => return Json::Array(vec![]),
+            None => (callee, false),
+        };
+        let Some(indexed) = index.get(&class_name) else {
+            return Json::Array(vec![]);
+        };
+
+        let items = indexed
+            .class
+            .routines
+            .iter()
+            .filter(|routine| on_object == (routine.routine_type == RoutineType::Method))
+            .map(|routine| {
+                Json::object(vec![
+                    ("label", routine.name.clone().into()),
+                    ("kind", (if on_object { 2 } else { 3 }).into()),
+                    ("detail", routine_signature(&class_name, routine).into()),
+                ])
+            })
+            .collect();
+
+        Json::Array(items)
+    }
+
+    fn document_symbols(self: &Self, path: &str) -> Json {
+        let Some(class) = self.parsed.get(path) else {
+            return Json::Array(vec![]);
+        };
+        let Some(indexed) = self.index(path).remove(&class.name) else {
+            return Json::Array(vec![]);
+        };
+
+        let vars = class.vars.iter().map(|var| {
+            let kind = match var.var_type {
+                ClassVarType::Field => 8,
+                ClassVarType::Static => 13,
+            };
+            document_symbol(&var.name, kind, var.jack_type.to_string(), &var.span, vec![])
+        });
+        let consts = class.consts.iter().map(|constant| {
+            let signature = format!("const {}", constant.jack_type);
+            document_symbol(&constant.name, 14, signature, &constant.span, vec![])
+        });
+        let routines = class.routines.iter().map(|routine| {
+            let kind = match routine.routine_type {
+                RoutineType::Constructor => 9,
+                RoutineType::Function => 12,
+                RoutineType::Method => 6,
+            };
+            let signature = routine_signature(&class.name, routine);
+            document_symbol(&routine.name, kind, signature, &routine.span, vec![])
+        });
+
+        Json::Array(vec![document_symbol(
+            &class.name,
+            5,
+            format!("class {}", class.name),
+            &indexed.name_span,
+            vars.chain(consts).chain(routines).collect(),
+        )])
+    }
+
+    /* Identifier under the cursor -> its declaration */
+    fn resolve(self: &Self, path: &str, position: &Json) -> Option<Symbol> {
+        let line = position.get("line").as_usize()? + 1;
+        let column = position.get("character").as_usize()? + 1;
+        let tokens = jack_to_vm::tokenize(self.documents.get(path)?, path).ok()?;
+
+        let i = tokens.iter().position(|token| {
+            matches!(&token.kind, TokenKind::Identifier(name)
+                if token.span.line == line
+                    && token.span.column <= column
+                    && column <= token.span.column + name.len())
+        })?;
+        let name = tokens[i].kind.to_string();
+        let index = self.index(path);
+        let current_class = &self.parsed.get(path)?.name;
+
+        /* callee.name */
+        if i >= 2 && is_symbol(&tokens[i - 1], ".") {
+            let callee = tokens[i - 2].kind.to_string();
+            let class_name = match self.variable(path, &callee, line) {
+                Some((_, JackType::Class(class_name), _)) => class_name,
+                Some(_) => return None,
+                None => callee,
+            };
+            /* `Enum.MEMBER` within the class declaring the enum */
+            let current = index.get(current_class)?;
+            if current.class.consts.iter().any(|constant| {
+                constant.name == name && constant.enum_name.as_ref() == Some(&class_name)
+            }) {
+                return const_symbol(current, &name);
+            }
+            let indexed = index.get(&class_name)?;
+            return routine_symbol(indexed, &name).or_else(|| const_symbol(indexed, &name));
+        }
+
+        /* name(...) */
+        if tokens.get(i + 1).is_some_and(|token| is_symbol(token, "(")) {
+            return routine_symbol(index.get(current_class)?, &name);
+        }
+
+        if let Some((kind, jack_type, span)) = self.variable(path, &name, line) {
+            let kind = match kind {
+                Kind::Local => "var",
+                Kind::Argument => "argument",
+                Kind::Field => "field",
+                Kind::Static => "static",
+                Kind::Constant => "const",
+            };
+            return Some(Symbol {
+                signature: format!("{kind} {jack_type} {name}"),
+                path: path.to_string(),
+                span,
+                name,
+            });
+        }
+
+        if let Some(symbol) = index.get(current_class).and_then(|c| const_symbol(c, &name)) {
+            return Some(symbol);
+        }
+
+        let indexed = index.get(&name)?;
+        Some(Symbol {
+            signature: format!("class {name}"),
+            path: indexed.path.clone(),
+            span: indexed.name_span.clone(),
+            name,
+        })
+    }
+
+    /* Variable in scope at `line`: locals and parameters of the enclosing subroutine, then class variables.
+     * Locals of blocks are taken to be the last ones of their name declared before the line */
+    fn variable(
+        self: &Self,
+        path: &str,
+        name: &str,
+        line: usize,
+    ) -> Option<(Kind, JackType, Span)> {
+        let class = self.parsed.get(path)?;
+        let routine = class.routines.iter().filter(|routine| routine.span.line <= line).next_back();
+
+        if let Some(routine) = routine
+            && let Some(entry) = RoutineSymbols::new(routine, &class.name).entries.remove(name)
+        {
+            let params = routine.parameters.iter().map(|param| (&param.name, &param.span));
+            let locals = routine.body.vars.iter().map(|var| (&var.name, &var.span));
+            /* `this` is declared by the method itself */
+            let span = params
+                .chain(locals)
+                .find(|(var_name, _)| *var_name == name)
+                .map_or(routine.span.clone(), |(_, span)| span.clone());
+
+            return Some((entry.kind, entry.jtype, span));
+        }
+
+        if let Some(routine) = routine
+            && let Some(var) = routine
+                .body
+                .all_vars()
+                .into_iter()
+                .filter(|var| var.name == name && var.span.line <= line)
+                .next_back()
+        {
+            return Some((Kind::Local, var.jack_type.clone(), var.span.clone()));
+        }
+
+        let entry = ClassSymbols::new(&class.vars).entries.remove(name)?;
+        let var = class.vars.iter().find(|var| var.name == name)?;
+        Some((entry.kind, entry.jtype, var.span.clone()))
+    }
+
+    /* Classes of the OS and of the directory of the document, by name */
+    fn index(self: &Self, path: &str) -> HashMap<String, IndexedClass> {
+        let mut index: HashMap<String, IndexedClass> = HashMap::new();
+        let program_dir = Path::new(path).parent().map(Path::to_path_buf).unwrap_or_default();
+
+        let mut files: Vec<String> = vec![];
+        for dir in [&self.os_path, &program_dir] {
+            if let Ok(entries) = dir.read_dir() {
+                let paths = entries.flatten().map(|entry| entry.path());
+                files.extend(
+                    paths
+                        .filter(|path| path.extension().is_some_and(|ext| ext == "jack"))
+                        .map(|path| path.to_string_lossy().into_owned()),
+                );
+            }
+        }
+        /* open documents are indexed even when not saved to disk yet */
+        files.extend(
+            self.documents
+                .keys()
+                .filter(|file| Path::new(file).parent() == Some(&program_dir))
+                .filter(|file| !files.contains(file))
+                .cloned()
+                .collect::<Vec<String>>(),
+        );
+
+        for file in files {
+            let text = match self.documents.get(&file) {
+                Some(text) => text.clone(),
+                None => fs::read_to_string(&file).unwrap_or_default(),
+            };
+            if let Some(indexed) = index_class(&file, &text, self.parsed.get(&file)) {
+                index.insert(indexed.class.name.clone(), indexed);
+            }
+        }
+
+        index
+    }
+}
+
+fn index_class(path: &str, text: &str, last_parsed: Option<&Class>) -> Option<IndexedClass> {
+    let tokens: Vec<Token> = jack_to_vm::tokenize(text, path).ok()?;
+    /* the class name follows the `class` keyword, which a precedence pragma may precede */
+    let class_keyword = tokens.iter().position(|token| token.kind.to_string() == "class")?;
+    let name_span = tokens.get(class_keyword + 1)?.span.clone();
+    /* a document being edited resolves against its last valid version */
+    let mut class = jack_to_vm::parse(text, path).ok().or_else(|| last_parsed.cloned())?;
+
+    /* the default constructor is declared by the class name */
+    if class.has_default_constructor() {
+        class.routines.push(SubroutineDec {
+            routine_type: RoutineType::Constructor,
+            return_type: ReturnType::Type(JackType::Class(class.name.clone())),
+            name: "new".into(),
+            parameters: vec![],
+            body: SubroutineBody::default(),
+            span: name_span.clone(),
+            doc: None,
+        });
+    }
+
+    Some(IndexedClass { path: path.to_string(), name_span, class })
+}
+
+fn routine_symbol(indexed: &IndexedClass, name: &str) -> Option<Symbol> {
+    let routine = indexed.class.routines.iter().find(|routine| routine.name == name)?;
+
+    Some(Symbol {
+        signature: routine_signature(&indexed.class.name, routine),
+        path: indexed.path.clone(),
+        span: routine.span.clone(),
+        name: name.to_string(),
+    })
+}
+
+fn const_symbol(indexed: &IndexedClass, name: &str) -> Option<Symbol> {
+    let constant = indexed.class.consts.iter().find(|constant| constant.name == name)?;
+
+    Some(Symbol {
+        signature: format!("const {} {}.{name}", constant.jack_type, indexed.class.name),
+        path: indexed.path.clone(),
+        span: constant.span.clone(),
+        name: name.to_string(),
+    })
+}
+
+fn routine_signature(class_name: &str, routine: &SubroutineDec) -> String {
+    let params: Vec<String> = routine
+        .parameters
+        .iter()
+        .map(|param| format!("{} {}", param.jack_type, param.name))
+        .collect();
+
+    format!(
+        "{} {} {class_name}.{}({})",
+        routine.routine_type,
+        routine.return_type,
+        routine.name,
+        params.join(", ")
+    )
+}
+
+fn is_symbol(token: &Token, symbol: &str) -> bool {
+    matches!(&token.kind, TokenKind::Symbol(val) if val == symbol)
+}
+
+fn capabilities() -> Json {
+    Json::object(vec![
+        (
+            "capabilities",
+            Json::object(vec![
+                (
+                    "textDocumentSync",
+                    Json::object(vec![
+                        ("openClose", true.into()),
+                        ("change", 1.into()),
+                        ("save", Json::object(vec![("includeText", true.into())])),
+                    ]),
+                ),
+                ("definitionProvider", true.into()),
+                ("hoverProvider", true.into()),
+                (
+                    "completionProvider",
+                    Json::object(vec![("triggerCharacters", vec![".".into()].into())]),
+                ),
+                ("documentSymbolProvider", true.into()),
+            ]),
+        ),
+        ("serverInfo", Json::object(vec![("name", "jack-lsp".into())])),
+    ])
+}
+
+fn document_symbol(
+    name: &str,
+    kind: usize,
+    detail: String,
+    span: &Span,
+    children: Vec<Json>,
+) -> Json {
+    Json::object(vec![
+        ("name", name.into()),
+        ("kind", kind.into()),
+        ("detail", detail.into()),
+        ("range", range(span, name.len())),
+        ("selectionRange", range(span, name.len())),
+        ("children", Json::Array(children)),
+    ])
+}
+
+/* Spans start at line 1 column 1, lsp positions at line 0 character 0 */
+fn range(span: &Span, length: usize) -> Json {
+    let line = span.line.saturating_sub(1);
+    let character = span.column.saturating_sub(1);
+
+    Json::object(vec![
+        ("start", Json::object(vec![("line", line.into()), ("character", character.into())])),
+        (
+            "end",
+            Json::object(vec![("line", line.into()), ("character", (character + length).into())]),
+        ),
+    ])
+}
+
+fn uri_to_path(uri: &str) -> String {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut decoded: Vec<u8> = vec![];
+    let bytes = path.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match (bytes[i], path.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn path_to_uri(path: &str) -> String {
+    format!("file://{}", path.replace('%', "%25").replace(' ', "%20"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN: &str = "class Main {
+    function void main() {
+        var Point p;
+        let p = Point.new(1);
+        do p.draw();
+        do Output.printInt(Math.max(p.x(), 2));
+        return;
+    }
+}";
+
+    const POINT: &str = "class Point {
+    field int x;
+    constructor Point new(int ax) { let x = ax; return this; }
+    method int x() { return x; }
+    method void draw() { return; }
+}";
+
+    fn server() -> Server {
+        let mut server = Server::new(PathBuf::from(crate::JACK_OS_PATH));
+        open(&mut server, "/program/Point.jack", POINT);
+        server
+    }
+
+    fn open(server: &mut Server, path: &str, text: &str) -> Vec<Json> {
+        let text_document =
+            Json::object(vec![("uri", path_to_uri(path).into()), ("text", text.into())]);
+        server.handle(&Json::object(vec![
+            ("method", "textDocument/didOpen".into()),
+            ("params", Json::object(vec![("textDocument", text_document)])),
+        ]))
+    }
+
+    fn request(
+        server: &mut Server,
+        method: &str,
+        path: &str,
+        line: usize,
+        character: usize,
+    ) -> Json {
+        let position = Json::object(vec![("line", line.into()), ("character", character.into())]);
+        let params = Json::object(vec![
+            ("textDocument", Json::object(vec![("uri", path_to_uri(path).into())])),
+            ("position", position),
+        ]);
+        let mut responses = server.handle(&Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("id", 1.into()),
+            ("method", method.into()),
+            ("params", params),
+        ]));
+
+        responses.remove(0).get("result").clone()
+    }
+
+    #[test]
+    fn initialize_and_exit() {
+        let mut server = server();
+        let initialize =
+            Json::parse(r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}"#);
+        let responses = server.handle(&initialize.unwrap());
+
+        assert_eq!(
+            &Json::Bool(true),
+            responses[0].get("result").get("capabilities").get("definitionProvider")
+        );
+
+        let unknown = Json::parse(r#"{"jsonrpc":"2.0","id":1,"method":"workspace/symbol"}"#);
+        let responses = server.handle(&unknown.unwrap());
+        assert_eq!(&Json::Number(-32601.0), responses[0].get("error").get("code"));
+
+        server.handle(&Json::parse(r#"{"jsonrpc":"2.0","method":"exit"}"#).unwrap());
+        assert!(server.exited);
+    }
+
+    #[test]
+    fn publish_diagnostics_on_open_and_save() {
+        let mut server = server();
+        let diagnostics = open(&mut server, "/program/Main.jack", "class Main {\n  field int;\n}");
+
+        assert_eq!(
+            r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///program/Main.jack","diagnostics":[{"range":{"start":{"line":1,"character":11},"end":{"line":1,"character":12}},"severity":1,"source":"jack","message":"expected a variable name, found `;`"}]}}"#,
+            diagnostics[0].to_string()
+        );
+
+        let save = Json::object(vec![
+            ("method", "textDocument/didSave".into()),
+            (
+                "params",
+                Json::object(vec![
+                    (
+                        "textDocument",
+                        Json::object(vec![("uri", "file:///program/Main.jack".into())]),
+                    ),
+                    ("text", MAIN.replace("Math.max", "Math.maximum").into()),
+                ]),
+            ),
+        ]);
+        let diagnostics = server.handle(&save);
+        let messages: Vec<&str> = diagnostics[0]
+            .get("params")
+            .get("diagnostics")
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|diagnostic| diagnostic.get("message").as_str().unwrap())
+            .collect();
+
+        assert_eq!(vec!["class Math has no subroutine `maximum`"], messages);
+    }
+
+    #[test]
+    fn go_to_definitions_and_hover_types() {
+        let mut server = server();
+        open(&mut server, "/program/Main.jack", MAIN);
+
+        /* `p` in `do p.draw();` */
+        let definition =
+            request(&mut server, "textDocument/definition", "/program/Main.jack", 4, 11);
+        assert_eq!(
+            r#"{"uri":"file:///program/Main.jack","range":{"start":{"line":2,"character":18},"end":{"line":2,"character":19}}}"#,
+            definition.to_string()
+        );
+        let hover = request(&mut server, "textDocument/hover", "/program/Main.jack", 4, 11);
+        assert_eq!(Some("```jack\nvar Point p\n```"), hover.get("contents").get("value").as_str());
+
+        /* `draw` in `do p.draw();` */
+        let definition =
+            request(&mut server, "textDocument/definition", "/program/Main.jack", 4, 14);
+        assert_eq!(Some("file:///program/Point.jack"), definition.get("uri").as_str());
+        assert_eq!(Some(4), definition.get("range").get("start").get("line").as_usize());
+
+        /* `max` in `Math.max(...)` */
+        let hover = request(&mut server, "textDocument/hover", "/program/Main.jack", 5, 32);
+        assert_eq!(
+            Some("```jack\nfunction int Math.max(int x, int y)\n```"),
+            hover.get("contents").get("value").as_str()
+        );
+
+        /* `Point` in `Point.new(1)` */
+        let hover = request(&mut server, "textDocument/hover", "/program/Main.jack", 3, 17);
+        assert_eq!(Some("```jack\nclass Point\n```"), hover.get("contents").get("value").as_str());
+
+        let field = request(&mut server, "textDocument/hover", "/program/Point.jack", 3, 29);
+        assert_eq!(Some("```jack\nfield int x\n```"), field.get("contents").get("value").as_str());
+    }
+
+    #[test]
+    fn complete_class_members_while_typing() {
+        let mut server = server();
+        open(&mut server, "/program/Main.jack", MAIN);
+        let typing = MAIN.replace("do p.draw();", "do p.\n        let p = Point.");
+        let change = Json::object(vec![
+            ("method", "textDocument/didChange".into()),
+            (
+                "params",
+                Json::object(vec![
+                    (
+                        "textDocument",
+                        Json::object(vec![("uri", "file:///program/Main.jack".into())]),
+                    ),
+                    ("contentChanges", vec![Json::object(vec![("text", typing.into())])].into()),
+                ]),
+            ),
+        ]);
+        server.handle(&change);
+
+        let labels = |completion: Json| -> Vec<String> {
+            completion
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item.get("label").as_str().unwrap().to_string())
+                .collect()
+        };
+
+        /* methods on an object, the class being resolved through the last valid parse */
+        let methods = request(&mut server, "textDocument/completion", "/program/Main.jack", 4, 13);
+        assert_eq!(vec!["x", "draw"], labels(methods));
+
+        /* functions and constructors on a class */
+        let functions =
+            request(&mut server, "textDocument/completion", "/program/Main.jack", 5, 22);
+        assert_eq!(vec!["new"], labels(functions));
+    }
+
+    #[test]
+    fn complete_and_hover_default_constructors() {
+        let mut server = server();
+        let size = "class Size {\n    field int width = 1, height = 2;\n}";
+        open(&mut server, "/program/Size.jack", size);
+        let main = "class Main {\n    function void main() {\n        do Size.new();\n    }\n}";
+        open(&mut server, "/program/Main.jack", main);
+
+        let completion =
+            request(&mut server, "textDocument/completion", "/program/Main.jack", 2, 16);
+        let item = &completion.as_array().unwrap()[0];
+        assert_eq!(Some("new"), item.get("label").as_str());
+        assert_eq!(Some("constructor Size Size.new()"), item.get("detail").as_str());
+
+        let hover = request(&mut server, "textDocument/hover", "/program/Main.jack", 2, 17);
+        assert_eq!(
+            Some("```jack\nconstructor Size Size.new()\n```"),
+            hover.get("contents").get("value").as_str()
+        );
+        let definition =
+            request(&mut server, "textDocument/definition", "/program/Main.jack", 2, 17);
+        assert_eq!(Some("file:///program/Size.jack"), definition.get("uri").as_str());
+        assert_eq!(Some(0), definition.get("range").get("start").get("line").as_usize());
+    }
+
+    #[test]
+    fn hover_enum_members_qualified_by_their_enum() {
+        let mut server = server();
+        let main = "class Main {\n    enum Dir { LEFT, RIGHT }\n    function int main() { return Dir.RIGHT; }\n}";
+        open(&mut server, "/program/Main.jack", main);
+
+        let hover = request(&mut server, "textDocument/hover", "/program/Main.jack", 2, 38);
+        assert_eq!(
+            Some("```jack\nconst int Main.RIGHT\n```"),
+            hover.get("contents").get("value").as_str()
+        );
+    }
+
+    #[test]
+    fn index_classes_as_they_compile() {
+        let mut server = server();
+        let shape = "// pragma precedence
+class Shape {
+    /** Sides of a square */
+    function int sides() { return 2 + 2 * 1; }
+}";
+        open(&mut server, "/program/Shape.jack", shape);
+
+        let index = server.index("/program/Shape.jack");
+        let indexed = &index["Shape"];
+        assert_eq!((2, 7), (indexed.name_span.line, indexed.name_span.column));
+        assert_eq!(Ok(indexed.class.clone()), jack_to_vm::parse(shape, "/program/Shape.jack"));
+        assert_eq!(Some("Sides of a square".into()), indexed.class.routines[0].doc);
+    }
+
+    #[test]
+    fn list_document_symbols() {
+        let mut server = server();
+        let symbols =
+            request(&mut server, "textDocument/documentSymbol", "/program/Point.jack", 0, 0);
+        let class = &symbols.as_array().unwrap()[0];
+        let children: Vec<(&str, usize)> = class
+            .get("children")
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| {
+                (symbol.get("name").as_str().unwrap(), symbol.get("kind").as_usize().unwrap())
+            })
+            .collect();
+
+        assert_eq!(Some("Point"), class.get("name").as_str());
+        assert_eq!(vec![("x", 8), ("new", 9), ("x", 6), ("draw", 6)], children);
+    }
+}
@@ -1,11 +1,13 @@
 use std::{env, path::PathBuf};
 
 fn main() {
-    let path = if env::current_dir().unwrap().ends_with("jack-to-hack") {
-        "jack-to-vm/examples/program"
+    let (path, os_path) = if env::current_dir().unwrap().ends_with("jack-to-hack") {
+        ("jack-to-vm/examples/program", "compiler/jack-os")
     } else {
-        "examples/program"
+        ("examples/program", "../compiler/jack-os")
     };
 
-    jack_to_vm::compile(&PathBuf::from(path));
+    let options =
+        jack_to_vm::Options { libraries: vec![PathBuf::from(os_path)], ..Default::default() };
+    jack_to_vm::compile(&PathBuf::from(path), &options).expect("Parsing jack classes failed");
 }
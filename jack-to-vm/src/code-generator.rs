@@ -1,55 +1,87 @@
 #[path = "code-generator/symbols.rs"]
-mod symbols;
+pub mod symbols;
 
+use super::Options;
 use super::grammar::*;
+use super::syntax_analyzer::tokenizer::hack_code;
 use symbols::*;
 
-pub fn compile(class: Class) -> Vec<String> {
-    let mut code_generator = CodeGenerator::new(&class);
+/// Name of the function generated for the static initializers of a class, `Main.$clinit`,
+/// which cannot clash with a Jack subroutine name
+pub const STATIC_INITIALIZER: &str = "$clinit";
+
+/* Sys.error codes reported by the debug instrumentation */
+const NULL_DEREFERENCE_ERROR: u16 = 101;
+const DIVISION_BY_ZERO_ERROR: u16 = 102;
+
+/* The OS class addressing the whole RAM through an array based at 0 on purpose */
+const MEMORY_CLASS: &str = "Memory";
+
+pub fn compile(class: Class, class_table: &ClassTable, options: &Options) -> Vec<String> {
+    let mut code_generator = CodeGenerator::new(&class, class_table, options);
     code_generator.compile();
     code_generator.vm
 }
 
 struct CodeGenerator<'a> {
     class: &'a Class,
+    class_table: &'a ClassTable,
     class_symbols: ClassSymbols,
     routine_symbols: Option<RoutineSymbols>,
     label_counter: u16,
+    /* (continue, break) labels of the enclosing loops and switches, innermost last:
+     * a switch only defines where to break, continuing its enclosing loop */
+    loops: Vec<(Option<String>, String)>,
+    debug: bool,
     vm: Vec<String>,
 }
 
 impl<'a> CodeGenerator<'a> {
-    fn new(class: &'a Class) -> Self {
+    fn new(class: &'a Class, class_table: &'a ClassTable, options: &Options) -> Self {
+        let mut class_symbols = ClassSymbols::new(&class.vars);
+        class_symbols.add_constants(&class.name, class_table);
+
         Self {
             class: class,
-            class_symbols: ClassSymbols::new(&class.vars),
+            class_table,
+            class_symbols,
             routine_symbols: None,
             label_counter: 0,
+            loops: vec![],
+            debug: options.debug,
             vm: vec![],
         }
     }
 
     fn compile(self: &mut Self) {
         self.class.routines.iter().for_each(|routine| self.compile_routine(routine));
+
+        if self.class.has_default_constructor() {
+            self.compile_default_constructor();
+        }
+        if self
+            .class
+            .vars
+            .iter()
+            .any(|var| var.var_type == ClassVarType::Static && var.exp.is_some())
+        {
+            self.compile_static_initializers();
+        }
     }
 
     fn compile_routine(self: &mut Self, routine: &SubroutineDec) {
+        let routine_symbols = RoutineSymbols::new(routine, &self.class.name);
         self.vm.push(format!(
             "function {}.{} {}",
-            self.class.name,
-            routine.name,
-            routine.body.vars.len()
+            self.class.name, routine.name, routine_symbols.local_count
         ));
 
         self.label_counter = 0;
-        self.routine_symbols = Some(RoutineSymbols::new(routine, &self.class.name));
+        /* initializers only see class variables, whatever the parameters are named */
+        self.routine_symbols = None;
 
         match routine.routine_type {
-            RoutineType::Constructor => {
-                self.vm.push(format!("push constant {}", self.class_symbols.field_counter));
-                self.vm.push("call Memory.alloc 1".into());
-                self.vm.push("pop pointer 0".into());
-            }
+            RoutineType::Constructor => self.compile_constructor_prologue(),
             RoutineType::Method => {
                 self.vm.push("push argument 0".into());
                 self.vm.push("pop pointer 0".into());
@@ -57,18 +89,76 @@ impl<'a> CodeGenerator<'a> {
             RoutineType::Function => {}
         }
 
+        self.routine_symbols = Some(routine_symbols);
         routine.body.statements.iter().for_each(|statement| self.compile_statement(statement));
     }
 
+    /* Allocates the object, then sets its fields from their initializers in declaration order */
+    fn compile_constructor_prologue(self: &mut Self) {
+        self.vm.push(format!("push constant {}", self.class_symbols.field_counter));
+        self.vm.push("call Memory.alloc 1".into());
+        self.vm.push("pop pointer 0".into());
+
+        self.compile_initializers(ClassVarType::Field);
+    }
+
+    /* `constructor C new() { return this; }` */
+    fn compile_default_constructor(self: &mut Self) {
+        self.vm.push(format!("function {}.new 0", self.class.name));
+        self.label_counter = 0;
+        self.routine_symbols = None;
+
+        self.compile_constructor_prologue();
+        self.vm.push("push pointer 0".into());
+        self.vm.push("return".into());
+    }
+
+    /* Called once before `Main.main` runs, see STATIC_INITIALIZER */
+    fn compile_static_initializers(self: &mut Self) {
+        self.vm.push(format!("function {}.{STATIC_INITIALIZER} 0", self.class.name));
+        self.label_counter = 0;
+        self.routine_symbols = None;
+
+        self.compile_initializers(ClassVarType::Static);
+        self.vm.push("push constant 0".into());
+        self.vm.push("return".into());
+    }
+
+    fn compile_initializers(self: &mut Self, var_type: ClassVarType) {
+        let class = self.class;
+        class.vars.iter().filter(|var| var.var_type == var_type).for_each(|var| {
+            if let Some(exp) = &var.exp {
+                self.compile_expression(exp);
+                let entry = self.fetch_symbol_entry(&var.name).unwrap();
+                self.vm.push(format!("pop {} {}", entry.kind.vm(), entry.index));
+            }
+        });
+    }
+
     fn compile_statement(self: &mut Self, statement: &Statement) {
         match statement {
-            Statement::Let { var_name, array_access, exp } => match array_access {
+            /* slots may have been used by a previous block, or a previous loop iteration */
+            Statement::Var(vars) => {
+                let symbols = self.routine_symbols.as_mut().unwrap();
+                symbols.add_vars(vars);
+                let indexes: Vec<u16> =
+                    vars.iter().map(|var| symbols.entries[&var.name].index).collect();
+                indexes.into_iter().for_each(|index| {
+                    self.vm.push("push constant 0".into());
+                    self.vm.push(format!("pop local {index}"));
+                });
+            }
+            Statement::Let { var_name, array_access, exp, .. } => match array_access {
                 None => {
                     self.compile_expression(exp);
                     let entry = self.fetch_symbol_entry(var_name).unwrap();
                     self.vm.push(format!("pop {} {}", entry.kind.vm(), entry.index));
                 }
                 Some(index_exp) => {
+                    if self.debug {
+                        self.check_null_dereference(var_name);
+                    }
+
                     let entry = self.fetch_symbol_entry(var_name).unwrap();
                     self.vm.push(format!("push {} {}", entry.kind.vm(), entry.index));
                     self.compile_expression(index_exp);
@@ -80,48 +170,163 @@ impl<'a> CodeGenerator<'a> {
                     self.vm.push("pop that 0".into());
                 }
             },
-            Statement::If { exp, statements, else_statements } => {
+            /* `x++` adds 1, the operand being the constant */
+            Statement::Update { var_name, array_access, op, exp, .. } => {
+                let operand = |generator: &mut Self| match exp {
+                    Some(exp) => generator.compile_expression(exp),
+                    None => generator.vm.push("push constant 1".into()),
+                };
+                let entry = self.fetch_symbol_entry(var_name).unwrap();
+                let variable = format!("{} {}", entry.kind.vm(), entry.index);
+
+                match array_access {
+                    None => {
+                        self.vm.push(format!("push {variable}"));
+                        operand(self);
+                        self.compile_operation(op);
+                        self.vm.push(format!("pop {variable}"));
+                    }
+                    /* the element address stays on the stack below its value: popping it to
+                     * `pointer 1` and pushing it back reads the element, whatever the operand
+                     * then does with `that` */
+                    Some(index_exp) => {
+                        if self.debug {
+                            self.check_null_dereference(var_name);
+                        }
+
+                        self.vm.push(format!("push {variable}"));
+                        self.compile_expression(index_exp);
+                        self.vm.push("add".into());
+                        self.vm.push("pop pointer 1".into());
+                        self.vm.push("push pointer 1".into());
+                        self.vm.push("push that 0".into());
+                        operand(self);
+                        self.compile_operation(op);
+                        self.vm.push("pop temp 0".into());
+                        self.vm.push("pop pointer 1".into());
+                        self.vm.push("push temp 0".into());
+                        self.vm.push("pop that 0".into());
+                    }
+                }
+            }
+            Statement::If { exp, statements, else_statements, .. } => {
                 let counter = self.label_counter;
                 self.label_counter += 1;
 
-                self.compile_expression(exp);
-                self.vm.push(format!("if-goto IfTrue${}", counter));
+                self.compile_branch(exp, true, &format!("IfTrue${counter}"));
 
                 /* handle the optional FALSE branch */
                 if let Some(else_statements) = else_statements {
-                    else_statements.iter().for_each(|s| self.compile_statement(s));
+                    self.compile_block(else_statements);
                 }
                 self.vm.push(format!("goto IfDone${}", counter));
 
                 /* handle TRUE branch */
                 self.vm.push(format!("label IfTrue${}", counter));
-                statements.iter().for_each(|s| self.compile_statement(s));
+                self.compile_block(statements);
                 self.vm.push(format!("label IfDone${}", counter));
             }
             /* TODO: optimize branch handling setup ? */
-            Statement::While { exp, statements } => {
+            Statement::While { exp, statements, .. } => {
                 let counter = self.label_counter;
                 self.label_counter += 1;
 
                 /* define condition */
                 self.vm.push(format!("label WhileCondition${}", counter));
-                self.compile_expression(exp);
 
                 /* execute the loop statements or break out of it */
-                self.vm.push(format!("if-goto WhileStatements${}", counter));
+                self.compile_branch(exp, true, &format!("WhileStatements${counter}"));
                 self.vm.push(format!("goto WhileDone${}", counter));
                 self.vm.push(format!("label WhileStatements${}", counter));
-                statements.iter().for_each(|statement| self.compile_statement(statement));
+                self.compile_breakable(
+                    statements,
+                    Some(format!("WhileCondition${counter}")),
+                    format!("WhileDone${counter}"),
+                );
                 self.vm.push(format!("goto WhileCondition${}", counter));
 
                 /* resume execution after the while statement is complete */
                 self.vm.push(format!("label WhileDone${}", counter));
             }
+            Statement::For { init, exp, step, statements, .. } => {
+                let counter = self.label_counter;
+                self.label_counter += 1;
+
+                if let Some(init) = init {
+                    self.compile_statement(init);
+                }
+
+                /* same as a while loop, continue going through the step first */
+                self.vm.push(format!("label ForCondition${}", counter));
+                if let Some(exp) = exp {
+                    self.compile_branch(exp, true, &format!("ForStatements${counter}"));
+                    self.vm.push(format!("goto ForDone${}", counter));
+                    self.vm.push(format!("label ForStatements${}", counter));
+                }
+                self.compile_breakable(
+                    statements,
+                    Some(format!("ForStep${counter}")),
+                    format!("ForDone${counter}"),
+                );
+
+                self.vm.push(format!("label ForStep${}", counter));
+                if let Some(step) = step {
+                    self.compile_statement(step);
+                }
+                self.vm.push(format!("goto ForCondition${}", counter));
+                self.vm.push(format!("label ForDone${}", counter));
+            }
+            Statement::Switch { exp, cases, default, .. } => {
+                let counter = self.label_counter;
+                self.label_counter += 1;
+
+                let done = format!("SwitchDone${counter}");
+                let otherwise = match default {
+                    Some(_) => format!("SwitchDefault${counter}"),
+                    None => done.clone(),
+                };
+                /* (value, case index) pairs */
+                let generator: &Self = self;
+                let values: Vec<(i16, usize)> = cases
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(k, case)| {
+                        case.values.iter().map(move |value| (generator.constant_value(value), k))
+                    })
+                    .collect();
+
+                self.compile_expression(exp);
+                self.vm.push("pop temp 2".into());
+                self.compile_switch_dispatch(&values, &otherwise, counter);
+
+                /* cases do not fall through */
+                let continue_label = self.loops.last().and_then(|(label, _)| label.clone());
+                cases.iter().enumerate().for_each(|(k, case)| {
+                    self.vm.push(format!("label SwitchCase{k}${counter}"));
+                    self.compile_breakable(&case.statements, continue_label.clone(), done.clone());
+                    self.vm.push(format!("goto {done}"));
+                });
+                if let Some(default) = default {
+                    self.vm.push(format!("label {otherwise}"));
+                    self.compile_breakable(default, continue_label, done.clone());
+                }
+
+                self.vm.push(format!("label {done}"));
+            }
+            /* the semantic analysis rejects both outside of loops and switches */
+            Statement::Break(_) => {
+                let (_, break_label) = self.loops.last().unwrap();
+                self.vm.push(format!("goto {break_label}"));
+            }
+            Statement::Continue(_) => {
+                let (continue_label, _) = self.loops.last().unwrap();
+                self.vm.push(format!("goto {}", continue_label.as_ref().unwrap()));
+            }
             Statement::Do(call) => {
                 self.compile_routine_call(call);
                 self.vm.push("pop temp 0".into());
             }
-            Statement::Return(exp_opt) => match exp_opt {
+            Statement::Return(exp_opt, _) => match exp_opt {
                 None => {
                     self.vm.push("push constant 0".into());
                     self.vm.push("return".into());
@@ -134,39 +339,317 @@ impl<'a> CodeGenerator<'a> {
         }
     }
 
-    fn compile_expression(self: &mut Self, exp: &Expression) {
-        self.compile_term(&exp.term);
-
-        exp.additional.iter().for_each(|(op, term)| {
-            self.compile_term(term);
-
-            match op {
-                Operation::Plus => self.vm.push("add".into()),
-                Operation::Minus => self.vm.push("sub".into()),
-                Operation::GreaterThan => self.vm.push("gt".into()),
-                Operation::LessThan => self.vm.push("lt".into()),
-                Operation::Equals => self.vm.push("eq".into()),
-                Operation::And => self.vm.push("and".into()),
-                Operation::Or => self.vm.push("or".into()),
-                Operation::Not => self.vm.push("neg".into()),
-                Operation::Multiply => self.vm.push("call Math.multiply 2".into()),
-                Operation::Divide => self.vm.push("call Math.divide 2".into()),
-            };
+    fn compile_breakable(
+        self: &mut Self,
+        statements: &[Statement],
+        continue_label: Option<String>,
+        break_label: String,
+    ) {
+        self.loops.push((continue_label, break_label));
+        self.compile_block(statements);
+        self.loops.pop();
+    }
+
+    /* Locals declared within the block give their slots back when leaving it */
+    fn compile_block(self: &mut Self, statements: &[Statement]) {
+        self.routine_symbols.as_mut().unwrap().enter_block();
+        statements.iter().for_each(|statement| self.compile_statement(statement));
+        self.routine_symbols.as_mut().unwrap().exit_block();
+    }
+
+    /*
+     * Jumps to the case of the value held by temp 2, or to `otherwise`.
+     * The vm language has no indirect jump: dense values are dispatched by testing the bits
+     * of their offset from the smallest value, down to its slot in a table of cases,
+     * while sparse ones are compared one by one.
+     */
+    fn compile_switch_dispatch(
+        self: &mut Self,
+        values: &[(i16, usize)],
+        otherwise: &str,
+        counter: u16,
+    ) {
+        let min = values.iter().map(|(value, _)| *value as i32).min().unwrap_or(0);
+        let max = values.iter().map(|(value, _)| *value as i32).max().unwrap_or(0);
+        let range = (max - min + 1) as usize;
+
+        if values.len() < 4 || range > 2 * values.len() {
+            values.iter().for_each(|(value, k)| {
+                self.vm.push("push temp 2".into());
+                self.push_constant(*value as u16);
+                self.vm.push("eq".into());
+                self.vm.push(format!("if-goto SwitchCase{k}${counter}"));
+            });
+            self.vm.push(format!("goto {otherwise}"));
+            return;
+        }
+
+        let mut slots = vec![otherwise.to_string(); range];
+        values.iter().for_each(|(value, k)| {
+            slots[(*value as i32 - min) as usize] = format!("SwitchCase{k}${counter}");
         });
+
+        self.vm.push("push temp 2".into());
+        self.push_constant(min as u16);
+        self.vm.push("sub".into());
+        self.vm.push("pop temp 2".into());
+
+        self.vm.push("push temp 2".into());
+        self.vm.push("push constant 0".into());
+        self.vm.push("lt".into());
+        self.vm.push(format!("if-goto {otherwise}"));
+        self.vm.push("push temp 2".into());
+        self.vm.push(format!("push constant {}", range - 1));
+        self.vm.push("gt".into());
+        self.vm.push(format!("if-goto {otherwise}"));
+
+        self.compile_jump_table(&slots, 0, range.next_power_of_two(), counter);
+    }
+
+    /* Slots `start..start + size` of the table, split on the bit of `size / 2` of the offset */
+    fn compile_jump_table(
+        self: &mut Self,
+        slots: &[String],
+        start: usize,
+        size: usize,
+        counter: u16,
+    ) {
+        let end = slots.len().min(start + size);
+        if slots[start..end].iter().all(|slot| *slot == slots[start]) {
+            self.vm.push(format!("goto {}", slots[start]));
+            return;
+        }
+
+        let half = size / 2;
+        if start + half >= slots.len() {
+            return self.compile_jump_table(slots, start, half, counter);
+        }
+
+        let upper = format!("SwitchSlot{}_{half}${counter}", start + half);
+        self.vm.push("push temp 2".into());
+        self.vm.push(format!("push constant {half}"));
+        self.vm.push("and".into());
+        self.vm.push(format!("if-goto {upper}"));
+        self.compile_jump_table(slots, start, half, counter);
+        self.vm.push(format!("label {upper}"));
+        self.compile_jump_table(slots, start + half, half, counter);
+    }
+
+    /* `push constant` only takes 0..32767: other 16 bit words are pushed inverted, then `not` */
+    fn push_constant(self: &mut Self, word: u16) {
+        if word > 0x7FFF {
+            self.vm.push(format!("push constant {}", 0xFFFF - word));
+            self.vm.push("not".into());
+        } else {
+            self.vm.push(format!("push constant {}", word));
+        }
+    }
+
+    fn compile_expression(self: &mut Self, exp: &Expression) {
+        self.compile_operations(&exp.term, &exp.additional);
+    }
+
+    fn compile_operations(self: &mut Self, term: &Term, additional: &[(Operation, Term)]) {
+        self.compile_term(term);
+
+        additional.iter().for_each(|(op, term)| self.compile_operand(op, term));
+    }
+
+    /* The left operand sits on top of the stack */
+    fn compile_operand(self: &mut Self, op: &Operation, term: &Term) {
+        match op {
+            Operation::LogicalAnd | Operation::LogicalOr => self.compile_short_circuit(op, term),
+            op => {
+                self.compile_term(term);
+                self.compile_operation(op);
+            }
+        }
+    }
+
+    /* The left operand sits on top of the stack, the right one is evaluated only when it
+     * decides the result: the result is -1 or 0 whatever the operands, so `~` still negates */
+    fn compile_short_circuit(self: &mut Self, op: &Operation, term: &Term) {
+        let counter = self.label_counter;
+        self.label_counter += 1;
+
+        let is_or = *op == Operation::LogicalOr;
+        let (decided, done) = match is_or {
+            true => (format!("OrTrue${counter}"), format!("OrDone${counter}")),
+            false => (format!("AndFalse${counter}"), format!("AndDone${counter}")),
+        };
+
+        if !is_or {
+            self.vm.push("push constant 0".into());
+            self.vm.push("eq".into());
+        }
+        self.vm.push(format!("if-goto {decided}"));
+        self.compile_operations_branch(term, &[], is_or, &decided);
+        self.compile_boolean(!is_or);
+        self.vm.push(format!("goto {done}"));
+        self.vm.push(format!("label {decided}"));
+        self.compile_boolean(is_or);
+        self.vm.push(format!("label {done}"));
+    }
+
+    fn compile_boolean(self: &mut Self, val: bool) {
+        if val {
+            self.vm.push("push constant 1".into());
+            self.vm.push("neg".into());
+        } else {
+            self.vm.push("push constant 0".into());
+        }
+    }
+
+    /* Jumps to `label` when the condition is `when`, falls through otherwise: `&&` and `||`
+     * branch on each operand instead of computing their result on the stack */
+    fn compile_branch(self: &mut Self, exp: &Expression, when: bool, label: &str) {
+        self.compile_operations_branch(&exp.term, &exp.additional, when, label);
+    }
+
+    fn compile_operations_branch(
+        self: &mut Self,
+        term: &Term,
+        additional: &[(Operation, Term)],
+        when: bool,
+        label: &str,
+    ) {
+        match (term, additional.split_last()) {
+            (Term::Expression(exp), None) => self.compile_branch(exp, when, label),
+            (
+                Term::Binary {
+                    left,
+                    op: op @ (Operation::LogicalAnd | Operation::LogicalOr),
+                    right,
+                },
+                None,
+            ) => self.compile_logical_branch(op, (left, &[]), right, when, label),
+            (_, Some(((op @ (Operation::LogicalAnd | Operation::LogicalOr), last), rest))) => {
+                self.compile_logical_branch(op, (term, rest), last, when, label)
+            }
+            _ => {
+                self.compile_operations(term, additional);
+                if !when {
+                    self.vm.push("push constant 0".into());
+                    self.vm.push("eq".into());
+                }
+                self.vm.push(format!("if-goto {label}"));
+            }
+        }
+    }
+
+    /* `a && b` is false as soon as `a` is, `a || b` true as soon as `a` is */
+    fn compile_logical_branch(
+        self: &mut Self,
+        op: &Operation,
+        (term, additional): (&Term, &[(Operation, Term)]),
+        last: &Term,
+        when: bool,
+        label: &str,
+    ) {
+        let decisive = *op == Operation::LogicalOr;
+        if when == decisive {
+            self.compile_operations_branch(term, additional, when, label);
+            self.compile_operations_branch(last, &[], when, label);
+        } else {
+            let counter = self.label_counter;
+            self.label_counter += 1;
+
+            let skip = format!("CondSkip${counter}");
+            self.compile_operations_branch(term, additional, decisive, &skip);
+            self.compile_operations_branch(last, &[], when, label);
+            self.vm.push(format!("label {skip}"));
+        }
+    }
+
+    /* Both operands sit on top of the stack */
+    fn compile_operation(self: &mut Self, op: &Operation) {
+        match op {
+            Operation::Plus => self.vm.push("add".into()),
+            Operation::Minus => self.vm.push("sub".into()),
+            Operation::GreaterThan => self.vm.push("gt".into()),
+            Operation::LessThan => self.vm.push("lt".into()),
+            Operation::Equals => self.vm.push("eq".into()),
+            Operation::And => self.vm.push("and".into()),
+            Operation::Or => self.vm.push("or".into()),
+            Operation::Not => self.vm.push("neg".into()),
+            Operation::LogicalAnd | Operation::LogicalOr => {
+                unreachable!("short-circuit operations are compiled as jumps")
+            }
+            Operation::Multiply => self.vm.push("call Math.multiply 2".into()),
+            Operation::Divide => {
+                if self.debug {
+                    self.check_division_by_zero();
+                }
+                self.vm.push("call Math.divide 2".into());
+            }
+        };
+    }
+
+    /* The array base must not be null before writing through `pointer 1`, outside of
+     * `Memory`: `Sys.error` pokes through it, which would fail the check over and over */
+    fn check_null_dereference(self: &mut Self, var_name: &String) {
+        if self.class.name == MEMORY_CLASS {
+            return;
+        }
+
+        let counter = self.label_counter;
+        self.label_counter += 1;
+
+        let entry = self.fetch_symbol_entry(var_name).unwrap();
+        self.vm.push(format!("push {} {}", entry.kind.vm(), entry.index));
+        self.vm.push(format!("if-goto NullCheckOk${}", counter));
+        self.report_error(NULL_DEREFERENCE_ERROR);
+        self.vm.push(format!("label NullCheckOk${}", counter));
+    }
+
+    /* The divisor sits on top of the stack: stash it in temp 1 to test it */
+    fn check_division_by_zero(self: &mut Self) {
+        let counter = self.label_counter;
+        self.label_counter += 1;
+
+        self.vm.push("pop temp 1".into());
+        self.vm.push("push temp 1".into());
+        self.vm.push(format!("if-goto DivisorOk${}", counter));
+        self.report_error(DIVISION_BY_ZERO_ERROR);
+        self.vm.push(format!("label DivisorOk${}", counter));
+        self.vm.push("push temp 1".into());
+    }
+
+    /* Sys.error halts the program, its return value is only popped to keep the stack balanced */
+    fn report_error(self: &mut Self, error_code: u16) {
+        self.vm.push(format!("push constant {}", error_code));
+        self.vm.push("call Sys.error 1".into());
+        self.vm.push("pop temp 0".into());
     }
 
     fn compile_term(self: &mut Self, term: &Term) {
         match term {
-            Term::IntConst(val) => {
-                self.vm.push(format!("push constant {}", val));
+            Term::IntConst(val) => self.push_constant(*val as u16),
+            Term::CharConst(val) => {
+                self.vm.push(format!("push constant {}", hack_code(*val).unwrap()));
             }
-            Term::VarName(val) => {
+            /* constants are inlined, taking no static slot */
+            Term::VarName(val, _) => {
                 let entry = self.fetch_symbol_entry(val).unwrap();
-                self.vm.push(format!("push {} {}", entry.kind.vm(), entry.index));
+                match entry.kind {
+                    Kind::Constant => self.push_constant(entry.index),
+                    _ => self.vm.push(format!("push {} {}", entry.kind.vm(), entry.index)),
+                }
+            }
+            Term::ClassConst { class_name, name, .. } => {
+                let entry = self
+                    .class_table
+                    .fetch_qualified_constant(&self.class.name, class_name, name)
+                    .unwrap();
+                self.push_constant(entry.index);
             }
             Term::Expression(exp) => {
                 self.compile_expression(exp);
             }
+            Term::Binary { left, op, right } => {
+                self.compile_term(left);
+                self.compile_operand(op, right);
+            }
             Term::Unary { op, term } => {
                 self.compile_term(term);
                 match op {
@@ -177,14 +660,14 @@ impl<'a> CodeGenerator<'a> {
             }
             Term::Call(call) => self.compile_routine_call(call),
             Term::StrConst(val) => {
-                self.vm.push(format!("push constant {}", val.len()));
+                self.vm.push(format!("push constant {}", val.chars().count()));
                 self.vm.push("call String.new 1".into());
                 val.chars().for_each(|c| {
-                    self.vm.push(format!("push constant {}", c as u8));
+                    self.vm.push(format!("push constant {}", hack_code(c).unwrap()));
                     self.vm.push("call String.appendChar 2".into());
                 });
             }
-            Term::ArrayAccess { var_name, exp } => {
+            Term::ArrayAccess { var_name, exp, .. } => {
                 let entry = self.fetch_symbol_entry(var_name).unwrap();
                 self.vm.push(format!("push {} {}", entry.kind.vm(), entry.index));
                 self.compile_expression(exp);
@@ -192,7 +675,7 @@ impl<'a> CodeGenerator<'a> {
                 self.vm.push("pop pointer 1".into());
                 self.vm.push("push that 0".into());
             }
-            Term::KeywordConst(val) => match val.as_str() {
+            Term::KeywordConst(val, _) => match val.as_str() {
                 "true" => {
                     self.vm.push("push constant 1".into());
                     self.vm.push("neg".into());
@@ -205,26 +688,24 @@ impl<'a> CodeGenerator<'a> {
         }
     }
 
+    /* Callees are resolved by the semantic analysis: a callee which is not a variable
+     * in scope is a class of the program or of its libraries */
     fn compile_routine_call(self: &mut Self, call: &SubroutineCall) {
         let (callee_name, n_args) = match &call.callee {
-            Some(callee) => {
-                if callee.chars().nth(0).unwrap().is_uppercase() {
-                    /* function call */
-                    (callee, call.expressions.len())
-                } else {
-                    /* method call on other object */
-                    self.compile_term(&Term::VarName(callee.to_owned()));
-                    let callee_entry = self.fetch_symbol_entry(callee).unwrap();
-                    match &callee_entry.jtype {
-                        JackType::Class(class) => (&class.to_owned(), call.expressions.len() + 1),
-                        _ => panic!("Callee must be a Jack Type"),
-                    }
+            Some(callee) => match self.fetch_symbol_entry(callee).map(|e| e.jtype.clone()) {
+                /* method call on other object */
+                Some(JackType::Class(class)) => {
+                    self.compile_term(&Term::VarName(callee.to_owned(), call.span.clone()));
+                    (class, call.expressions.len() + 1)
                 }
-            }
+                Some(_) => panic!("Callee must be a Jack Type"),
+                /* function call */
+                None => (callee.to_owned(), call.expressions.len()),
+            },
             None => {
                 /* method call on current object */
-                self.compile_term(&Term::KeywordConst("this".into()));
-                (&self.class.name, call.expressions.len() + 1)
+                self.vm.push("push pointer 0".into());
+                (self.class.name.to_owned(), call.expressions.len() + 1)
             }
         };
 
@@ -232,8 +713,13 @@ impl<'a> CodeGenerator<'a> {
         self.vm.push(format!("call {}.{} {}", callee_name, call.routine_name, n_args));
     }
 
+    /* Case values are terms of constant value, checked by the semantic analysis */
+    fn constant_value(self: &Self, term: &Term) -> i16 {
+        self.class_table.term_value(&self.class.name, term).unwrap()
+    }
+
     fn fetch_symbol_entry(self: &Self, varname: &String) -> Option<&SymbolEntry> {
-        match self.routine_symbols.as_ref().unwrap().entries.get(varname) {
+        match self.routine_symbols.as_ref().and_then(|symbols| symbols.entries.get(varname)) {
             Some(entry) => Some(entry),
             None => match self.class_symbols.entries.get(varname) {
                 Some(entry) => Some(entry),
@@ -245,7 +731,10 @@ impl<'a> CodeGenerator<'a> {
 
 #[cfg(test)]
 mod tests {
+    use super::super::Options;
     use super::super::grammar::*;
+    use super::symbols::ClassTable;
+    use crate::Span;
 
     #[test]
     fn compile_class_with_void_function() {
@@ -259,18 +748,24 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Void,
                 name: "doNothing".into(),
                 parameters: vec![],
-                body: SubroutineBody { vars: vec![], statements: vec![Statement::Return(None)] },
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![Statement::Return(None, Span::default())],
+                },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
         let expected = vec!["function Example.doNothing 0", "push constant 0", "return"];
 
-        assert_eq!(expected, super::compile(class))
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()))
     }
 
     #[test]
@@ -287,31 +782,47 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Int),
                 name: "incrTwice".into(),
-                parameters: vec![Parameter { jack_type: JackType::Int, name: "a".into() }],
+                parameters: vec![Parameter {
+                    jack_type: JackType::Int,
+                    name: "a".into(),
+                    span: Span::default(),
+                }],
                 body: SubroutineBody {
-                    vars: vec![VarDec { jack_type: JackType::Int, name: "res".into() }],
+                    vars: vec![VarDec {
+                        jack_type: JackType::Int,
+                        name: "res".into(),
+                        span: Span::default(),
+                        declaration_span: Span::default(),
+                    }],
                     statements: vec![
                         Statement::Let {
                             var_name: "res".into(),
                             array_access: None,
                             exp: Expression {
-                                term: Term::VarName("a".into()),
+                                term: Term::VarName("a".into(), Span::default()),
                                 additional: vec![
                                     (Operation::Plus, Term::IntConst(1)),
                                     (Operation::Plus, Term::IntConst(1)),
                                 ],
                             },
+                            span: Span::default(),
                         },
-                        Statement::Return(Some(Expression {
-                            term: Term::VarName("res".into()),
-                            additional: vec![],
-                        })),
+                        Statement::Return(
+                            Some(Expression {
+                                term: Term::VarName("res".into(), Span::default()),
+                                additional: vec![],
+                            }),
+                            Span::default(),
+                        ),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -327,7 +838,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class))
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()))
     }
 
     #[test]
@@ -348,32 +859,48 @@ mod tests {
                 var_type: ClassVarType::Static,
                 jack_type: JackType::Int,
                 name: "a".into(),
+                span: Span::default(),
+                declaration_span: Span::default(),
+                exp: None,
+                doc: None,
             }],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Int),
                 name: "incrStaticVarA".into(),
                 parameters: vec![],
                 body: SubroutineBody {
-                    vars: vec![VarDec { jack_type: JackType::Int, name: "res".into() }],
+                    vars: vec![VarDec {
+                        jack_type: JackType::Int,
+                        name: "res".into(),
+                        span: Span::default(),
+                        declaration_span: Span::default(),
+                    }],
                     statements: vec![
                         Statement::Let {
                             var_name: "res".into(),
                             array_access: None,
                             exp: Expression {
-                                term: Term::VarName("a".into()),
+                                term: Term::VarName("a".into(), Span::default()),
                                 additional: vec![
                                     (Operation::Plus, Term::IntConst(1)),
                                     (Operation::Plus, Term::IntConst(1)),
                                 ],
                             },
+                            span: Span::default(),
                         },
-                        Statement::Return(Some(Expression {
-                            term: Term::VarName("res".into()),
-                            additional: vec![],
-                        })),
+                        Statement::Return(
+                            Some(Expression {
+                                term: Term::VarName("res".into(), Span::default()),
+                                additional: vec![],
+                            }),
+                            Span::default(),
+                        ),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -391,7 +918,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class))
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()))
     }
 
     #[test]
@@ -406,21 +933,31 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Int),
                 name: "incrTwice".into(),
-                parameters: vec![Parameter { jack_type: JackType::Int, name: "a".into() }],
+                parameters: vec![Parameter {
+                    jack_type: JackType::Int,
+                    name: "a".into(),
+                    span: Span::default(),
+                }],
                 body: SubroutineBody {
                     vars: vec![],
-                    statements: vec![Statement::Return(Some(Expression {
-                        term: Term::VarName("a".into()),
-                        additional: vec![
-                            (Operation::Plus, Term::IntConst(1)),
-                            (Operation::Plus, Term::IntConst(1)),
-                        ],
-                    }))],
+                    statements: vec![Statement::Return(
+                        Some(Expression {
+                            term: Term::VarName("a".into(), Span::default()),
+                            additional: vec![
+                                (Operation::Plus, Term::IntConst(1)),
+                                (Operation::Plus, Term::IntConst(1)),
+                            ],
+                        }),
+                        Span::default(),
+                    )],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -435,7 +972,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class))
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()))
     }
 
     #[test]
@@ -450,25 +987,31 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Int),
                 name: "sumArgs".into(),
                 parameters: vec![
-                    Parameter { jack_type: JackType::Int, name: "a".into() },
-                    Parameter { jack_type: JackType::Int, name: "b".into() },
-                    Parameter { jack_type: JackType::Int, name: "c".into() },
+                    Parameter { jack_type: JackType::Int, name: "a".into(), span: Span::default() },
+                    Parameter { jack_type: JackType::Int, name: "b".into(), span: Span::default() },
+                    Parameter { jack_type: JackType::Int, name: "c".into(), span: Span::default() },
                 ],
                 body: SubroutineBody {
                     vars: vec![],
-                    statements: vec![Statement::Return(Some(Expression {
-                        term: Term::VarName("a".into()),
-                        additional: vec![
-                            (Operation::Plus, Term::VarName("b".into())),
-                            (Operation::Plus, Term::VarName("c".into())),
-                        ],
-                    }))],
+                    statements: vec![Statement::Return(
+                        Some(Expression {
+                            term: Term::VarName("a".into(), Span::default()),
+                            additional: vec![
+                                (Operation::Plus, Term::VarName("b".into(), Span::default())),
+                                (Operation::Plus, Term::VarName("c".into(), Span::default())),
+                            ],
+                        }),
+                        Span::default(),
+                    )],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -483,7 +1026,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class))
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()))
     }
 
     #[test]
@@ -498,27 +1041,37 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Int),
                 name: "negate".into(),
-                parameters: vec![Parameter { jack_type: JackType::Int, name: "a".into() }],
+                parameters: vec![Parameter {
+                    jack_type: JackType::Int,
+                    name: "a".into(),
+                    span: Span::default(),
+                }],
                 body: SubroutineBody {
                     vars: vec![],
-                    statements: vec![Statement::Return(Some(Expression {
-                        term: Term::Unary {
-                            op: Operation::Minus,
-                            term: Box::new(Term::VarName("a".into())),
-                        },
-                        additional: vec![],
-                    }))],
+                    statements: vec![Statement::Return(
+                        Some(Expression {
+                            term: Term::Unary {
+                                op: Operation::Minus,
+                                term: Box::new(Term::VarName("a".into(), Span::default())),
+                            },
+                            additional: vec![],
+                        }),
+                        Span::default(),
+                    )],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
         let expected = vec!["function Example.negate 0", "push argument 0", "neg", "return"];
 
-        assert_eq!(expected, super::compile(class))
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()))
     }
 
     #[test]
@@ -541,48 +1094,62 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Boolean),
                 name: "greater".into(),
                 parameters: vec![
-                    Parameter { jack_type: JackType::Int, name: "a".into() },
-                    Parameter { jack_type: JackType::Int, name: "b".into() },
+                    Parameter { jack_type: JackType::Int, name: "a".into(), span: Span::default() },
+                    Parameter { jack_type: JackType::Int, name: "b".into(), span: Span::default() },
                 ],
                 body: SubroutineBody {
-                    vars: vec![VarDec { jack_type: JackType::Boolean, name: "res".into() }],
+                    vars: vec![VarDec {
+                        jack_type: JackType::Boolean,
+                        name: "res".into(),
+                        span: Span::default(),
+                        declaration_span: Span::default(),
+                    }],
                     statements: vec![
                         Statement::If {
                             exp: Expression {
-                                term: Term::VarName("a".into()),
+                                term: Term::VarName("a".into(), Span::default()),
                                 additional: vec![(
                                     Operation::GreaterThan,
-                                    Term::VarName("b".into()),
+                                    Term::VarName("b".into(), Span::default()),
                                 )],
                             },
                             statements: vec![Statement::Let {
                                 var_name: "res".into(),
                                 array_access: None,
                                 exp: Expression {
-                                    term: Term::KeywordConst("true".into()),
+                                    term: Term::KeywordConst("true".into(), Span::default()),
                                     additional: vec![],
                                 },
+                                span: Span::default(),
                             }],
                             else_statements: Some(vec![Statement::Let {
                                 var_name: "res".into(),
                                 array_access: None,
                                 exp: Expression {
-                                    term: Term::KeywordConst("false".into()),
+                                    term: Term::KeywordConst("false".into(), Span::default()),
                                     additional: vec![],
                                 },
+                                span: Span::default(),
                             }]),
+                            span: Span::default(),
                         },
-                        Statement::Return(Some(Expression {
-                            term: Term::VarName("res".into()),
-                            additional: vec![],
-                        })),
+                        Statement::Return(
+                            Some(Expression {
+                                term: Term::VarName("res".into(), Span::default()),
+                                additional: vec![],
+                            }),
+                            Span::default(),
+                        ),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -604,7 +1171,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -620,6 +1187,7 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 name: "test".into(),
                 routine_type: RoutineType::Function,
@@ -635,10 +1203,13 @@ mod tests {
                                 term: Term::IntConst(1),
                                 additional: vec![],
                             }],
+                            span: Span::default(),
                         }),
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -651,7 +1222,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -669,31 +1240,40 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 name: "incrUntilTen".into(),
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Void,
-                parameters: vec![Parameter { jack_type: JackType::Int, name: "a".into() }],
+                parameters: vec![Parameter {
+                    jack_type: JackType::Int,
+                    name: "a".into(),
+                    span: Span::default(),
+                }],
                 body: SubroutineBody {
                     vars: vec![],
                     statements: vec![
                         Statement::While {
                             exp: Expression {
-                                term: Term::VarName("a".into()),
+                                term: Term::VarName("a".into(), Span::default()),
                                 additional: vec![(Operation::LessThan, Term::IntConst(10))],
                             },
                             statements: vec![Statement::Let {
                                 var_name: "a".into(),
                                 array_access: None,
                                 exp: Expression {
-                                    term: Term::VarName("a".into()),
+                                    term: Term::VarName("a".into(), Span::default()),
                                     additional: vec![(Operation::Plus, Term::IntConst(1))],
                                 },
+                                span: Span::default(),
                             }],
+                            span: Span::default(),
                         },
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -716,7 +1296,116 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
+    }
+
+    #[test]
+    fn compile_for_statement_with_break_and_continue() {
+        /*
+         * class Main {
+         *     function void main() {
+         *         var int i;
+         *         for (let i = 0; i < 3; let i = i + 1) {
+         *             if (i) { continue; }
+         *             break;
+         *         }
+         *         return;
+         *     }
+         * }
+         */
+        let assign_i = |exp: Expression| Statement::Let {
+            var_name: "i".into(),
+            array_access: None,
+            exp,
+            span: Span::default(),
+        };
+        let i = || Term::VarName("i".into(), Span::default());
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Void,
+                name: "main".into(),
+                parameters: vec![],
+                body: SubroutineBody {
+                    vars: vec![VarDec {
+                        jack_type: JackType::Int,
+                        name: "i".into(),
+                        span: Span::default(),
+                        declaration_span: Span::default(),
+                    }],
+                    statements: vec![
+                        Statement::For {
+                            init: Some(
+                                assign_i(Expression {
+                                    term: Term::IntConst(0),
+                                    additional: vec![],
+                                })
+                                .into(),
+                            ),
+                            exp: Some(Expression {
+                                term: i(),
+                                additional: vec![(Operation::LessThan, Term::IntConst(3))],
+                            }),
+                            step: Some(
+                                assign_i(Expression {
+                                    term: i(),
+                                    additional: vec![(Operation::Plus, Term::IntConst(1))],
+                                })
+                                .into(),
+                            ),
+                            statements: vec![
+                                Statement::If {
+                                    exp: Expression { term: i(), additional: vec![] },
+                                    statements: vec![Statement::Continue(Span::default())],
+                                    else_statements: None,
+                                    span: Span::default(),
+                                },
+                                Statement::Break(Span::default()),
+                            ],
+                            span: Span::default(),
+                        },
+                        Statement::Return(None, Span::default()),
+                    ],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+
+        /* continue goes through the step before testing the condition again */
+        let expected = vec![
+            "function Main.main 1",
+            "push constant 0",
+            "pop local 0",
+            "label ForCondition$0",
+            "push local 0",
+            "push constant 3",
+            "lt",
+            "if-goto ForStatements$0",
+            "goto ForDone$0",
+            "label ForStatements$0",
+            "push local 0",
+            "if-goto IfTrue$1",
+            "goto IfDone$1",
+            "label IfTrue$1",
+            "goto ForStep$0",
+            "label IfDone$1",
+            "goto ForDone$0",
+            "label ForStep$0",
+            "push local 0",
+            "push constant 1",
+            "add",
+            "pop local 0",
+            "goto ForCondition$0",
+            "label ForDone$0",
+            "push constant 0",
+            "return",
+        ];
+
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -737,12 +1426,17 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![
                 SubroutineDec {
                     routine_type: RoutineType::Function,
                     return_type: ReturnType::Void,
                     name: "execute".into(),
-                    parameters: vec![Parameter { jack_type: JackType::Int, name: "a".into() }],
+                    parameters: vec![Parameter {
+                        jack_type: JackType::Int,
+                        name: "a".into(),
+                        span: Span::default(),
+                    }],
                     body: SubroutineBody {
                         vars: vec![],
                         statements: vec![
@@ -754,22 +1448,30 @@ mod tests {
                                         callee: Some("Example".into()),
                                         routine_name: "incr".into(),
                                         expressions: vec![Expression {
-                                            term: Term::VarName("a".into()),
+                                            term: Term::VarName("a".into(), Span::default()),
                                             additional: vec![],
                                         }],
+                                        span: Span::default(),
                                     }),
                                     additional: vec![],
                                 },
+                                span: Span::default(),
                             },
-                            Statement::Return(None),
+                            Statement::Return(None, Span::default()),
                         ],
                     },
+                    span: Span::default(),
+                    doc: None,
                 },
                 SubroutineDec {
                     routine_type: RoutineType::Function,
                     return_type: ReturnType::Type(JackType::Int),
                     name: "incr".into(),
-                    parameters: vec![Parameter { jack_type: JackType::Int, name: "a".into() }],
+                    parameters: vec![Parameter {
+                        jack_type: JackType::Int,
+                        name: "a".into(),
+                        span: Span::default(),
+                    }],
                     body: SubroutineBody {
                         vars: vec![],
                         statements: vec![
@@ -777,16 +1479,22 @@ mod tests {
                                 var_name: "a".into(),
                                 array_access: None,
                                 exp: Expression {
-                                    term: Term::VarName("a".into()),
+                                    term: Term::VarName("a".into(), Span::default()),
                                     additional: vec![(Operation::Plus, Term::IntConst(1))],
                                 },
+                                span: Span::default(),
                             },
-                            Statement::Return(Some(Expression {
-                                term: Term::VarName("a".into()),
-                                additional: vec![],
-                            })),
+                            Statement::Return(
+                                Some(Expression {
+                                    term: Term::VarName("a".into(), Span::default()),
+                                    additional: vec![],
+                                }),
+                                Span::default(),
+                            ),
                         ],
                     },
+                    span: Span::default(),
+                    doc: None,
                 },
             ],
         };
@@ -807,7 +1515,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -824,6 +1532,7 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Void,
@@ -833,6 +1542,8 @@ mod tests {
                     vars: vec![VarDec {
                         jack_type: JackType::Class("Point".into()),
                         name: "p1".into(),
+                        span: Span::default(),
+                        declaration_span: Span::default(),
                     }],
                     statements: vec![
                         Statement::Let {
@@ -846,13 +1557,17 @@ mod tests {
                                         Expression { term: Term::IntConst(1), additional: vec![] },
                                         Expression { term: Term::IntConst(2), additional: vec![] },
                                     ],
+                                    span: Span::default(),
                                 }),
                                 additional: vec![],
                             },
+                            span: Span::default(),
                         },
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -866,7 +1581,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -889,20 +1604,37 @@ mod tests {
                     var_type: ClassVarType::Field,
                     jack_type: JackType::Int,
                     name: "x".into(),
+                    span: Span::default(),
+                    declaration_span: Span::default(),
+                    exp: None,
+                    doc: None,
                 },
                 ClassVarDec {
                     var_type: ClassVarType::Field,
                     jack_type: JackType::Int,
                     name: "y".into(),
+                    span: Span::default(),
+                    declaration_span: Span::default(),
+                    exp: None,
+                    doc: None,
                 },
             ],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Constructor,
                 return_type: ReturnType::Type(JackType::Class("Point".into())),
                 name: "new".into(),
                 parameters: vec![
-                    Parameter { jack_type: JackType::Int, name: "argX".into() },
-                    Parameter { jack_type: JackType::Int, name: "argY".into() },
+                    Parameter {
+                        jack_type: JackType::Int,
+                        name: "argX".into(),
+                        span: Span::default(),
+                    },
+                    Parameter {
+                        jack_type: JackType::Int,
+                        name: "argY".into(),
+                        span: Span::default(),
+                    },
                 ],
                 body: SubroutineBody {
                     vars: vec![],
@@ -911,24 +1643,31 @@ mod tests {
                             var_name: "x".into(),
                             array_access: None,
                             exp: Expression {
-                                term: Term::VarName("argX".into()),
+                                term: Term::VarName("argX".into(), Span::default()),
                                 additional: vec![],
                             },
+                            span: Span::default(),
                         },
                         Statement::Let {
                             var_name: "y".into(),
                             array_access: None,
                             exp: Expression {
-                                term: Term::VarName("argY".into()),
+                                term: Term::VarName("argY".into(), Span::default()),
                                 additional: vec![],
                             },
+                            span: Span::default(),
                         },
-                        Statement::Return(Some(Expression {
-                            term: Term::KeywordConst("this".into()),
-                            additional: vec![],
-                        })),
+                        Statement::Return(
+                            Some(Expression {
+                                term: Term::KeywordConst("this".into(), Span::default()),
+                                additional: vec![],
+                            }),
+                            Span::default(),
+                        ),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -945,7 +1684,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -960,6 +1699,7 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Void,
@@ -967,21 +1707,28 @@ mod tests {
                 parameters: vec![Parameter {
                     jack_type: JackType::Class("Another".into()),
                     name: "arg".into(),
+                    span: Span::default(),
                 }],
                 body: SubroutineBody {
                     vars: vec![],
-                    statements: vec![Statement::Return(Some(Expression {
-                        term: Term::Call(SubroutineCall {
-                            callee: Some("arg".into()),
-                            routine_name: "execute".into(),
-                            expressions: vec![Expression {
-                                term: Term::IntConst(1),
-                                additional: vec![],
-                            }],
+                    statements: vec![Statement::Return(
+                        Some(Expression {
+                            term: Term::Call(SubroutineCall {
+                                callee: Some("arg".into()),
+                                routine_name: "execute".into(),
+                                expressions: vec![Expression {
+                                    term: Term::IntConst(1),
+                                    additional: vec![],
+                                }],
+                                span: Span::default(),
+                            }),
+                            additional: vec![],
                         }),
-                        additional: vec![],
-                    }))],
+                        Span::default(),
+                    )],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -993,25 +1740,84 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
-    fn compile_output_print_string() {
+    fn compile_calls_on_uppercase_variable_and_lowercase_class() {
         /*
-         * class Main {
-         *     function void main() {
-         *         do Output.printString("Hello");
-         *         return;
+         * class Example {
+         *     function int execute(vec Player) {
+         *         return Player.length() + vec.zero();
          *     }
          * }
          */
+        let call = |callee: &str, routine_name: &str| {
+            Term::Call(SubroutineCall {
+                callee: Some(callee.into()),
+                routine_name: routine_name.into(),
+                expressions: vec![],
+                span: Span::default(),
+            })
+        };
+
         let class = Class {
-            name: "Main".into(),
+            name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
-                return_type: ReturnType::Void,
+                return_type: ReturnType::Type(JackType::Int),
+                name: "execute".into(),
+                parameters: vec![Parameter {
+                    jack_type: JackType::Class("vec".into()),
+                    name: "Player".into(),
+                    span: Span::default(),
+                }],
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![Statement::Return(
+                        Some(Expression {
+                            term: call("Player", "length"),
+                            additional: vec![(Operation::Plus, call("vec", "zero"))],
+                        }),
+                        Span::default(),
+                    )],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+
+        let expected = vec![
+            "function Example.execute 0",
+            "push argument 0",
+            "call vec.length 1",
+            "call vec.zero 0",
+            "add",
+            "return",
+        ];
+
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
+    }
+
+    #[test]
+    fn compile_output_print_string() {
+        /*
+         * class Main {
+         *     function void main() {
+         *         do Output.printString("Hello");
+         *         return;
+         *     }
+         * }
+         */
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Void,
                 name: "main".into(),
                 parameters: vec![],
                 body: SubroutineBody {
@@ -1024,10 +1830,13 @@ mod tests {
                                 term: Term::StrConst("Hello".into()),
                                 additional: vec![],
                             }],
+                            span: Span::default(),
                         }),
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -1052,7 +1861,69 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
+    }
+
+    #[test]
+    fn compile_char_constants_and_escape_sequences() {
+        /*
+         * class Main {
+         *     function void main() {
+         *         do Output.printChar('A');
+         *         do Output.printString("\t\n");
+         *         return;
+         *     }
+         * }
+         */
+        let print = |routine_name: &str, term: Term| {
+            Statement::Do(SubroutineCall {
+                callee: Some("Output".into()),
+                routine_name: routine_name.into(),
+                expressions: vec![Expression { term, additional: vec![] }],
+                span: Span::default(),
+            })
+        };
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Void,
+                name: "main".into(),
+                parameters: vec![],
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![
+                        print("printChar", Term::CharConst('A')),
+                        print("printString", Term::StrConst("\t\n".into())),
+                        Statement::Return(None, Span::default()),
+                    ],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+
+        /* Tabs are shown as spaces and newlines are 128 in the Hack character set */
+        let expected = vec![
+            "function Main.main 0",
+            "push constant 65",
+            "call Output.printChar 1",
+            "pop temp 0",
+            "push constant 2",
+            "call String.new 1",
+            "push constant 32",
+            "call String.appendChar 2",
+            "push constant 128",
+            "call String.appendChar 2",
+            "call Output.printString 1",
+            "pop temp 0",
+            "push constant 0",
+            "return",
+        ];
+
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -1081,13 +1952,22 @@ mod tests {
                 jack_type: JackType::Int,
                 name: "x".into(),
                 var_type: ClassVarType::Field,
+                span: Span::default(),
+                declaration_span: Span::default(),
+                exp: None,
+                doc: None,
             }],
+            consts: vec![],
             routines: vec![
                 SubroutineDec {
                     routine_type: RoutineType::Constructor,
                     return_type: ReturnType::Type(JackType::Class("Example".into())),
                     name: "new".into(),
-                    parameters: vec![Parameter { jack_type: JackType::Int, name: "argX".into() }],
+                    parameters: vec![Parameter {
+                        jack_type: JackType::Int,
+                        name: "argX".into(),
+                        span: Span::default(),
+                    }],
                     body: SubroutineBody {
                         vars: vec![],
                         statements: vec![
@@ -1095,16 +1975,22 @@ mod tests {
                                 var_name: "x".into(),
                                 array_access: None,
                                 exp: Expression {
-                                    term: Term::VarName("argX".into()),
+                                    term: Term::VarName("argX".into(), Span::default()),
                                     additional: vec![],
                                 },
+                                span: Span::default(),
                             },
-                            Statement::Return(Some(Expression {
-                                term: Term::KeywordConst("this".into()),
-                                additional: vec![],
-                            })),
+                            Statement::Return(
+                                Some(Expression {
+                                    term: Term::KeywordConst("this".into(), Span::default()),
+                                    additional: vec![],
+                                }),
+                                Span::default(),
+                            ),
                         ],
                     },
+                    span: Span::default(),
+                    doc: None,
                 },
                 SubroutineDec {
                     routine_type: RoutineType::Method,
@@ -1113,15 +1999,21 @@ mod tests {
                     parameters: vec![],
                     body: SubroutineBody {
                         vars: vec![],
-                        statements: vec![Statement::Return(Some(Expression {
-                            term: Term::Call(SubroutineCall {
-                                callee: None,
-                                routine_name: "incrX".into(),
-                                expressions: vec![],
+                        statements: vec![Statement::Return(
+                            Some(Expression {
+                                term: Term::Call(SubroutineCall {
+                                    callee: None,
+                                    routine_name: "incrX".into(),
+                                    expressions: vec![],
+                                    span: Span::default(),
+                                }),
+                                additional: vec![],
                             }),
-                            additional: vec![],
-                        }))],
+                            Span::default(),
+                        )],
                     },
+                    span: Span::default(),
+                    doc: None,
                 },
                 SubroutineDec {
                     routine_type: RoutineType::Method,
@@ -1135,16 +2027,22 @@ mod tests {
                                 var_name: "x".into(),
                                 array_access: None,
                                 exp: Expression {
-                                    term: Term::VarName("x".into()),
+                                    term: Term::VarName("x".into(), Span::default()),
                                     additional: vec![(Operation::Plus, Term::IntConst(1))],
                                 },
+                                span: Span::default(),
                             },
-                            Statement::Return(Some(Expression {
-                                term: Term::VarName("x".into()),
-                                additional: vec![],
-                            })),
+                            Statement::Return(
+                                Some(Expression {
+                                    term: Term::VarName("x".into(), Span::default()),
+                                    additional: vec![],
+                                }),
+                                Span::default(),
+                            ),
                         ],
                     },
+                    span: Span::default(),
+                    doc: None,
                 },
             ],
         };
@@ -1178,7 +2076,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -1194,6 +2092,7 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Void,
@@ -1201,6 +2100,7 @@ mod tests {
                 parameters: vec![Parameter {
                     name: "arg".into(),
                     jack_type: JackType::Class("Another".into()),
+                    span: Span::default(),
                 }],
                 body: SubroutineBody {
                     vars: vec![],
@@ -1212,10 +2112,13 @@ mod tests {
                                 term: Term::IntConst(1),
                                 additional: vec![],
                             }],
+                            span: Span::default(),
                         }),
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -1229,7 +2132,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -1247,6 +2150,7 @@ mod tests {
         let class = Class {
             name: "Main".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 name: "main".into(),
                 routine_type: RoutineType::Function,
@@ -1256,6 +2160,8 @@ mod tests {
                     vars: vec![VarDec {
                         name: "a".into(),
                         jack_type: JackType::Class("Array".into()),
+                        span: Span::default(),
+                        declaration_span: Span::default(),
                     }],
                     statements: vec![
                         Statement::Let {
@@ -1269,9 +2175,11 @@ mod tests {
                                         term: Term::IntConst(10),
                                         additional: vec![],
                                     }],
+                                    span: Span::default(),
                                 }),
                                 additional: vec![],
                             },
+                            span: Span::default(),
                         },
                         Statement::Let {
                             var_name: "a".into(),
@@ -1280,6 +2188,7 @@ mod tests {
                                 additional: vec![],
                             }),
                             exp: Expression { term: Term::IntConst(19), additional: vec![] },
+                            span: Span::default(),
                         },
                         Statement::Do(SubroutineCall {
                             callee: Some("Output".into()),
@@ -1291,13 +2200,17 @@ mod tests {
                                         term: Term::IntConst(0),
                                         additional: vec![],
                                     }),
+                                    span: Span::default(),
                                 },
                                 additional: vec![],
                             }],
+                            span: Span::default(),
                         }),
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -1325,7 +2238,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -1342,6 +2255,7 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Void,
@@ -1351,6 +2265,8 @@ mod tests {
                     vars: vec![VarDec {
                         name: "s".into(),
                         jack_type: JackType::Class("String".into()),
+                        span: Span::default(),
+                        declaration_span: Span::default(),
                     }],
                     statements: vec![
                         Statement::Let {
@@ -1360,10 +2276,13 @@ mod tests {
                                 term: Term::StrConst("test".into()),
                                 additional: vec![],
                             },
+                            span: Span::default(),
                         },
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -1384,7 +2303,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -1409,59 +2328,73 @@ mod tests {
         let class = Class {
             name: "Main".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 name: "main".into(),
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Void,
                 parameters: vec![],
                 body: SubroutineBody {
-                    vars: vec![VarDec { name: "a".into(), jack_type: JackType::Int }],
+                    vars: vec![VarDec {
+                        name: "a".into(),
+                        jack_type: JackType::Int,
+                        span: Span::default(),
+                        declaration_span: Span::default(),
+                    }],
                     statements: vec![
                         Statement::Let {
                             var_name: "a".into(),
                             array_access: None,
                             exp: Expression { term: Term::IntConst(6), additional: vec![] },
+                            span: Span::default(),
                         },
                         Statement::If {
                             exp: Expression {
-                                term: Term::VarName("a".into()),
+                                term: Term::VarName("a".into(), Span::default()),
                                 additional: vec![(Operation::LessThan, Term::IntConst(10))],
                             },
                             statements: vec![Statement::If {
                                 exp: Expression {
-                                    term: Term::VarName("a".into()),
+                                    term: Term::VarName("a".into(), Span::default()),
                                     additional: vec![(Operation::GreaterThan, Term::IntConst(5))],
                                 },
                                 statements: vec![Statement::Let {
                                     var_name: "a".into(),
                                     array_access: None,
                                     exp: Expression {
-                                        term: Term::VarName("a".into()),
+                                        term: Term::VarName("a".into(), Span::default()),
                                         additional: vec![(Operation::Plus, Term::IntConst(1))],
                                     },
+                                    span: Span::default(),
                                 }],
                                 else_statements: Some(vec![Statement::Let {
                                     var_name: "a".into(),
                                     array_access: None,
                                     exp: Expression {
-                                        term: Term::VarName("a".into()),
+                                        term: Term::VarName("a".into(), Span::default()),
                                         additional: vec![(Operation::Plus, Term::IntConst(2))],
                                     },
+                                    span: Span::default(),
                                 }]),
+                                span: Span::default(),
                             }],
                             else_statements: None,
+                            span: Span::default(),
                         },
                         Statement::Do(SubroutineCall {
                             callee: Some("Output".into()),
                             routine_name: "printInt".into(),
                             expressions: vec![Expression {
-                                term: Term::VarName("a".into()),
+                                term: Term::VarName("a".into(), Span::default()),
                                 additional: vec![],
                             }],
+                            span: Span::default(),
                         }),
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -1498,7 +2431,7 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class));
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 
     #[test]
@@ -1517,18 +2450,25 @@ mod tests {
         let class = Class {
             name: "Main".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 name: "main".into(),
                 return_type: ReturnType::Void,
                 parameters: vec![],
                 routine_type: RoutineType::Function,
                 body: SubroutineBody {
-                    vars: vec![VarDec { name: "a".into(), jack_type: JackType::Int }],
+                    vars: vec![VarDec {
+                        name: "a".into(),
+                        jack_type: JackType::Int,
+                        span: Span::default(),
+                        declaration_span: Span::default(),
+                    }],
                     statements: vec![
                         Statement::Let {
                             var_name: "a".into(),
                             array_access: None,
                             exp: Expression { term: Term::IntConst(5), additional: vec![] },
+                            span: Span::default(),
                         },
                         Statement::Let {
                             var_name: "a".into(),
@@ -1536,22 +2476,26 @@ mod tests {
                             exp: Expression {
                                 term: Term::Unary {
                                     op: Operation::Not,
-                                    term: Box::new(Term::VarName("a".into())),
+                                    term: Box::new(Term::VarName("a".into(), Span::default())),
                                 },
                                 additional: vec![],
                             },
+                            span: Span::default(),
                         },
                         Statement::Do(SubroutineCall {
                             callee: Some("Output".into()),
                             routine_name: "printInt".into(),
                             expressions: vec![Expression {
-                                term: Term::VarName("a".into()),
+                                term: Term::VarName("a".into(), Span::default()),
                                 additional: vec![],
                             }],
+                            span: Span::default(),
                         }),
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -1569,6 +2513,854 @@ mod tests {
             "return",
         ];
 
-        assert_eq!(expected, super::compile(class))
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()))
+    }
+
+    #[test]
+    fn compile_integer_constants_past_push_constant_range() {
+        /*
+         * class Main {
+         *     function int main() {
+         *         return -32768 & 0xFF00;
+         *     }
+         * }
+         */
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Type(JackType::Int),
+                name: "main".into(),
+                parameters: vec![],
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![Statement::Return(
+                        Some(Expression {
+                            term: Term::Unary {
+                                op: Operation::Minus,
+                                term: Term::IntConst(32768).into(),
+                            },
+                            additional: vec![(Operation::And, Term::IntConst(0xFF00))],
+                        }),
+                        Span::default(),
+                    )],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+
+        /* push constant only takes 0..32767, bigger patterns are pushed inverted then `not` */
+        let expected = vec![
+            "function Main.main 0",
+            "push constant 32767",
+            "not",
+            "neg",
+            "push constant 255",
+            "not",
+            "and",
+            "return",
+        ];
+
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
+    }
+
+    #[test]
+    fn compile_debug_checks_for_array_write_and_division() {
+        /*
+         * class Main {
+         *     function void main(Array a, int b) {
+         *         let a[0] = 10 / b;
+         *         return;
+         *     }
+         * }
+         */
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                name: "main".into(),
+                return_type: ReturnType::Void,
+                parameters: vec![
+                    Parameter {
+                        jack_type: JackType::Class("Array".into()),
+                        name: "a".into(),
+                        span: Span::default(),
+                    },
+                    Parameter { jack_type: JackType::Int, name: "b".into(), span: Span::default() },
+                ],
+                routine_type: RoutineType::Function,
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![
+                        Statement::Let {
+                            var_name: "a".into(),
+                            array_access: Some(Expression {
+                                term: Term::IntConst(0),
+                                additional: vec![],
+                            }),
+                            exp: Expression {
+                                term: Term::IntConst(10),
+                                additional: vec![(
+                                    Operation::Divide,
+                                    Term::VarName("b".into(), Span::default()),
+                                )],
+                            },
+                            span: Span::default(),
+                        },
+                        Statement::Return(None, Span::default()),
+                    ],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+
+        let expected = vec![
+            "function Main.main 0",
+            /* null check on the array base */
+            "push argument 0",
+            "if-goto NullCheckOk$0",
+            "push constant 101",
+            "call Sys.error 1",
+            "pop temp 0",
+            "label NullCheckOk$0",
+            /* array address */
+            "push argument 0",
+            "push constant 0",
+            "add",
+            /* division with zero check on the divisor */
+            "push constant 10",
+            "push argument 1",
+            "pop temp 1",
+            "push temp 1",
+            "if-goto DivisorOk$1",
+            "push constant 102",
+            "call Sys.error 1",
+            "pop temp 0",
+            "label DivisorOk$1",
+            "push temp 1",
+            "call Math.divide 2",
+            /* array write */
+            "pop temp 0",
+            "pop pointer 1",
+            "push temp 0",
+            "pop that 0",
+            "push constant 0",
+            "return",
+        ];
+
+        assert_eq!(
+            expected,
+            super::compile(
+                class,
+                &ClassTable::default(),
+                &Options { debug: true, ..Options::default() }
+            )
+        );
+    }
+
+    #[test]
+    fn compile_switch_statements() {
+        /*
+         * class Main {
+         *     function int main(int x) {
+         *         switch (x) {
+         *             case 1: case 3: return 1;
+         *             case 2: case 4: break;
+         *         }
+         *         switch (x) {
+         *             case 'A': return 2;
+         *             default: return 0;
+         *         }
+         *     }
+         * }
+         */
+        let x =
+            || Expression { term: Term::VarName("x".into(), Span::default()), additional: vec![] };
+        let return_int = |val| {
+            Statement::Return(
+                Some(Expression { term: Term::IntConst(val), additional: vec![] }),
+                Span::default(),
+            )
+        };
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Type(JackType::Int),
+                name: "main".into(),
+                parameters: vec![Parameter {
+                    jack_type: JackType::Int,
+                    name: "x".into(),
+                    span: Span::default(),
+                }],
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![
+                        Statement::Switch {
+                            exp: x(),
+                            cases: vec![
+                                Case {
+                                    values: vec![Term::IntConst(1), Term::IntConst(3)],
+                                    statements: vec![return_int(1)],
+                                    span: Span::default(),
+                                },
+                                Case {
+                                    values: vec![Term::IntConst(2), Term::IntConst(4)],
+                                    statements: vec![Statement::Break(Span::default())],
+                                    span: Span::default(),
+                                },
+                            ],
+                            default: None,
+                            span: Span::default(),
+                        },
+                        Statement::Switch {
+                            exp: x(),
+                            cases: vec![Case {
+                                values: vec![Term::CharConst('A')],
+                                statements: vec![return_int(2)],
+                                span: Span::default(),
+                            }],
+                            default: Some(vec![return_int(0)]),
+                            span: Span::default(),
+                        },
+                    ],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+
+        /* dense values go through the bits of their offset from 1, 'A' is compared alone */
+        let expected = vec![
+            "function Main.main 0",
+            "push argument 0",
+            "pop temp 2",
+            "push temp 2",
+            "push constant 1",
+            "sub",
+            "pop temp 2",
+            "push temp 2",
+            "push constant 0",
+            "lt",
+            "if-goto SwitchDone$0",
+            "push temp 2",
+            "push constant 3",
+            "gt",
+            "if-goto SwitchDone$0",
+            "push temp 2",
+            "push constant 2",
+            "and",
+            "if-goto SwitchSlot2_2$0",
+            "push temp 2",
+            "push constant 1",
+            "and",
+            "if-goto SwitchSlot1_1$0",
+            "goto SwitchCase0$0",
+            "label SwitchSlot1_1$0",
+            "goto SwitchCase1$0",
+            "label SwitchSlot2_2$0",
+            "push temp 2",
+            "push constant 1",
+            "and",
+            "if-goto SwitchSlot3_1$0",
+            "goto SwitchCase0$0",
+            "label SwitchSlot3_1$0",
+            "goto SwitchCase1$0",
+            "label SwitchCase0$0",
+            "push constant 1",
+            "return",
+            "goto SwitchDone$0",
+            "label SwitchCase1$0",
+            "goto SwitchDone$0",
+            "goto SwitchDone$0",
+            "label SwitchDone$0",
+            "push argument 0",
+            "pop temp 2",
+            "push temp 2",
+            "push constant 65",
+            "eq",
+            "if-goto SwitchCase0$1",
+            "goto SwitchDefault$1",
+            "label SwitchCase0$1",
+            "push constant 2",
+            "return",
+            "goto SwitchDone$1",
+            "label SwitchDefault$1",
+            "push constant 0",
+            "return",
+            "label SwitchDone$1",
+        ];
+
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
+    }
+
+    #[test]
+    fn inline_constants() {
+        /*
+         * class Main {
+         *     const int MASK = 0x8000 | Keys.LEFT;
+         *     function int main() {
+         *         return MASK - Keys.LEFT;
+         *     }
+         * }
+         *
+         * class Keys {
+         *     enum Arrow { LEFT = 2 }
+         * }
+         */
+        let keys_left = || Term::ClassConst {
+            class_name: "Keys".into(),
+            name: "LEFT".into(),
+            span: Span::default(),
+        };
+        let constant = |name: &str, exp| ConstDec {
+            jack_type: JackType::Int,
+            name: name.into(),
+            exp: Some(exp),
+            span: Span::default(),
+            declaration_span: Span::default(),
+            enum_name: None,
+            doc: None,
+        };
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![constant(
+                "MASK",
+                Expression {
+                    term: Term::IntConst(0x8000),
+                    additional: vec![(Operation::Or, keys_left())],
+                },
+            )],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Type(JackType::Int),
+                name: "main".into(),
+                parameters: vec![],
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![Statement::Return(
+                        Some(Expression {
+                            term: Term::VarName("MASK".into(), Span::default()),
+                            additional: vec![(Operation::Minus, keys_left())],
+                        }),
+                        Span::default(),
+                    )],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+        let keys = Class {
+            name: "Keys".into(),
+            vars: vec![],
+            consts: vec![constant(
+                "LEFT",
+                Expression { term: Term::IntConst(2), additional: vec![] },
+            )],
+            routines: vec![],
+        };
+        let mut class_table = ClassTable::default();
+        class_table.add_class(&class);
+        class_table.add_class(&keys);
+
+        /* 0x8002 is out of the range of push constant, its complement is pushed instead */
+        let expected = vec![
+            "function Main.main 0",
+            "push constant 32765",
+            "not",
+            "push constant 2",
+            "sub",
+            "return",
+        ];
+
+        assert_eq!(expected, super::compile(class, &class_table, &Options::default()));
+    }
+
+    #[test]
+    fn inline_members_qualified_by_their_enum() {
+        /*
+         * class Main {
+         *     enum Dir { LEFT, RIGHT }
+         *     function int main() {
+         *         return Dir.RIGHT;
+         *     }
+         * }
+         */
+        let member = |name: &str| ConstDec {
+            jack_type: JackType::Int,
+            name: name.into(),
+            exp: None,
+            span: Span::default(),
+            declaration_span: Span::default(),
+            enum_name: Some("Dir".into()),
+            doc: None,
+        };
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![member("LEFT"), member("RIGHT")],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Type(JackType::Int),
+                name: "main".into(),
+                parameters: vec![],
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![Statement::Return(
+                        Some(Expression {
+                            term: Term::ClassConst {
+                                class_name: "Dir".into(),
+                                name: "RIGHT".into(),
+                                span: Span::default(),
+                            },
+                            additional: vec![],
+                        }),
+                        Span::default(),
+                    )],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+        let mut class_table = ClassTable::default();
+        class_table.add_class(&class);
+
+        let expected = vec!["function Main.main 0", "push constant 1", "return"];
+
+        assert_eq!(expected, super::compile(class, &class_table, &Options::default()));
+    }
+
+    #[test]
+    fn compile_compound_assignments() {
+        /*
+         * class Main {
+         *     function void main(Array a, int x) {
+         *         let x *= 3;
+         *         let a[x]--;
+         *     }
+         * }
+         */
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Void,
+                name: "main".into(),
+                parameters: vec![
+                    Parameter {
+                        jack_type: JackType::Class("Array".into()),
+                        name: "a".into(),
+                        span: Span::default(),
+                    },
+                    Parameter { jack_type: JackType::Int, name: "x".into(), span: Span::default() },
+                ],
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![
+                        Statement::Update {
+                            var_name: "x".into(),
+                            array_access: None,
+                            op: Operation::Multiply,
+                            exp: Some(Expression { term: Term::IntConst(3), additional: vec![] }),
+                            span: Span::default(),
+                        },
+                        Statement::Update {
+                            var_name: "a".into(),
+                            array_access: Some(Expression {
+                                term: Term::VarName("x".into(), Span::default()),
+                                additional: vec![],
+                            }),
+                            op: Operation::Minus,
+                            exp: None,
+                            span: Span::default(),
+                        },
+                    ],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+
+        /* the element address is computed once, then duplicated through pointer 1 */
+        let expected = vec![
+            "function Main.main 0",
+            "push argument 1",
+            "push constant 3",
+            "call Math.multiply 2",
+            "pop argument 1",
+            "push argument 0",
+            "push argument 1",
+            "add",
+            "pop pointer 1",
+            "push pointer 1",
+            "push that 0",
+            "push constant 1",
+            "sub",
+            "pop temp 0",
+            "pop pointer 1",
+            "push temp 0",
+            "pop that 0",
+        ];
+
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
+    }
+
+    #[test]
+    fn compile_block_declarations() {
+        /*
+         * class Main {
+         *     function void main() {
+         *         var int a;
+         *         while (true) { var int b; let b++; }
+         *     }
+         * }
+         */
+        let var = |name: &str| VarDec {
+            jack_type: JackType::Int,
+            name: name.into(),
+            span: Span::default(),
+            declaration_span: Span::default(),
+        };
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Void,
+                name: "main".into(),
+                parameters: vec![],
+                body: SubroutineBody {
+                    vars: vec![var("a")],
+                    statements: vec![Statement::While {
+                        exp: Expression {
+                            term: Term::KeywordConst("true".into(), Span::default()),
+                            additional: vec![],
+                        },
+                        statements: vec![
+                            Statement::Var(vec![var("b")]),
+                            Statement::Update {
+                                var_name: "b".into(),
+                                array_access: None,
+                                op: Operation::Plus,
+                                exp: None,
+                                span: Span::default(),
+                            },
+                        ],
+                        span: Span::default(),
+                    }],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+
+        /* b is reset on every iteration, as a is on entering the function */
+        let expected = vec![
+            "function Main.main 2",
+            "label WhileCondition$0",
+            "push constant 1",
+            "neg",
+            "if-goto WhileStatements$0",
+            "goto WhileDone$0",
+            "label WhileStatements$0",
+            "push constant 0",
+            "pop local 1",
+            "push local 1",
+            "push constant 1",
+            "add",
+            "pop local 1",
+            "goto WhileCondition$0",
+            "label WhileDone$0",
+        ];
+
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
+    }
+
+    #[test]
+    fn compile_initializers() {
+        /*
+         * class Point {
+         *     static int count = 2;
+         *     field int x = count, y;
+         *     constructor Point new(int count) { return this; }
+         * }
+         *
+         * class Vector {
+         *     field int dx = 1;
+         * }
+         */
+        let var = |var_type, name: &str, exp| ClassVarDec {
+            var_type,
+            jack_type: JackType::Int,
+            name: name.into(),
+            span: Span::default(),
+            declaration_span: Span::default(),
+            exp,
+            doc: None,
+        };
+        let int = |val| Some(Expression { term: Term::IntConst(val), additional: vec![] });
+        let count = Some(Expression {
+            term: Term::VarName("count".into(), Span::default()),
+            additional: vec![],
+        });
+        let point = Class {
+            name: "Point".into(),
+            vars: vec![
+                var(ClassVarType::Static, "count", int(2)),
+                var(ClassVarType::Field, "x", count),
+                var(ClassVarType::Field, "y", None),
+            ],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Constructor,
+                return_type: ReturnType::Type(JackType::Class("Point".into())),
+                name: "new".into(),
+                parameters: vec![Parameter {
+                    jack_type: JackType::Int,
+                    name: "count".into(),
+                    span: Span::default(),
+                }],
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![Statement::Return(
+                        Some(Expression {
+                            term: Term::KeywordConst("this".into(), Span::default()),
+                            additional: vec![],
+                        }),
+                        Span::default(),
+                    )],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+        let vector = Class {
+            name: "Vector".into(),
+            vars: vec![var(ClassVarType::Field, "dx", int(1))],
+            consts: vec![],
+            routines: vec![],
+        };
+
+        /* the initializer of x reads the static, not the parameter of the same name */
+        let expected_point = vec![
+            "function Point.new 0",
+            "push constant 2",
+            "call Memory.alloc 1",
+            "pop pointer 0",
+            "push static 0",
+            "pop this 0",
+            "push pointer 0",
+            "return",
+            "function Point.$clinit 0",
+            "push constant 2",
+            "pop static 0",
+            "push constant 0",
+            "return",
+        ];
+        let expected_vector = vec![
+            "function Vector.new 0",
+            "push constant 1",
+            "call Memory.alloc 1",
+            "pop pointer 0",
+            "push constant 1",
+            "pop this 0",
+            "push pointer 0",
+            "return",
+        ];
+
+        assert_eq!(
+            expected_point,
+            super::compile(point, &ClassTable::default(), &Options::default())
+        );
+        assert_eq!(
+            expected_vector,
+            super::compile(vector, &ClassTable::default(), &Options::default())
+        );
+    }
+
+    #[test]
+    fn compile_short_circuit_operations() {
+        /*
+         * class Main {
+         *     function void main(int a, int b) {
+         *         if ((a > 0) && b) { let a = 1; }
+         *         let b = a || b;
+         *     }
+         * }
+         */
+        let var = |name: &str| Term::VarName(name.into(), Span::default());
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Void,
+                name: "main".into(),
+                parameters: vec![
+                    Parameter { jack_type: JackType::Int, name: "a".into(), span: Span::default() },
+                    Parameter { jack_type: JackType::Int, name: "b".into(), span: Span::default() },
+                ],
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![
+                        Statement::If {
+                            exp: Expression {
+                                term: Term::Expression(Box::new(Expression {
+                                    term: var("a"),
+                                    additional: vec![(Operation::GreaterThan, Term::IntConst(0))],
+                                })),
+                                additional: vec![(Operation::LogicalAnd, var("b"))],
+                            },
+                            statements: vec![Statement::Let {
+                                var_name: "a".into(),
+                                array_access: None,
+                                exp: Expression { term: Term::IntConst(1), additional: vec![] },
+                                span: Span::default(),
+                            }],
+                            else_statements: None,
+                            span: Span::default(),
+                        },
+                        Statement::Let {
+                            var_name: "b".into(),
+                            array_access: None,
+                            exp: Expression {
+                                term: var("a"),
+                                additional: vec![(Operation::LogicalOr, var("b"))],
+                            },
+                            span: Span::default(),
+                        },
+                    ],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+
+        /* conditions branch on each operand, values are -1 or 0 */
+        let expected = vec![
+            "function Main.main 0",
+            "push argument 0",
+            "push constant 0",
+            "gt",
+            "push constant 0",
+            "eq",
+            "if-goto CondSkip$1",
+            "push argument 1",
+            "if-goto IfTrue$0",
+            "label CondSkip$1",
+            "goto IfDone$0",
+            "label IfTrue$0",
+            "push constant 1",
+            "pop argument 0",
+            "label IfDone$0",
+            "push argument 0",
+            "if-goto OrTrue$2",
+            "push argument 1",
+            "if-goto OrTrue$2",
+            "push constant 0",
+            "goto OrDone$2",
+            "label OrTrue$2",
+            "push constant 1",
+            "neg",
+            "label OrDone$2",
+            "pop argument 1",
+        ];
+
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
+    }
+
+    #[test]
+    fn compile_precedence_trees() {
+        /*
+         * // pragma precedence
+         * class Main {
+         *     function void main(int a, int b) {
+         *         if (a > 0 && b) { let a = a + b * 2; }
+         *     }
+         * }
+         */
+        let var = |name: &str| Term::VarName(name.into(), Span::default());
+        let binary =
+            |left, op, right| Term::Binary { left: Box::new(left), op, right: Box::new(right) };
+        let class = Class {
+            name: "Main".into(),
+            vars: vec![],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Void,
+                name: "main".into(),
+                parameters: vec![
+                    Parameter { jack_type: JackType::Int, name: "a".into(), span: Span::default() },
+                    Parameter { jack_type: JackType::Int, name: "b".into(), span: Span::default() },
+                ],
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![Statement::If {
+                        exp: Expression {
+                            term: binary(
+                                binary(var("a"), Operation::GreaterThan, Term::IntConst(0)),
+                                Operation::LogicalAnd,
+                                var("b"),
+                            ),
+                            additional: vec![],
+                        },
+                        statements: vec![Statement::Let {
+                            var_name: "a".into(),
+                            array_access: None,
+                            exp: Expression {
+                                term: binary(
+                                    var("a"),
+                                    Operation::Plus,
+                                    binary(var("b"), Operation::Multiply, Term::IntConst(2)),
+                                ),
+                                additional: vec![],
+                            },
+                            span: Span::default(),
+                        }],
+                        else_statements: None,
+                        span: Span::default(),
+                    }],
+                },
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+
+        let expected = vec![
+            "function Main.main 0",
+            "push argument 0",
+            "push constant 0",
+            "gt",
+            "push constant 0",
+            "eq",
+            "if-goto CondSkip$1",
+            "push argument 1",
+            "if-goto IfTrue$0",
+            "label CondSkip$1",
+            "goto IfDone$0",
+            "label IfTrue$0",
+            "push argument 0",
+            "push argument 1",
+            "push constant 2",
+            "call Math.multiply 2",
+            "add",
+            "pop argument 0",
+            "label IfDone$0",
+        ];
+
+        assert_eq!(expected, super::compile(class, &ClassTable::default(), &Options::default()));
     }
 }
@@ -1,8 +1,10 @@
 use super::super::grammar::*;
+use super::super::syntax_analyzer::tokenizer::hack_code;
 use std::collections::HashMap;
 
 type SymbolsTable = HashMap<String, SymbolEntry>;
 
+/// Fields and statics of a class, numbered in order of declaration within their kind
 #[derive(Debug, PartialEq)]
 pub struct ClassSymbols {
     pub entries: SymbolsTable,
@@ -11,7 +13,7 @@ pub struct ClassSymbols {
 }
 
 impl ClassSymbols {
-    pub fn new(class_var_decs: &Vec<ClassVarDec>) -> Self {
+    pub fn new(class_var_decs: &[ClassVarDec]) -> Self {
         let mut field_counter: u16 = 0;
         let mut static_counter: u16 = 0;
         let mut entries = SymbolsTable::new();
@@ -39,21 +41,42 @@ impl ClassSymbols {
             entries.insert(var.name.clone(), entry);
         });
 
-        Self {
-            entries,
-            field_counter,
-            static_counter,
-        }
+        Self { entries, field_counter, static_counter }
+    }
+
+    /// Adds the constants of the class, valued through `class_table`.
+    /// A constant never shadows a variable of the same name, reported as a duplicate anyway
+    pub fn add_constants(self: &mut Self, class_name: &str, class_table: &ClassTable) {
+        let Some(constants) = class_table.constants.get(class_name) else { return };
+
+        constants.keys().for_each(|name| {
+            if !self.entries.contains_key(name) {
+                let entry = class_table.fetch_constant(class_name, name).unwrap();
+                self.entries.insert(name.clone(), entry);
+            }
+        });
     }
 }
 
+/// Arguments and locals of a subroutine, methods taking `this` as argument 0
+///
+/// Locals declared within a block are only visible until the end of it, their slots being
+/// reused by the blocks that follow: walking the subroutine, blocks are entered and exited
+/// as they are met, their `var` statements added when reached.
 #[derive(Debug, PartialEq)]
 pub struct RoutineSymbols {
     pub entries: SymbolsTable,
+    /// Slots of the `local` segment, enough for the locals in scope at any point
+    pub local_count: u16,
     local_counter: u16,
     argument_counter: u16,
+    /* enclosing blocks, innermost last */
+    blocks: Vec<Block>,
 }
 
+/* Locals counter on entering a block, along with the entries its locals hide */
+type Block = (u16, Vec<(String, Option<SymbolEntry>)>);
+
 impl RoutineSymbols {
     pub fn new(routine: &SubroutineDec, class_name: &String) -> Self {
         let (entries, argument_counter) = match routine.routine_type {
@@ -71,26 +94,46 @@ impl RoutineSymbols {
             _ => (SymbolsTable::new(), 0),
         };
 
+        let local_count = routine.body.vars.len() as u16 + block_locals(&routine.body.statements);
         let mut symbols = Self {
             entries: entries,
+            local_count,
             local_counter: 0,
             argument_counter: argument_counter,
+            blocks: vec![],
         };
 
-        routine
-            .parameters
-            .iter()
-            .for_each(|param| symbols.add_param(param));
+        routine.parameters.iter().for_each(|param| symbols.add_param(param));
 
-        routine
-            .body
-            .vars
-            .iter()
-            .for_each(|var| symbols.add_var(var));
+        routine.body.vars.iter().for_each(|var| symbols.add_var(var));
 
         symbols
     }
 
+    /// Declares the locals of a `var` statement, until the end of the current block
+    pub fn add_vars(self: &mut Self, vars: &[VarDec]) {
+        vars.iter().for_each(|var| self.add_var(var));
+    }
+
+    pub fn enter_block(self: &mut Self) {
+        self.blocks.push((self.local_counter, vec![]));
+    }
+
+    /// Forgets the locals of the block, bringing back the variables they hid
+    pub fn exit_block(self: &mut Self) {
+        let Some((local_counter, hidden)) = self.blocks.pop() else { return };
+
+        self.local_counter = local_counter;
+        hidden.into_iter().rev().for_each(|(name, entry)| match entry {
+            Some(entry) => {
+                self.entries.insert(name, entry);
+            }
+            None => {
+                self.entries.remove(&name);
+            }
+        });
+    }
+
     fn add_param(self: &mut Self, param: &Parameter) {
         let entry = SymbolEntry {
             kind: Kind::Argument,
@@ -109,11 +152,33 @@ impl RoutineSymbols {
             index: self.local_counter,
         };
 
-        self.entries.insert(var.name.clone(), entry);
+        let hidden = self.entries.insert(var.name.clone(), entry);
+        if let Some((_, block_hidden)) = self.blocks.last_mut() {
+            block_hidden.push((var.name.clone(), hidden));
+        }
         self.local_counter += 1;
     }
 }
 
+/* Locals needed by the statements of a block beyond those in scope on entering it: nested
+ * blocks start from the locals declared before them, and are given back their slots */
+fn block_locals(statements: &[Statement]) -> u16 {
+    let mut declared = 0;
+
+    statements.iter().fold(0, |most, statement| match statement {
+        Statement::Var(vars) => {
+            declared += vars.len() as u16;
+            most.max(declared)
+        }
+        statement => statement
+            .blocks()
+            .into_iter()
+            .fold(most, |most, block| most.max(declared + block_locals(block))),
+    })
+}
+
+/// What a name resolves to: its vm segment, type and index within the segment.
+/// Constants are resolved to their value, as a 16 bit word, instead of an index
 #[derive(Debug, PartialEq)]
 pub struct SymbolEntry {
     pub kind: Kind,
@@ -121,28 +186,262 @@ pub struct SymbolEntry {
     pub index: u16,
 }
 
+/// Kind of variable, mapped to the vm segment storing it
 #[derive(Debug, PartialEq)]
 pub enum Kind {
     Local,
     Argument,
     Field,
     Static,
+    Constant,
 }
 
 impl Kind {
+    /// Name of the vm segment storing variables of this kind
     pub fn vm(self: &Self) -> String {
         match self {
             Kind::Argument => "argument".into(),
             Kind::Local => "local".into(),
             Kind::Field => "this".into(),
             Kind::Static => "static".into(),
+            Kind::Constant => "constant".into(),
         }
     }
 }
 
+/// Subroutines and constants declared by every class of the program
+/// and of the libraries it is linked with
+#[derive(Debug, PartialEq, Default)]
+pub struct ClassTable {
+    pub classes: HashMap<String, HashMap<String, RoutineSignature>>,
+    /* type and expression of the constants of each class, enum members included */
+    constants: HashMap<String, HashMap<String, (JackType, Expression)>>,
+    /* enum declaring each enum member of a class */
+    enums: HashMap<String, HashMap<String, String>>,
+}
+
+impl ClassTable {
+    pub fn add_class(self: &mut Self, class: &Class) {
+        let mut routines: HashMap<String, RoutineSignature> = class
+            .routines
+            .iter()
+            .map(|routine| {
+                let signature = RoutineSignature {
+                    routine_type: routine.routine_type.clone(),
+                    parameters: routine.parameters.iter().map(|p| p.jack_type.clone()).collect(),
+                    return_type: routine.return_type.clone(),
+                };
+                (routine.name.clone(), signature)
+            })
+            .collect();
+        if class.has_default_constructor() {
+            let signature = RoutineSignature {
+                routine_type: RoutineType::Constructor,
+                parameters: vec![],
+                return_type: ReturnType::Type(JackType::Class(class.name.clone())),
+            };
+            routines.insert("new".into(), signature);
+        }
+
+        self.classes.insert(class.name.clone(), routines);
+
+        /* an enum member without a value is the previous member + 1, the first one 0 */
+        let mut constants: HashMap<String, (JackType, Expression)> = HashMap::new();
+        class.consts.iter().enumerate().for_each(|(i, constant)| {
+            let exp = match &constant.exp {
+                Some(exp) => exp.clone(),
+                None => match i.checked_sub(1).map(|previous| &class.consts[previous]) {
+                    Some(previous) if previous.declaration_span == constant.declaration_span => {
+                        Expression {
+                            term: Term::VarName(previous.name.clone(), constant.span.clone()),
+                            additional: vec![(Operation::Plus, Term::IntConst(1))],
+                        }
+                    }
+                    _ => Expression { term: Term::IntConst(0), additional: vec![] },
+                },
+            };
+            constants.entry(constant.name.clone()).or_insert((constant.jack_type.clone(), exp));
+        });
+        self.constants.insert(class.name.clone(), constants);
+
+        let members = class.consts.iter().filter_map(|constant| {
+            constant.enum_name.as_ref().map(|enum_name| (constant.name.clone(), enum_name.clone()))
+        });
+        self.enums.insert(class.name.clone(), members.collect());
+    }
+
+    pub fn contains_class(self: &Self, class_name: &str) -> bool {
+        self.classes.contains_key(class_name)
+    }
+
+    pub fn fetch_routine(self: &Self, class_name: &str, name: &str) -> Option<&RoutineSignature> {
+        self.classes.get(class_name).and_then(|routines| routines.get(name))
+    }
+
+    pub fn declares_enum(self: &Self, class_name: &str, enum_name: &str) -> bool {
+        self.enums.get(class_name).is_some_and(|members| members.values().any(|e| e == enum_name))
+    }
+
+    /// Class of the constant `qualifier.name` used within `class_name`: the class itself
+    /// when `qualifier` is one of its enums, None if `name` is not a member of it, and
+    /// otherwise the class named `qualifier`
+    pub fn constant_class<'b>(
+        self: &Self,
+        class_name: &'b str,
+        qualifier: &'b str,
+        name: &str,
+    ) -> Option<&'b str> {
+        if !self.declares_enum(class_name, qualifier) {
+            return Some(qualifier);
+        }
+
+        let enum_name = self.enums.get(class_name).and_then(|members| members.get(name))?;
+        (enum_name == qualifier).then_some(class_name)
+    }
+
+    /// Symbol entry of `qualifier.name` used within `class_name`, see `constant_class`
+    pub fn fetch_qualified_constant(
+        self: &Self,
+        class_name: &str,
+        qualifier: &str,
+        name: &str,
+    ) -> Option<SymbolEntry> {
+        self.fetch_constant(self.constant_class(class_name, qualifier, name)?, name)
+    }
+
+    /// Symbol entry of a constant, its value being 0 when it cannot be computed
+    pub fn fetch_constant(self: &Self, class_name: &str, name: &str) -> Option<SymbolEntry> {
+        let (jack_type, _) = self.constants.get(class_name)?.get(name)?;
+        let value = self.constant_value(class_name, name).unwrap_or(0);
+
+        Some(SymbolEntry { kind: Kind::Constant, jtype: jack_type.clone(), index: value as u16 })
+    }
+
+    /// Value of a constant, as a 16 bit word: `-1` and `0xFFFF` are the same value
+    pub fn constant_value(self: &Self, class_name: &str, name: &str) -> Result<i16, ConstantError> {
+        self.evaluate_constant(class_name, name, &mut vec![])
+    }
+
+    /// Value of a term made of literals and constants, names being constants of `class_name`
+    pub fn term_value(self: &Self, class_name: &str, term: &Term) -> Result<i16, ConstantError> {
+        self.evaluate_term(class_name, term, &mut vec![])
+    }
+
+    /* `pending` holds the constants being evaluated, which may not refer to themselves */
+    fn evaluate_constant(
+        self: &Self,
+        class_name: &str,
+        name: &str,
+        pending: &mut Vec<(String, String)>,
+    ) -> Result<i16, ConstantError> {
+        let Some((_, exp)) = self.constants.get(class_name).and_then(|c| c.get(name)) else {
+            return Err(ConstantError::NotConstant);
+        };
+
+        let key = (class_name.to_string(), name.to_string());
+        if pending.contains(&key) {
+            return Err(ConstantError::Cycle(key.0, key.1));
+        }
+        pending.push(key);
+        let value = self.evaluate(class_name, exp, pending);
+        pending.pop();
+
+        value
+    }
+
+    /* Left to right, with the wrapping arithmetic of the Hack platform */
+    fn evaluate(
+        self: &Self,
+        class_name: &str,
+        exp: &Expression,
+        pending: &mut Vec<(String, String)>,
+    ) -> Result<i16, ConstantError> {
+        let first = self.evaluate_term(class_name, &exp.term, pending)?;
+
+        exp.additional.iter().try_fold(first, |acc, (op, term)| {
+            let val = self.evaluate_term(class_name, term, pending)?;
+            apply(op, acc, val)
+        })
+    }
+
+    fn evaluate_term(
+        self: &Self,
+        class_name: &str,
+        term: &Term,
+        pending: &mut Vec<(String, String)>,
+    ) -> Result<i16, ConstantError> {
+        match term {
+            Term::IntConst(val) => Ok(*val as u16 as i16),
+            Term::CharConst(c) => {
+                hack_code(*c).map(|code| code as i16).ok_or(ConstantError::NotConstant)
+            }
+            Term::KeywordConst(val, _) if val == "true" => Ok(-1),
+            Term::KeywordConst(val, _) if val == "false" => Ok(0),
+            Term::VarName(name, _) => self.evaluate_constant(class_name, name, pending),
+            Term::ClassConst { class_name: qualifier, name, .. } => {
+                match self.constant_class(class_name, qualifier, name) {
+                    Some(class_name) => self.evaluate_constant(class_name, name, pending),
+                    None => Err(ConstantError::NotConstant),
+                }
+            }
+            Term::Expression(exp) => self.evaluate(class_name, exp, pending),
+            Term::Binary { left, op, right } => {
+                let acc = self.evaluate_term(class_name, left, pending)?;
+                let val = self.evaluate_term(class_name, right, pending)?;
+                apply(op, acc, val)
+            }
+            Term::Unary { op: Operation::Minus, term } => {
+                self.evaluate_term(class_name, term, pending).map(|val| val.wrapping_neg())
+            }
+            Term::Unary { op: Operation::Not, term } => {
+                self.evaluate_term(class_name, term, pending).map(|val| !val)
+            }
+            _ => Err(ConstantError::NotConstant),
+        }
+    }
+}
+
+/* Wrapping arithmetic of the Hack platform, comparisons being -1 when true */
+fn apply(op: &Operation, acc: i16, val: i16) -> Result<i16, ConstantError> {
+    Ok(match op {
+        Operation::Plus => acc.wrapping_add(val),
+        Operation::Minus => acc.wrapping_sub(val),
+        Operation::Multiply => acc.wrapping_mul(val),
+        Operation::Divide if val == 0 => return Err(ConstantError::DivisionByZero),
+        Operation::Divide => acc.wrapping_div(val),
+        Operation::And => acc & val,
+        Operation::Or => acc | val,
+        Operation::LessThan => -((acc < val) as i16),
+        Operation::GreaterThan => -((acc > val) as i16),
+        Operation::Equals => -((acc == val) as i16),
+        Operation::LogicalAnd => -((acc != 0 && val != 0) as i16),
+        Operation::LogicalOr => -((acc != 0 || val != 0) as i16),
+        Operation::Not => return Err(ConstantError::NotConstant),
+    })
+}
+
+/// Why a term has no value at compile time
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConstantError {
+    /// A variable, call, string, `null` or `this`, or a name no class declares as a constant
+    NotConstant,
+    /// A constant whose value depends on itself: its class and name
+    Cycle(String, String),
+    DivisionByZero,
+}
+
+/// Kind, parameter types and return type of a subroutine, as seen by its callers
+#[derive(Debug, PartialEq, Clone)]
+pub struct RoutineSignature {
+    pub routine_type: RoutineType,
+    pub parameters: Vec<JackType>,
+    pub return_type: ReturnType,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Span;
 
     #[test]
     fn handle_class_symbol_tables() {
@@ -152,18 +451,19 @@ mod tests {
                 var_type: ClassVarType::Static,
                 jack_type: JackType::Int,
                 name: "a".into(),
+                span: Span::default(),
+                declaration_span: Span::default(),
+                exp: None,
+                doc: None,
             }],
+            consts: vec![],
             routines: vec![],
         };
 
         let expected_class_symbols = ClassSymbols {
             entries: SymbolsTable::from([(
                 "a".into(),
-                SymbolEntry {
-                    jtype: JackType::Int,
-                    kind: Kind::Static,
-                    index: 0,
-                },
+                SymbolEntry { jtype: JackType::Int, kind: Kind::Static, index: 0 },
             )]),
             field_counter: 0,
             static_counter: 1,
@@ -178,6 +478,7 @@ mod tests {
         let class = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Method,
                 return_type: ReturnType::Void,
@@ -186,19 +487,25 @@ mod tests {
                     Parameter {
                         name: "b".into(),
                         jack_type: JackType::Char,
+                        span: Span::default(),
                     },
                     Parameter {
                         name: "c".into(),
                         jack_type: JackType::Char,
+                        span: Span::default(),
                     },
                 ],
                 body: SubroutineBody {
                     vars: vec![VarDec {
                         jack_type: JackType::Boolean,
                         name: "d".into(),
+                        span: Span::default(),
+                        declaration_span: Span::default(),
                     }],
-                    statements: vec![Statement::Return(None)],
+                    statements: vec![Statement::Return(None, Span::default())],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
@@ -212,33 +519,14 @@ mod tests {
                         index: 0,
                     },
                 ),
-                (
-                    "b".into(),
-                    SymbolEntry {
-                        jtype: JackType::Char,
-                        kind: Kind::Argument,
-                        index: 1,
-                    },
-                ),
-                (
-                    "c".into(),
-                    SymbolEntry {
-                        jtype: JackType::Char,
-                        kind: Kind::Argument,
-                        index: 2,
-                    },
-                ),
-                (
-                    "d".into(),
-                    SymbolEntry {
-                        jtype: JackType::Boolean,
-                        kind: Kind::Local,
-                        index: 0,
-                    },
-                ),
+                ("b".into(), SymbolEntry { jtype: JackType::Char, kind: Kind::Argument, index: 1 }),
+                ("c".into(), SymbolEntry { jtype: JackType::Char, kind: Kind::Argument, index: 2 }),
+                ("d".into(), SymbolEntry { jtype: JackType::Boolean, kind: Kind::Local, index: 0 }),
             ]),
+            local_count: 1,
             local_counter: 1,
             argument_counter: 3,
+            blocks: vec![],
         };
 
         let actual_routine_symbols =
@@ -246,4 +534,109 @@ mod tests {
 
         assert_eq!(expected_routine_symbols, actual_routine_symbols);
     }
+
+    #[test]
+    fn handle_class_table() {
+        let class = Class {
+            name: "Example".into(),
+            vars: vec![],
+            consts: vec![],
+            routines: vec![SubroutineDec {
+                routine_type: RoutineType::Function,
+                return_type: ReturnType::Type(JackType::Int),
+                name: "sum".into(),
+                parameters: vec![
+                    Parameter { name: "a".into(), jack_type: JackType::Int, span: Span::default() },
+                    Parameter {
+                        name: "b".into(),
+                        jack_type: JackType::Char,
+                        span: Span::default(),
+                    },
+                ],
+                body: SubroutineBody::default(),
+                span: Span::default(),
+                doc: None,
+            }],
+        };
+
+        let mut class_table = ClassTable::default();
+        class_table.add_class(&class);
+
+        assert!(class_table.contains_class("Example"));
+        assert!(!class_table.contains_class("example"));
+        assert_eq!(None, class_table.fetch_routine("Example", "product"));
+        assert_eq!(
+            Some(&RoutineSignature {
+                routine_type: RoutineType::Function,
+                parameters: vec![JackType::Int, JackType::Char],
+                return_type: ReturnType::Type(JackType::Int),
+            }),
+            class_table.fetch_routine("Example", "sum")
+        );
+    }
+
+    #[test]
+    fn reuse_local_slots_across_blocks() {
+        let var = |name: &str| VarDec {
+            jack_type: JackType::Int,
+            name: name.into(),
+            span: Span::default(),
+            declaration_span: Span::default(),
+        };
+        let condition = || Expression { term: Term::IntConst(1), additional: vec![] };
+        /*
+         * var int a;
+         * if (1) { var int x, y; } else { var int z; while (1) { var int w; } }
+         * var int b;
+         */
+        let routine = SubroutineDec {
+            routine_type: RoutineType::Function,
+            return_type: ReturnType::Void,
+            name: "run".into(),
+            parameters: vec![],
+            body: SubroutineBody {
+                vars: vec![var("a")],
+                statements: vec![
+                    Statement::If {
+                        exp: condition(),
+                        statements: vec![Statement::Var(vec![var("x"), var("y")])],
+                        else_statements: Some(vec![
+                            Statement::Var(vec![var("z")]),
+                            Statement::While {
+                                exp: condition(),
+                                statements: vec![Statement::Var(vec![var("w")])],
+                                span: Span::default(),
+                            },
+                        ]),
+                        span: Span::default(),
+                    },
+                    Statement::Var(vec![var("b")]),
+                ],
+            },
+            span: Span::default(),
+            doc: None,
+        };
+        let index =
+            |symbols: &RoutineSymbols, name: &str| symbols.entries.get(name).map(|e| e.index);
+
+        let mut symbols = RoutineSymbols::new(&routine, &"Example".into());
+        assert_eq!(3, symbols.local_count);
+
+        symbols.enter_block();
+        symbols.add_vars(&[var("x"), var("y")]);
+        assert_eq!((Some(1), Some(2)), (index(&symbols, "x"), index(&symbols, "y")));
+        symbols.exit_block();
+        assert_eq!(None, index(&symbols, "x"));
+
+        symbols.enter_block();
+        symbols.add_vars(&[var("z")]);
+        symbols.enter_block();
+        symbols.add_vars(&[var("w")]);
+        assert_eq!((Some(1), Some(2)), (index(&symbols, "z"), index(&symbols, "w")));
+        symbols.exit_block();
+        symbols.exit_block();
+
+        symbols.add_vars(&[var("b")]);
+        assert_eq!((Some(0), Some(1)), (index(&symbols, "a"), index(&symbols, "b")));
+    }
 }
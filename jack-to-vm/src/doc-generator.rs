@@ -0,0 +1,394 @@
+use std::collections::HashSet;
+
+use super::code_generator::symbols::ClassTable;
+use super::grammar::*;
+use super::xml::escape;
+
+/// Format of the API documentation pages
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl DocFormat {
+    pub fn extension(self: &Self) -> &str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+        }
+    }
+}
+
+impl TryFrom<&str> for DocFormat {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "md" | "markdown" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Derivation trees -> API documentation: one page per class, along with an index page
+///
+/// Pages are returned as (file name, content) pairs, `Math.md` or `Math.html` for instance.
+/// Subroutines are listed by kind with their signature, doc comment, parameter types
+/// and return type, class variables with their type and doc comment, constants and enums
+/// with their value. Types naming one of the documented classes link to its page.
+pub fn generate(classes: &[Class], format: DocFormat) -> Vec<(String, String)> {
+    let mut classes: Vec<&Class> = classes.iter().collect();
+    classes.sort_by(|a, b| a.name.cmp(&b.name));
+    let class_names: Vec<&str> = classes.iter().map(|class| class.name.as_str()).collect();
+    let documented: HashSet<&str> = class_names.iter().copied().collect();
+    let mut class_table = ClassTable::default();
+    classes.iter().for_each(|class| class_table.add_class(class));
+
+    let mut pages: Vec<(String, String)> = classes
+        .iter()
+        .map(|class| {
+            let mut page = Page { format, documented: &documented, lines: vec![] };
+            page.class(class, &class_table);
+            (format!("{}.{}", class.name, format.extension()), page.finish(&class.name))
+        })
+        .collect();
+
+    let mut index = Page { format, documented: &documented, lines: vec![] };
+    index.heading(1, "API documentation");
+    index.list(class_names.iter().map(|name| index.link(name)).collect());
+    pages.push((format!("index.{}", format.extension()), index.finish("API documentation")));
+
+    pages
+}
+
+struct Page<'a> {
+    format: DocFormat,
+    documented: &'a HashSet<&'a str>,
+    lines: Vec<String>,
+}
+
+impl<'a> Page<'a> {
+    fn class(self: &mut Self, class: &Class, class_table: &ClassTable) {
+        if self.format == DocFormat::Html {
+            self.lines.push(format!("<p>{}</p>", self.link("index")));
+        }
+        self.heading(1, &format!("class {}", class.name));
+
+        if !class.vars.is_empty() {
+            self.heading(2, "Variables");
+            class.vars.iter().for_each(|var| {
+                let kind = match var.var_type {
+                    ClassVarType::Static => "static",
+                    ClassVarType::Field => "field",
+                };
+                self.heading(3, &var.name);
+                self.code(&format!("{kind} {} {}", var.jack_type, var.name));
+                self.doc(&var.doc);
+            });
+        }
+
+        if !class.consts.is_empty() {
+            self.heading(2, "Constants");
+            self.constants(class, class_table);
+        }
+
+        [
+            (RoutineType::Constructor, "Constructors"),
+            (RoutineType::Function, "Functions"),
+            (RoutineType::Method, "Methods"),
+        ]
+        .iter()
+        .for_each(|(routine_type, title)| {
+            let routines: Vec<&SubroutineDec> =
+                class.routines.iter().filter(|r| r.routine_type == *routine_type).collect();
+
+            if !routines.is_empty() {
+                self.heading(2, title);
+                routines.into_iter().for_each(|routine| self.routine(routine));
+            }
+        });
+    }
+
+    /* Values referring to classes which are not documented are left out */
+    fn constants(self: &mut Self, class: &Class, class_table: &ClassTable) {
+        let value =
+            |constant: &ConstDec| match class_table.constant_value(&class.name, &constant.name) {
+                Ok(value) => format!("{} = {value}", constant.name),
+                Err(_) => constant.name.clone(),
+            };
+
+        class.consts.chunk_by(|a, b| a.declaration_span == b.declaration_span).for_each(|decs| {
+            match &decs[0].enum_name {
+                Some(enum_name) => {
+                    self.heading(3, enum_name);
+                    let members: Vec<String> =
+                        decs.iter().map(|member| format!("    {},", value(member))).collect();
+                    self.code(&format!("enum {enum_name} {{\n{}\n}}", members.join("\n")));
+
+                    let items: Vec<String> = decs
+                        .iter()
+                        .filter_map(|member| {
+                            let doc = match self.format {
+                                DocFormat::Markdown => member.doc.clone()?,
+                                DocFormat::Html => escape(member.doc.as_ref()?),
+                            };
+                            Some(format!("{}: {doc}", self.inline_code(&member.name)))
+                        })
+                        .collect();
+                    if !items.is_empty() {
+                        self.list(items);
+                    }
+                }
+                None => {
+                    let constant = &decs[0];
+                    self.heading(3, &constant.name);
+                    self.code(&format!("const {} {}", constant.jack_type, value(constant)));
+                    self.doc(&constant.doc);
+                }
+            }
+        });
+    }
+
+    fn routine(self: &mut Self, routine: &SubroutineDec) {
+        let params: Vec<String> = routine
+            .parameters
+            .iter()
+            .map(|param| format!("{} {}", param.jack_type, param.name))
+            .collect();
+
+        self.heading(3, &routine.name);
+        self.code(&format!(
+            "{} {} {}({})",
+            routine.routine_type,
+            routine.return_type,
+            routine.name,
+            params.join(", ")
+        ));
+        self.doc(&routine.doc);
+
+        if !routine.parameters.is_empty() {
+            self.paragraph("Parameters:");
+            let items = routine
+                .parameters
+                .iter()
+                .map(|param| {
+                    format!(
+                        "{}: {}",
+                        self.inline_code(&param.name),
+                        self.type_ref(&param.jack_type)
+                    )
+                })
+                .collect();
+            self.list(items);
+        }
+
+        match &routine.return_type {
+            ReturnType::Void => self.paragraph("Returns nothing."),
+            ReturnType::Type(jack_type) => {
+                let returns = format!("Returns {}.", self.type_ref(jack_type));
+                self.paragraph(&returns)
+            }
+        }
+    }
+
+    fn heading(self: &mut Self, level: usize, text: &str) {
+        match self.format {
+            DocFormat::Markdown => self.lines.push(format!("{} {text}\n", "#".repeat(level))),
+            DocFormat::Html => self.lines.push(format!("<h{level}>{}</h{level}>", escape(text))),
+        }
+    }
+
+    fn code(self: &mut Self, code: &str) {
+        match self.format {
+            DocFormat::Markdown => self.lines.push(format!("```jack\n{code}\n```\n")),
+            DocFormat::Html => self.lines.push(format!("<pre><code>{}</code></pre>", escape(code))),
+        }
+    }
+
+    /* Doc comments are Markdown already, paragraphs being separated by blank lines */
+    fn doc(self: &mut Self, doc: &Option<String>) {
+        let Some(doc) = doc else { return };
+
+        match self.format {
+            DocFormat::Markdown => self.lines.push(format!("{doc}\n")),
+            DocFormat::Html => doc.split("\n\n").for_each(|paragraph| {
+                self.lines.push(format!("<p>{}</p>", escape(paragraph)));
+            }),
+        }
+    }
+
+    /* Text already rendered in the page format */
+    fn paragraph(self: &mut Self, text: &str) {
+        match self.format {
+            DocFormat::Markdown => self.lines.push(format!("{text}\n")),
+            DocFormat::Html => self.lines.push(format!("<p>{text}</p>")),
+        }
+    }
+
+    fn list(self: &mut Self, items: Vec<String>) {
+        match self.format {
+            DocFormat::Markdown => {
+                items.iter().for_each(|item| self.lines.push(format!("- {item}")));
+                self.lines.push(String::new());
+            }
+            DocFormat::Html => {
+                self.lines.push("<ul>".to_string());
+                items.iter().for_each(|item| self.lines.push(format!("<li>{item}</li>")));
+                self.lines.push("</ul>".to_string());
+            }
+        }
+    }
+
+    fn inline_code(self: &Self, code: &str) -> String {
+        match self.format {
+            DocFormat::Markdown => format!("`{code}`"),
+            DocFormat::Html => format!("<code>{}</code>", escape(code)),
+        }
+    }
+
+    fn type_ref(self: &Self, jack_type: &JackType) -> String {
+        match jack_type {
+            JackType::Class(class_name) if self.documented.contains(class_name.as_str()) => {
+                self.link(class_name)
+            }
+            jack_type => self.inline_code(&jack_type.to_string()),
+        }
+    }
+
+    fn link(self: &Self, page: &str) -> String {
+        let extension = self.format.extension();
+        match self.format {
+            DocFormat::Markdown => format!("[{page}]({page}.{extension})"),
+            DocFormat::Html => format!("<a href=\"{page}.{extension}\">{}</a>", escape(page)),
+        }
+    }
+
+    fn finish(self: Self, title: &str) -> String {
+        match self.format {
+            DocFormat::Markdown => self.lines.join("\n"),
+            DocFormat::Html => format!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+                escape(title),
+                self.lines.join("\n")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::syntax_analyzer;
+    use super::*;
+
+    const POINT: &str = "class Point {
+    /** Horizontal position */
+    field int x;
+
+    /** Creates a point at `ax` */
+    constructor Point new(int ax) { let x = ax; return this; }
+
+    /**
+     * Distance between both points.
+     *
+     * Rounded down.
+     */
+    method int distance(Point other) { return x - other.x(); }
+
+    method void draw() { return; }
+}";
+
+    fn pages(format: DocFormat) -> Vec<(String, String)> {
+        let class = syntax_analyzer::run(POINT.into(), "Point.jack").unwrap();
+        generate(&[class], format)
+    }
+
+    #[test]
+    fn generate_markdown_pages() {
+        let pages = pages(DocFormat::Markdown);
+
+        assert_eq!(
+            (
+                "Point.md".to_string(),
+                "# class Point
+
+## Variables
+
+### x
+
+```jack
+field int x
+```
+
+Horizontal position
+
+## Constructors
+
+### new
+
+```jack
+constructor Point new(int ax)
+```
+
+Creates a point at `ax`
+
+Parameters:
+
+- `ax`: `int`
+
+Returns [Point](Point.md).
+
+## Methods
+
+### distance
+
+```jack
+method int distance(Point other)
+```
+
+Distance between both points.
+
+Rounded down.
+
+Parameters:
+
+- `other`: [Point](Point.md)
+
+Returns `int`.
+
+### draw
+
+```jack
+method void draw()
+```
+
+Returns nothing.
+"
+                .to_string()
+            ),
+            pages[0]
+        );
+        assert_eq!(
+            ("index.md".to_string(), "# API documentation\n\n- [Point](Point.md)\n".to_string()),
+            pages[1]
+        );
+    }
+
+    #[test]
+    fn generate_html_pages() {
+        let pages = pages(DocFormat::Html);
+        let point = &pages[0].1;
+
+        assert!(point.starts_with(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Point</title>"
+        ));
+        assert!(point.contains("<p><a href=\"index.html\">index</a></p>\n<h1>class Point</h1>"));
+        assert!(point.contains(
+            "<h3>distance</h3>\n<pre><code>method int distance(Point other)</code></pre>\n<p>Distance between both points.</p>\n<p>Rounded down.</p>\n<p>Parameters:</p>\n<ul>\n<li><code>other</code>: <a href=\"Point.html\">Point</a></li>\n</ul>\n<p>Returns <code>int</code>.</p>"
+        ));
+        assert_eq!("index.html", pages[1].0);
+        assert!(pages[1].1.contains("<ul>\n<li><a href=\"Point.html\">Point</a></li>\n</ul>"));
+        assert_eq!(Ok(DocFormat::Html), DocFormat::try_from("html"));
+    }
+}
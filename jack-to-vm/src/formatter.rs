@@ -0,0 +1,861 @@
+use super::grammar::*;
+use super::syntax_analyzer::tokenizer::{Span, Token, TokenKind, escape};
+
+const INDENTATION: &str = "    ";
+
+/// Tokens, comments included, and the derivation tree built from them -> canonical source
+///
+/// The derivation tree decides the layout: one declaration or statement per line,
+/// nested blocks indented by four spaces, single spaces around binary operations and
+/// after commas. The tokens are printed as found in the source, in the order the tree
+/// expects them, and comments are printed back right before the token following them:
+/// - on the line of the previous token when they were trailing it
+/// - on their own lines, indented as the code they precede, otherwise
+///
+/// Single blank lines between declarations and statements are kept, subroutines are
+/// always separated by one.
+pub fn format(tokens: Vec<Token>, class: &Class) -> String {
+    let (comments, tokens): (Vec<Token>, Vec<Token>) =
+        tokens.into_iter().partition(|token| matches!(token.kind, TokenKind::Comment(_)));
+
+    let mut formatter = Formatter {
+        tokens,
+        comments,
+        token_index: 0,
+        comment_index: 0,
+        last_line: 0,
+        lines: vec![],
+        current: String::new(),
+        depth: 0,
+    };
+    formatter.format_class(class);
+    formatter.finish()
+}
+
+enum BlankLine {
+    /* kept when found in the source */
+    Preserve,
+    Force,
+    Never,
+}
+
+struct Formatter {
+    tokens: Vec<Token>,
+    comments: Vec<Token>,
+    token_index: usize,
+    comment_index: usize,
+    /* source line the last printed token or comment ends at */
+    last_line: usize,
+    lines: Vec<String>,
+    current: String,
+    depth: usize,
+}
+
+impl Formatter {
+    /* 'class' className '{' (classVarDec | constDec | enumDec)* subroutineDec* '}' */
+    fn format_class(self: &mut Self, class: &Class) {
+        self.new_line(BlankLine::Preserve);
+        self.token();
+        self.space();
+        self.token();
+        self.space();
+        self.token();
+        self.depth += 1;
+
+        class.declarations().into_iter().for_each(|declaration| {
+            self.new_line(BlankLine::Preserve);
+            match declaration {
+                ClassDeclaration::Vars(decs) => self.class_var_dec(decs),
+                ClassDeclaration::Consts(decs) if decs[0].enum_name.is_some() => {
+                    self.enum_dec(decs)
+                }
+                ClassDeclaration::Consts(decs) => self.const_dec(&decs[0]),
+            }
+        });
+
+        class.routines.iter().for_each(|routine| self.format_subroutine_dec(routine));
+
+        self.close_block();
+    }
+
+    /* ('constructor'|'function'|'method') ('void'|type) subroutineName '(' parameterList ')' subroutineBody */
+    fn format_subroutine_dec(self: &mut Self, routine: &SubroutineDec) {
+        self.new_line(BlankLine::Force);
+        self.token();
+        self.space();
+        self.token();
+        self.space();
+        self.token();
+
+        self.token();
+        routine.parameters.iter().enumerate().for_each(|(i, _)| {
+            if i > 0 {
+                self.token();
+                self.space();
+            }
+            self.token();
+            self.space();
+            self.token();
+        });
+        self.token();
+
+        self.space();
+        self.token();
+        self.depth += 1;
+
+        routine.body.vars.chunk_by(|a, b| a.declaration_span == b.declaration_span).for_each(
+            |decs| {
+                self.new_line(BlankLine::Preserve);
+                self.var_dec(decs.len());
+            },
+        );
+        routine.body.statements.iter().for_each(|statement| self.format_statement(statement));
+
+        self.close_block();
+    }
+
+    /* ('static'|'field') type varName ('=' expression)? (',' varName ('=' expression)?)* ';' */
+    fn class_var_dec(self: &mut Self, decs: &[ClassVarDec]) {
+        self.token();
+        self.space();
+        self.token();
+        self.space();
+        decs.iter().enumerate().for_each(|(i, dec)| {
+            if i > 0 {
+                self.token();
+                self.space();
+            }
+            self.token();
+            if let Some(exp) = &dec.exp {
+                self.space();
+                self.token();
+                self.space();
+                self.format_expression(exp);
+            }
+        });
+        self.token();
+    }
+
+    /* 'var' type varName (',' varName)* ';' */
+    fn var_dec(self: &mut Self, var_count: usize) {
+        self.token();
+        self.space();
+        self.token();
+        self.space();
+        for i in 0..var_count {
+            if i > 0 {
+                self.token();
+                self.space();
+            }
+            self.token();
+        }
+        self.token();
+    }
+
+    /* 'const' type constName '=' expression ';' */
+    fn const_dec(self: &mut Self, constant: &ConstDec) {
+        for _ in 0..3 {
+            self.token();
+            self.space();
+        }
+        self.token();
+        self.space();
+        self.format_expression(constant.exp.as_ref().unwrap());
+        self.token();
+    }
+
+    /* 'enum' enumName '{' constName ('=' expression)? (',' constName ('=' expression)?)* ','? '}'
+     * one member per line */
+    fn enum_dec(self: &mut Self, members: &[ConstDec]) {
+        self.token();
+        self.space();
+        self.token();
+        self.space();
+        self.token();
+        self.depth += 1;
+
+        members.iter().for_each(|member| {
+            self.new_line(BlankLine::Preserve);
+            self.token();
+            if let Some(exp) = &member.exp {
+                self.space();
+                self.token();
+                self.space();
+                self.format_expression(exp);
+            }
+            if matches!(&self.tokens[self.token_index].kind, TokenKind::Symbol(val) if val == ",") {
+                self.token();
+            }
+        });
+
+        self.close_block();
+    }
+
+    fn format_statement(self: &mut Self, statement: &Statement) {
+        self.new_line(BlankLine::Preserve);
+        self.statement(statement);
+    }
+
+    /* Tokens of the statement, on the current line */
+    fn statement(self: &mut Self, statement: &Statement) {
+        match statement {
+            Statement::Var(vars) => self.var_dec(vars.len()),
+            /* 'let' varName ('[' expression ']')? '=' expression ';' */
+            Statement::Let { array_access, exp, .. } => {
+                self.token();
+                self.space();
+                self.token();
+                if let Some(index_exp) = array_access {
+                    self.token();
+                    self.format_expression(index_exp);
+                    self.token();
+                }
+                self.space();
+                self.token();
+                self.space();
+                self.format_expression(exp);
+                self.token();
+            }
+            /* 'let' varName ('[' expression ']')? (op '=' expression | '++' | '--') ';' */
+            Statement::Update { array_access, exp, .. } => {
+                self.token();
+                self.space();
+                self.token();
+                if let Some(index_exp) = array_access {
+                    self.token();
+                    self.format_expression(index_exp);
+                    self.token();
+                }
+                if let Some(exp) = exp {
+                    self.space();
+                    self.token();
+                    self.token();
+                    self.space();
+                    self.format_expression(exp);
+                } else {
+                    self.token();
+                    self.token();
+                }
+                self.token();
+            }
+            /* 'if' '(' expression ')' '{' statements '}' ('else' ('{' statements '}' | ifStatement))? */
+            Statement::If { exp, statements, else_statements, .. } => {
+                self.token();
+                self.space();
+                self.condition(exp);
+                self.block(statements);
+                if let Some(else_statements) = else_statements {
+                    self.space();
+                    self.token();
+                    self.space();
+                    /* `else if` chains stay flat, the nested if alone making the else branch */
+                    match &self.tokens[self.token_index].kind {
+                        TokenKind::Keyword(keyword) if keyword == "if" => {
+                            self.statement(&else_statements[0])
+                        }
+                        _ => self.block(else_statements),
+                    }
+                }
+            }
+            /* 'while' '(' expression ')' '{' statements '}' */
+            Statement::While { exp, statements, .. } => {
+                self.token();
+                self.space();
+                self.condition(exp);
+                self.block(statements);
+            }
+            /* 'switch' '(' expression ')' '{' (('case' term ':')+ statements)*
+             * ('default' ':' statements)? '}', labels on their own lines above the statements */
+            Statement::Switch { exp, cases, default, .. } => {
+                self.token();
+                self.space();
+                self.condition(exp);
+                self.token();
+                self.depth += 1;
+                cases.iter().for_each(|case| {
+                    case.values.iter().for_each(|value| {
+                        self.new_line(BlankLine::Preserve);
+                        self.token();
+                        self.space();
+                        self.format_term(value);
+                        self.token();
+                    });
+                    self.labelled_statements(&case.statements);
+                });
+                if let Some(default) = default {
+                    self.new_line(BlankLine::Preserve);
+                    self.token();
+                    self.token();
+                    self.labelled_statements(default);
+                }
+                self.close_block();
+            }
+            /* 'for' '(' statement? expression? ';' statement? ')' '{' statements '}'
+             * init and step consume their terminating ';' and ')' */
+            Statement::For { init, exp, step, statements, .. } => {
+                self.token();
+                self.space();
+                self.token();
+                match init {
+                    Some(init) => self.statement(init),
+                    None => self.token(),
+                }
+                if let Some(exp) = exp {
+                    self.space();
+                    self.format_expression(exp);
+                }
+                self.token();
+                if let Some(step) = step {
+                    self.space();
+                    self.statement(step);
+                } else {
+                    self.token();
+                }
+                self.space();
+                self.block(statements);
+            }
+            /* ('break' | 'continue') ';' */
+            Statement::Break(_) | Statement::Continue(_) => {
+                self.token();
+                self.token();
+            }
+            /* 'return' expression? ';' */
+            Statement::Return(exp_opt, _) => {
+                self.token();
+                if let Some(exp) = exp_opt {
+                    self.space();
+                    self.format_expression(exp);
+                }
+                self.token();
+            }
+            /* 'do' subroutineCall ';' */
+            Statement::Do(call) => {
+                self.token();
+                self.space();
+                self.format_subroutine_call(call);
+                self.token();
+            }
+        }
+    }
+
+    /* '(' expression ')' */
+    fn condition(self: &mut Self, exp: &Expression) {
+        self.token();
+        self.format_expression(exp);
+        self.token();
+        self.space();
+    }
+
+    /* statements following a case label, one level deeper */
+    fn labelled_statements(self: &mut Self, statements: &[Statement]) {
+        self.depth += 1;
+        statements.iter().for_each(|statement| self.format_statement(statement));
+        self.depth -= 1;
+    }
+
+    /* '{' statements '}' */
+    fn block(self: &mut Self, statements: &[Statement]) {
+        self.token();
+        self.depth += 1;
+        statements.iter().for_each(|statement| self.format_statement(statement));
+        self.close_block();
+    }
+
+    /* Comments left before the closing '}' belong to the block, empty blocks stay on one line */
+    fn close_block(self: &mut Self) {
+        let closing_span = self.tokens[self.token_index].span.clone();
+        let is_empty = self.current.ends_with('{');
+
+        if !(is_empty && self.comment_before(&closing_span).is_none()) {
+            /* comments of an otherwise empty block go on their own lines */
+            if is_empty {
+                self.end_line();
+            }
+            self.comments_before(&closing_span);
+            self.depth -= 1;
+            self.new_line(BlankLine::Never);
+        } else {
+            self.depth -= 1;
+        }
+
+        self.token();
+    }
+
+    /* term (op term)* */
+    fn format_expression(self: &mut Self, exp: &Expression) {
+        self.format_term(&exp.term);
+        exp.additional.iter().for_each(|(_, term)| {
+            self.space();
+            self.token();
+            self.space();
+            self.format_term(term);
+        });
+    }
+
+    fn format_term(self: &mut Self, term: &Term) {
+        match term {
+            Term::IntConst(_)
+            | Term::StrConst(_)
+            | Term::CharConst(_)
+            | Term::KeywordConst(_, _)
+            | Term::VarName(_, _) => self.token(),
+            Term::ArrayAccess { exp, .. } => {
+                self.token();
+                self.token();
+                self.format_expression(exp);
+                self.token();
+            }
+            Term::Expression(exp) => {
+                self.token();
+                self.format_expression(exp);
+                self.token();
+            }
+            Term::ClassConst { .. } => {
+                self.token();
+                self.token();
+                self.token();
+            }
+            Term::Unary { term, .. } => {
+                self.token();
+                self.format_term(term);
+            }
+            Term::Binary { left, right, .. } => {
+                self.format_term(left);
+                self.space();
+                self.token();
+                self.space();
+                self.format_term(right);
+            }
+            Term::Call(call) => self.format_subroutine_call(call),
+        }
+    }
+
+    /* ((className|varName) '.')? subroutineName '(' expressionList ')' */
+    fn format_subroutine_call(self: &mut Self, call: &SubroutineCall) {
+        if call.callee.is_some() {
+            self.token();
+            self.token();
+        }
+        self.token();
+
+        self.token();
+        call.expressions.iter().enumerate().for_each(|(i, exp)| {
+            if i > 0 {
+                self.token();
+                self.space();
+            }
+            self.format_expression(exp);
+        });
+        self.token();
+    }
+
+    /* Starts the line of a declaration or statement, printing the comments preceding it */
+    fn new_line(self: &mut Self, blank_line: BlankLine) {
+        let Some(next_span) = self.tokens.get(self.token_index).map(|token| token.span.clone())
+        else {
+            return self.end_line();
+        };
+
+        /* comments on the line of the previous token stay there */
+        while let Some(comment) = self.comment_before(&next_span).cloned()
+            && comment.span.line == self.last_line
+        {
+            self.comment(&comment);
+            self.comment_index += 1;
+        }
+        self.end_line();
+
+        let next_line = self.comment_before(&next_span).map_or(next_span.line, |c| c.span.line);
+        let after_opening = self.lines.last().is_none_or(|line| line.ends_with('{'));
+        let has_blank_line = match blank_line {
+            BlankLine::Preserve => next_line > self.last_line + 1,
+            BlankLine::Force => true,
+            BlankLine::Never => false,
+        };
+
+        if has_blank_line && !after_opening {
+            self.lines.push(String::new());
+        }
+
+        self.comments_before(&next_span);
+    }
+
+    /* Next source token, preceded by the comments found before it */
+    fn token(self: &mut Self) {
+        let token = self.tokens[self.token_index].clone();
+        self.comments_before(&token.span);
+
+        if self.current.is_empty() {
+            self.current = INDENTATION.repeat(self.depth);
+        }
+        match &token.kind {
+            TokenKind::StrConst(val) => self.current.push_str(&format!("\"{}\"", escape(val, '"'))),
+            kind => self.current.push_str(&kind.to_string()),
+        }
+
+        self.last_line = token.span.line;
+        self.token_index += 1;
+    }
+
+    fn space(self: &mut Self) {
+        self.current.push(' ');
+    }
+
+    fn comment_before(self: &Self, span: &Span) -> Option<&Token> {
+        self.comments
+            .get(self.comment_index)
+            .filter(|comment| (comment.span.line, comment.span.column) < (span.line, span.column))
+    }
+
+    fn comments_before(self: &mut Self, span: &Span) {
+        while let Some(comment) = self.comment_before(span).cloned() {
+            self.comment(&comment);
+            self.comment_index += 1;
+        }
+    }
+
+    fn comment(self: &mut Self, comment: &Token) {
+        let text = comment.kind.to_string();
+        let is_trailing = !self.current.trim().is_empty() && comment.span.line == self.last_line;
+
+        if is_trailing {
+            self.current = format!("{} {text} ", self.current.trim_end());
+        } else {
+            if comment.span.line > self.last_line + 1 && self.last_line > 0 {
+                self.end_line();
+                if self.lines.last().is_some_and(|line| !line.ends_with('{') && !line.is_empty()) {
+                    self.lines.push(String::new());
+                }
+            }
+            self.end_line();
+
+            /* block comments are aligned on their first line, ` * ` prefixes included */
+            let indentation = INDENTATION.repeat(self.depth);
+            text.lines().enumerate().for_each(|(i, line)| match (i, line.trim_start()) {
+                (0, line) => self.lines.push(format!("{indentation}{line}")),
+                (_, line) if line.starts_with('*') => {
+                    self.lines.push(format!("{indentation} {line}"))
+                }
+                (_, line) => self.lines.push(format!("{indentation}{line}")),
+            });
+        }
+
+        self.last_line = comment.span.line + text.matches('\n').count();
+        /* nothing can follow a line comment on its line */
+        if text.starts_with("//") {
+            self.end_line();
+        }
+    }
+
+    fn end_line(self: &mut Self) {
+        if !self.current.trim().is_empty() {
+            self.lines.push(self.current.trim_end().to_string());
+        }
+        self.current.clear();
+    }
+
+    fn finish(mut self: Self) -> String {
+        self.end_line();
+        let trailing_comments: Vec<Token> = self.comments.drain(self.comment_index..).collect();
+        trailing_comments.iter().for_each(|comment| self.comment(comment));
+        self.end_line();
+
+        self.lines.join("\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::syntax_analyzer::{parser, tokenizer};
+
+    fn format(source: &str) -> String {
+        let tokens = tokenizer::tokenize_with_comments(source, "Main.jack").unwrap();
+        let code_tokens = tokens
+            .iter()
+            .filter(|token| !matches!(token.kind, tokenizer::TokenKind::Comment(_)))
+            .cloned()
+            .collect();
+        let class = parser::parse(code_tokens).unwrap();
+
+        super::format(tokens, &class)
+    }
+
+    #[test]
+    fn indent_canonically() {
+        let source = "class Main {
+\tfield int x,y;  static boolean done;
+\tfunction void main(int a,Array b) {
+  var int i;
+\t\tlet b[i+1]=-a*(i-Math.max(1,2));
+\t\tif (~done) { do Output.printString(\"a  b\"); } else {}
+\t\twhile (i<10) { let i = i+1; }
+\t\treturn;
+\t}
+    method int get() { return x; }
+}";
+
+        assert_eq!(
+            "class Main {
+    field int x, y;
+    static boolean done;
+
+    function void main(int a, Array b) {
+        var int i;
+        let b[i + 1] = -a * (i - Math.max(1, 2));
+        if (~done) {
+            do Output.printString(\"a  b\");
+        } else {}
+        while (i < 10) {
+            let i = i + 1;
+        }
+        return;
+    }
+
+    method int get() {
+        return x;
+    }
+}
+",
+            format(source)
+        );
+    }
+
+    #[test]
+    fn format_for_loops() {
+        let source = "class Main {
+    function void main() {
+        var int i;
+        for(let i=0;i<10;let i=i+1){if(i=5){break;}else{continue;}}
+        for ( ; ; ) { do Output.println() ; }
+        for (do Main.init();;do Main.step()) {}
+        return;
+    }
+}";
+
+        assert_eq!(
+            "class Main {
+    function void main() {
+        var int i;
+        for (let i = 0; i < 10; let i = i + 1) {
+            if (i = 5) {
+                break;
+            } else {
+                continue;
+            }
+        }
+        for (;;) {
+            do Output.println();
+        }
+        for (do Main.init();; do Main.step()) {}
+        return;
+    }
+}
+",
+            format(source)
+        );
+    }
+
+    #[test]
+    fn format_else_if_chains_and_switches() {
+        let source = "class Main {
+    function void main(int x) {
+        if(x=1){return;}else if(x=2){return;}else{if(x){return;}}
+        switch(x){case 1:case -2:do Output.println();break;
+        // fallback
+        default:return;}
+        switch (x) {}
+    }
+}";
+
+        assert_eq!(
+            "class Main {
+    function void main(int x) {
+        if (x = 1) {
+            return;
+        } else if (x = 2) {
+            return;
+        } else {
+            if (x) {
+                return;
+            }
+        }
+        switch (x) {
+            case 1:
+            case -2:
+                do Output.println();
+                break;
+            // fallback
+            default:
+                return;
+        }
+        switch (x) {}
+    }
+}
+",
+            format(source)
+        );
+    }
+
+    #[test]
+    fn format_initializers() {
+        let source = "class Main {
+    static int count=1,total ,max=count*2;
+    field Array items = Array.new(max);
+}";
+
+        assert_eq!(
+            "class Main {
+    static int count = 1, total, max = count * 2;
+    field Array items = Array.new(max);
+}
+",
+            format(source)
+        );
+    }
+
+    #[test]
+    fn format_block_declarations() {
+        let source = "class Main {
+    function void main(int x) {
+        var int a;
+        if (x) {var int b,c;
+        let b = 1;}
+        var char d;
+    }
+}";
+
+        assert_eq!(
+            "class Main {
+    function void main(int x) {
+        var int a;
+        if (x) {
+            var int b, c;
+            let b = 1;
+        }
+        var char d;
+    }
+}
+",
+            format(source)
+        );
+    }
+
+    #[test]
+    fn format_compound_assignments() {
+        let source = "class Main {
+    function void main(Array a, int x) {
+        let a[x]+=1;let x--;
+        for (let x = 0; x < 3; let x++) {let x|=x-1;}
+    }
+}";
+
+        assert_eq!(
+            "class Main {
+    function void main(Array a, int x) {
+        let a[x] += 1;
+        let x--;
+        for (let x = 0; x < 3; let x++) {
+            let x |= x - 1;
+        }
+    }
+}
+",
+            format(source)
+        );
+    }
+
+    #[test]
+    fn format_precedence_trees() {
+        let source = "// pragma precedence
+class Main {
+    function int run(int a) {
+        return a+a*2<(a-1)&&~a|1=a;
+    }
+}";
+
+        assert_eq!(
+            "// pragma precedence
+class Main {
+    function int run(int a) {
+        return a + a * 2 < (a - 1) && ~a | 1 = a;
+    }
+}
+",
+            format(source)
+        );
+    }
+
+    #[test]
+    fn format_constants_and_enums() {
+        let source = "class Keys {
+    static int last;
+    const int  MASK=0x7F|Keys.FLAG;
+    enum Arrow{LEFT=130,
+    // vertical
+    UP,DOWN}
+    enum Flag { FLAG = 256, }
+    function int up() { return Arrow.UP; }
+}";
+
+        assert_eq!(
+            "class Keys {
+    static int last;
+    const int MASK = 0x7F | Keys.FLAG;
+    enum Arrow {
+        LEFT = 130,
+        // vertical
+        UP,
+        DOWN
+    }
+    enum Flag {
+        FLAG = 256,
+    }
+
+    function int up() {
+        return Arrow.UP;
+    }
+}
+",
+            format(source)
+        );
+    }
+
+    #[test]
+    fn preserve_comments_and_blank_lines() {
+        let source = "// Main entry point
+class Main {
+\t/** Runs
+\t  * the program */
+\tfunction void main() {
+\t\tvar int i; // counter
+
+
+\t\tlet i = /* start */ 0;
+\t\twhile (true) {
+\t\t\t// nothing yet
+\t\t}
+\t\treturn;
+\t}
+}
+// end";
+
+        let expected = "// Main entry point
+class Main {
+    /** Runs
+     * the program */
+    function void main() {
+        var int i; // counter
+
+        let i = /* start */ 0;
+        while (true) {
+            // nothing yet
+        }
+        return;
+    }
+}
+// end
+";
+        assert_eq!(expected, format(source));
+        assert_eq!(expected, format(expected));
+    }
+}
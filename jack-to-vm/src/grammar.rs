@@ -1,17 +1,85 @@
-#[derive(Debug, PartialEq)]
+use std::fmt::Display;
+
+use crate::Span;
+
+/// Root of the derivation tree of a `.jack` file, which declares exactly one class
+#[derive(Debug, PartialEq, Clone)]
 pub struct Class {
     pub name: String,
     pub vars: Vec<ClassVarDec>,
+    pub consts: Vec<ConstDec>,
     pub routines: Vec<SubroutineDec>,
 }
 
+/// A declaration of class variables or constants: `field int x, y;` and the members
+/// of an enum each make a single declaration
 #[derive(Debug, PartialEq)]
+pub enum ClassDeclaration<'a> {
+    Vars(&'a [ClassVarDec]),
+    Consts(&'a [ConstDec]),
+}
+
+impl Class {
+    /// Declarations of the class variables and constants, in source order
+    pub fn declarations(self: &Self) -> Vec<ClassDeclaration<'_>> {
+        let vars = self.vars.chunk_by(|a, b| a.declaration_span == b.declaration_span);
+        let consts = self.consts.chunk_by(|a, b| a.declaration_span == b.declaration_span);
+
+        let mut declarations: Vec<(&Span, ClassDeclaration)> = vars
+            .map(|decs| (&decs[0].declaration_span, ClassDeclaration::Vars(decs)))
+            .chain(consts.map(|decs| (&decs[0].declaration_span, ClassDeclaration::Consts(decs))))
+            .collect();
+        declarations.sort_by_key(|(span, _)| (span.line, span.column));
+
+        declarations.into_iter().map(|(_, declaration)| declaration).collect()
+    }
+
+    /// A class with fields but no constructor, nor any subroutine named `new`, is given
+    /// a `constructor C new()` setting the fields from their initializers
+    pub fn has_default_constructor(self: &Self) -> bool {
+        self.vars.iter().any(|var| var.var_type == ClassVarType::Field)
+            && !self.routines.iter().any(|routine| {
+                routine.routine_type == RoutineType::Constructor || routine.name == "new"
+            })
+    }
+}
+
+/// A single `static` or `field` variable, `field int x, y;` declaring two of them
+#[derive(Debug, PartialEq, Clone)]
 pub struct ClassVarDec {
     pub var_type: ClassVarType,
     pub jack_type: JackType,
     pub name: String,
+    pub span: Span,
+    /* position of the `static`/`field` keyword, shared by the variables declared together */
+    pub declaration_span: Span,
+    /* `field int x = 0;` sets the field in every constructor, `static int n = 1;` sets the
+     * static before `Main.main` runs */
+    pub exp: Option<Expression>,
+    /// Text of the `/** */` comment right before the declaration, delimiters and `*` margins removed
+    pub doc: Option<String>,
 }
 
+/// A class constant: `const int NAME = expression;`, or a member of an `enum` declaration,
+/// `enum Direction { UP, DOWN }` declaring two `int` constants numbered from 0.
+///
+/// Constants take no storage, their uses being replaced by their value at compile time
+#[derive(Debug, PartialEq, Clone)]
+pub struct ConstDec {
+    pub jack_type: JackType,
+    pub name: String,
+    /* always given for a `const`, an enum member without one follows the previous member */
+    pub exp: Option<Expression>,
+    pub span: Span,
+    /* position of the `const` or `enum` keyword, shared by the members of an enum */
+    pub declaration_span: Span,
+    /* name of the enum declaring the constant */
+    pub enum_name: Option<String>,
+    /// Text of the `/** */` comment right before the declaration, delimiters and `*` margins removed
+    pub doc: Option<String>,
+}
+
+/// Lifetime of a class variable: shared by the class, or owned by each object
 #[derive(Debug, PartialEq, Clone)]
 pub enum ClassVarType {
     Static,
@@ -30,6 +98,7 @@ impl TryFrom<String> for ClassVarType {
     }
 }
 
+/// Type of a variable, parameter or return value
 #[derive(Debug, PartialEq, Clone)]
 pub enum JackType {
     Int,
@@ -51,16 +120,32 @@ impl TryFrom<String> for JackType {
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl Display for JackType {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Int => write!(f, "int"),
+            Self::Char => write!(f, "char"),
+            Self::Boolean => write!(f, "boolean"),
+            Self::Class(class_name) => write!(f, "{class_name}"),
+        }
+    }
+}
+
+/// A constructor, function or method along with its body
+#[derive(Debug, PartialEq, Clone)]
 pub struct SubroutineDec {
     pub routine_type: RoutineType,
     pub return_type: ReturnType,
     pub name: String,
     pub parameters: Vec<Parameter>,
     pub body: SubroutineBody,
+    pub span: Span,
+    /// Text of the `/** */` comment right before the declaration, delimiters and `*` margins removed
+    pub doc: Option<String>,
 }
 
-#[derive(Debug, PartialEq)]
+/// Type of the value returned by a subroutine
+#[derive(Debug, PartialEq, Clone)]
 pub enum ReturnType {
     Void,
     Type(JackType),
@@ -77,7 +162,17 @@ impl TryFrom<String> for ReturnType {
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl Display for ReturnType {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Void => write!(f, "void"),
+            Self::Type(jack_type) => write!(f, "{jack_type}"),
+        }
+    }
+}
+
+/// Kind of subroutine, methods receiving the object they are called on as first argument
+#[derive(Debug, PartialEq, Clone)]
 pub enum RoutineType {
     Constructor,
     Function,
@@ -97,68 +192,188 @@ impl TryFrom<String> for RoutineType {
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl Display for RoutineType {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Constructor => write!(f, "constructor"),
+            Self::Function => write!(f, "function"),
+            Self::Method => write!(f, "method"),
+        }
+    }
+}
+
+/// A parameter of a subroutine
+#[derive(Debug, PartialEq, Clone)]
 pub struct Parameter {
     pub jack_type: JackType,
     pub name: String,
+    pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+/// Local variables declared at the top of a subroutine, followed by its statements
+#[derive(Debug, PartialEq, Clone, Default)]
 pub struct SubroutineBody {
     pub vars: Vec<VarDec>,
     pub statements: Vec<Statement>,
 }
 
-impl Default for SubroutineBody {
-    fn default() -> Self {
-        Self { vars: vec![], statements: vec![] }
+impl SubroutineBody {
+    /// Locals declared at the top of the body, then by `var` statements in source order,
+    /// whatever block they belong to
+    pub fn all_vars(self: &Self) -> Vec<&VarDec> {
+        fn block_vars<'a>(statements: &'a [Statement], vars: &mut Vec<&'a VarDec>) {
+            statements.iter().for_each(|statement| match statement {
+                Statement::Var(decs) => vars.extend(decs),
+                statement => statement.blocks().into_iter().for_each(|b| block_vars(b, vars)),
+            });
+        }
+
+        let mut vars: Vec<&VarDec> = self.vars.iter().collect();
+        block_vars(&self.statements, &mut vars);
+        vars
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// A single local variable, `var int i, j;` declaring two of them
+#[derive(Debug, PartialEq, Clone)]
 pub struct VarDec {
     pub jack_type: JackType,
     pub name: String,
+    pub span: Span,
+    /* position of the `var` keyword, shared by the variables declared together */
+    pub declaration_span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+/// Statements of subroutine bodies, which may declare locals of their block along the way
+#[derive(Debug, PartialEq, Clone)]
 pub enum Statement {
-    Let { var_name: String, array_access: Option<Expression>, exp: Expression },
-    If { exp: Expression, statements: Vec<Statement>, else_statements: Option<Vec<Statement>> },
-    While { exp: Expression, statements: Vec<Statement> },
-    Return(Option<Expression>),
+    /* `var` declaration among statements, in scope until the end of the enclosing block */
+    Var(Vec<VarDec>),
+    Let {
+        var_name: String,
+        array_access: Option<Expression>,
+        exp: Expression,
+        span: Span,
+    },
+    /* `let x += exp;` reading and assigning the variable or array element once, its index
+     * being evaluated once too, and `let x++;` or `let x--;` with no expression */
+    Update {
+        var_name: String,
+        array_access: Option<Expression>,
+        op: Operation,
+        exp: Option<Expression>,
+        span: Span,
+    },
+    If {
+        exp: Expression,
+        statements: Vec<Statement>,
+        else_statements: Option<Vec<Statement>>,
+        span: Span,
+    },
+    While {
+        exp: Expression,
+        statements: Vec<Statement>,
+        span: Span,
+    },
+    /* `for (init; exp; step)`, where init and step are `let` or `do` statements
+     * and a missing condition loops until a break or return */
+    For {
+        init: Option<Box<Statement>>,
+        exp: Option<Expression>,
+        step: Option<Box<Statement>>,
+        statements: Vec<Statement>,
+        span: Span,
+    },
+    /* `switch (exp) { case 1: ... default: ... }`, without fallthrough between cases */
+    Switch {
+        exp: Expression,
+        cases: Vec<Case>,
+        default: Option<Vec<Statement>>,
+        span: Span,
+    },
+    /* exits the innermost loop or switch */
+    Break(Span),
+    /* goes on with the next iteration of the innermost loop */
+    Continue(Span),
+    Return(Option<Expression>, Span),
     Do(SubroutineCall),
 }
 
-#[derive(Debug, PartialEq)]
+impl Statement {
+    /// Blocks nested in the statement, each one a scope of its own for `var` declarations
+    pub fn blocks(self: &Self) -> Vec<&[Statement]> {
+        match self {
+            Statement::If { statements, else_statements, .. } => {
+                let mut blocks = vec![statements.as_slice()];
+                blocks.extend(else_statements.as_deref());
+                blocks
+            }
+            Statement::While { statements, .. } | Statement::For { statements, .. } => {
+                vec![statements]
+            }
+            Statement::Switch { cases, default, .. } => {
+                let mut blocks: Vec<&[Statement]> =
+                    cases.iter().map(|case| case.statements.as_slice()).collect();
+                blocks.extend(default.as_deref());
+                blocks
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// `case 1: case 2: statements` within a switch statement, its values sharing the statements
+#[derive(Debug, PartialEq, Clone)]
+pub struct Case {
+    /* terms of constant value: literals and class constants */
+    pub values: Vec<Term>,
+    pub statements: Vec<Statement>,
+    pub span: Span,
+}
+
+/// A term followed by binary operations, evaluated left to right without precedence.
+///
+/// In precedence mode, the whole expression is a single [`Term::Binary`] tree instead.
+#[derive(Debug, PartialEq, Clone)]
 pub struct Expression {
     pub term: Term,
     pub additional: Vec<(Operation, Term)>,
 }
 
-#[derive(Debug, PartialEq)]
+/// Operand of an expression
+#[derive(Debug, PartialEq, Clone)]
 pub enum Term {
     IntConst(usize),
     StrConst(String),
-    KeywordConst(String),
-    VarName(String),
-    ArrayAccess { var_name: String, exp: Box<Expression> },
+    CharConst(char),
+    KeywordConst(String, Span),
+    VarName(String, Span),
+    /* `Keys.LEFT`: a constant of another class, or of the current one, or `Dir.LEFT`:
+     * a member of an enum of the current class */
+    ClassConst { class_name: String, name: String, span: Span },
+    ArrayAccess { var_name: String, exp: Box<Expression>, span: Span },
     Expression(Box<Expression>),
     Unary { op: Operation, term: Box<Term> },
+    /* `a + b * c` in precedence mode, `right` being `b * c` */
+    Binary { left: Box<Term>, op: Operation, right: Box<Term> },
     Call(SubroutineCall),
 }
 
-#[derive(Debug, PartialEq)]
+/// Call of a function, constructor or method, either a statement or a term
+#[derive(Debug, PartialEq, Clone)]
 pub struct SubroutineCall {
-    /* uppercase classname => function
-     * lowercase object => method call on a different object
+    /* variable in scope, local symbols first => method call on the object it holds
+     * otherwise a class of the `ClassTable`, whatever the case of its name => function
+     * or constructor call
      * no callee => method call on the current object */
     pub callee: Option<String>,
     pub routine_name: String,
     pub expressions: Vec<Expression>,
+    pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+/// Binary operations of expressions, and unary operations of terms
+#[derive(Debug, PartialEq, Clone)]
 pub enum Operation {
     Plus,
     Minus, /* [vm]sub when binary operation, [vm]neg when unary operator */
@@ -170,6 +385,8 @@ pub enum Operation {
     LessThan,
     GreaterThan,
     Equals,
+    LogicalAnd, /* `&&`, right operand evaluated only when the left one is true */
+    LogicalOr,  /* `||`, right operand evaluated only when the left one is false */
 }
 
 impl TryFrom<String> for Operation {
@@ -187,7 +404,46 @@ impl TryFrom<String> for Operation {
             ">" => Ok(Operation::GreaterThan),
             "=" => Ok(Operation::Equals),
             "~" => Ok(Operation::Not),
+            "&&" => Ok(Operation::LogicalAnd),
+            "||" => Ok(Operation::LogicalOr),
             _ => Err(()),
         }
     }
 }
+
+impl Display for Operation {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Plus => write!(f, "+"),
+            Self::Minus => write!(f, "-"),
+            Self::Multiply => write!(f, "*"),
+            Self::Divide => write!(f, "/"),
+            Self::And => write!(f, "&"),
+            Self::Or => write!(f, "|"),
+            Self::Not => write!(f, "~"),
+            Self::LessThan => write!(f, "<"),
+            Self::GreaterThan => write!(f, ">"),
+            Self::Equals => write!(f, "="),
+            Self::LogicalAnd => write!(f, "&&"),
+            Self::LogicalOr => write!(f, "||"),
+        }
+    }
+}
+
+impl Operation {
+    /// How tightly a binary operation binds in precedence mode, as in C: `*` and `/` first,
+    /// `||` last. Operations of the same precedence apply from left to right
+    pub fn precedence(self: &Self) -> u8 {
+        match self {
+            Self::Not => 9,
+            Self::Multiply | Self::Divide => 8,
+            Self::Plus | Self::Minus => 7,
+            Self::LessThan | Self::GreaterThan => 6,
+            Self::Equals => 5,
+            Self::And => 4,
+            Self::Or => 3,
+            Self::LogicalAnd => 2,
+            Self::LogicalOr => 1,
+        }
+    }
+}
@@ -1,22 +1,214 @@
-use std::{collections::HashMap, fs::read_to_string, path::PathBuf};
+//! Jack to VM compiler front end and code generator.
+//!
+//! Besides compiling whole programs with [`compile`], every stage is available on its own:
+//! [`tokenize`] a class, [`parse`] it into a [`grammar::Class`] derivation tree, walk the tree
+//! with a [`visitor::Visitor`], build its [`ClassSymbols`] and [`RoutineSymbols`] tables,
+//! and [`generate_vm`] code from it.
+//!
+//! ```
+//! let class = jack_to_vm::parse("class Main { function void main() { return; } }", "Main.jack")
+//!     .expect("Main is a valid class");
+//!
+//! assert_eq!("main", class.routines[0].name);
+//! let class_table = jack_to_vm::ClassTable::default();
+//! assert_eq!(
+//!     vec!["function Main.main 0", "push constant 0", "return"],
+//!     jack_to_vm::generate_vm(class, &class_table, &jack_to_vm::Options::default())
+//! );
+//! ```
+
+use std::{collections::HashMap, fmt::Display, fs::read_to_string, path::PathBuf};
 
 #[path = "code-generator.rs"]
 mod code_generator;
-mod grammar;
+#[path = "doc-generator.rs"]
+mod doc_generator;
+mod formatter;
+pub mod grammar;
+mod linter;
+#[path = "semantic-analyzer.rs"]
+mod semantic_analyzer;
 #[path = "syntax-analyzer.rs"]
 mod syntax_analyzer;
+#[path = "type-checker.rs"]
+mod type_checker;
+pub mod visitor;
+mod xml;
+
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// Instrument the generated vm code with runtime checks reporting failures through `Sys.error`
+    pub debug: bool,
+    /// Directories of classes the program is linked with, such as the OS:
+    /// calls to their subroutines are resolved, but no vm code is generated for them
+    pub libraries: Vec<PathBuf>,
+    /// Check the types of assignments, arguments and return values, reporting mismatches as errors
+    pub strict: bool,
+}
+
+pub use code_generator::STATIC_INITIALIZER;
+pub use code_generator::symbols::{
+    ClassSymbols, ClassTable, ConstantError, Kind, RoutineSignature, RoutineSymbols, SymbolEntry,
+};
+pub use doc_generator::DocFormat;
+pub use linter::{Lint, Rule};
+pub use semantic_analyzer::SemanticError;
+pub use syntax_analyzer::tokenizer::{Token, TokenKind};
+pub use syntax_analyzer::{ParseError, Span};
+
+#[derive(Debug, PartialEq)]
+pub enum CompileError {
+    Parse(ParseError),
+    Semantic(SemanticError),
+}
+
+impl Display for CompileError {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(err) => write!(f, "{err}"),
+            Self::Semantic(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// Compiles every class found at `program_path`.
+///
+/// The parse errors of all classes are reported at once, the whole program is then
+/// checked for name resolution and call errors, against a table of the subroutines
+/// of its classes and libraries, before any vm code is generated.
+/// In strict mode, type mismatches are reported as well.
+pub fn compile(
+    program_path: &PathBuf,
+    options: &Options,
+) -> Result<HashMap<String, Vec<String>>, Vec<CompileError>> {
+    let (names, classes) = parse_classes(program_path)?;
+    let mut class_table = ClassTable::default();
+
+    for library_path in &options.libraries {
+        let (_, library_classes) = parse_classes(library_path)?;
+        library_classes.iter().for_each(|class| class_table.add_class(class));
+    }
+    classes.iter().for_each(|class| class_table.add_class(class));
+
+    if let Err(semantic_errors) = check(&classes, &class_table, options) {
+        return Err(semantic_errors.into_iter().map(CompileError::Semantic).collect());
+    }
+
+    Ok(names
+        .into_iter()
+        .zip(classes)
+        .map(|(name, class)| (name, generate_vm(class, &class_table, options)))
+        .collect())
+}
+
+/// Source of a class -> tokens, `file` being the name their positions refer to
+pub fn tokenize(source: &str, file: &str) -> Result<Vec<Token>, Vec<ParseError>> {
+    syntax_analyzer::tokenizer::tokenize(source, file)
+}
+
+/// Tokens of a class -> derivation tree, `/** */` comments documenting the declarations they precede
+pub fn parse_tokens(tokens: Vec<Token>) -> Result<grammar::Class, Vec<ParseError>> {
+    syntax_analyzer::parser::parse(tokens)
+}
+
+/// Source of a class -> derivation tree, `file` being the name error positions refer to
+pub fn parse(source: &str, file: &str) -> Result<grammar::Class, Vec<ParseError>> {
+    syntax_analyzer::run(source.to_string(), file)
+}
 
-pub fn compile(program_path: &PathBuf) -> HashMap<String, Vec<String>> {
-    let jack_classes = read_jack_classes_from_fs(program_path);
-    let mut vm_outputs: HashMap<String, Vec<String>> = HashMap::new();
+/// Source of a class -> the same source, canonically indented and spaced, comments kept.
+///
+/// The source is left unformatted when it does not parse, its errors being returned instead.
+pub fn format(source: &str, file: &str) -> Result<String, Vec<ParseError>> {
+    let tokens = syntax_analyzer::tokenizer::tokenize_with_comments(source, file)?;
+    let class = parse_tokens(tokens.clone())?;
 
-    jack_classes.into_iter().for_each(|(name, content)| {
-        let derivation_tree = syntax_analyzer::run(content);
-        let vm_output = code_generator::compile(derivation_tree);
-        vm_outputs.insert(name, vm_output);
+    Ok(formatter::format(tokens, &class))
+}
+
+/// Derivation tree of a class -> vm code, constants being valued through `class_table`.
+///
+/// The tree is expected to be valid: see [`check`] for the errors it would otherwise run into.
+pub fn generate_vm(
+    class: grammar::Class,
+    class_table: &ClassTable,
+    options: &Options,
+) -> Vec<String> {
+    code_generator::compile(class, class_table, options)
+}
+
+/// Checks the derivation trees of a program for name resolution and call errors,
+/// along with type errors in strict mode, against a table of every class it is linked with
+pub fn check(
+    classes: &[grammar::Class],
+    class_table: &ClassTable,
+    options: &Options,
+) -> Result<(), Vec<SemanticError>> {
+    semantic_analyzer::check(classes, class_table)?;
+
+    if options.strict { type_checker::check(classes, class_table) } else { Ok(()) }
+}
+
+/// Lints every class found at `program_path`, reporting only the warnings of `rules`
+pub fn lint(program_path: &PathBuf, rules: &[Rule]) -> Result<Vec<Lint>, Vec<CompileError>> {
+    let (_, classes) = parse_classes(program_path)?;
+    Ok(linter::lint(&classes, rules))
+}
+
+/// Generates the API documentation of every class found at `program_path` from their
+/// `/** */` doc comments: one page per class and an index, keyed by their file name
+pub fn docs(
+    program_path: &PathBuf,
+    format: DocFormat,
+) -> Result<Vec<(String, String)>, Vec<CompileError>> {
+    let (_, classes) = parse_classes(program_path)?;
+    Ok(doc_generator::generate(&classes, format))
+}
+
+/// Emits the token file `XxxT.xml` and the parse tree `Xxx.xml` of every class found
+/// at `program_path`, in the format of the nand2tetris analyzer tools, keyed by their path
+pub fn to_xml(program_path: &PathBuf) -> Result<HashMap<String, Vec<String>>, Vec<CompileError>> {
+    let mut files: HashMap<String, Vec<String>> = HashMap::new();
+    let mut errors: Vec<CompileError> = vec![];
+
+    read_jack_classes_from_fs(program_path).into_iter().for_each(|(name, content)| {
+        let tokens = match syntax_analyzer::tokenizer::tokenize(&content, &name) {
+            Ok(tokens) => tokens,
+            Err(class_errors) => {
+                return errors.extend(class_errors.into_iter().map(CompileError::Parse));
+            }
+        };
+        let stem = name.trim_end_matches(".jack");
+        files.insert(format!("{stem}T.xml"), xml::tokens(&tokens));
+
+        match syntax_analyzer::parser::parse(tokens) {
+            Ok(class) => {
+                files.insert(format!("{stem}.xml"), xml::class(&class));
+            }
+            Err(class_errors) => errors.extend(class_errors.into_iter().map(CompileError::Parse)),
+        }
+    });
+
+    if errors.is_empty() { Ok(files) } else { Err(errors) }
+}
+
+/* Paths and derivation trees of every class found at `path` */
+fn parse_classes(path: &PathBuf) -> Result<(Vec<String>, Vec<grammar::Class>), Vec<CompileError>> {
+    let mut names: Vec<String> = vec![];
+    let mut classes: Vec<grammar::Class> = vec![];
+    let mut errors: Vec<CompileError> = vec![];
+
+    read_jack_classes_from_fs(path).into_iter().for_each(|(name, content)| {
+        match syntax_analyzer::run(content, &name) {
+            Ok(derivation_tree) => {
+                names.push(name);
+                classes.push(derivation_tree);
+            }
+            Err(class_errors) => errors.extend(class_errors.into_iter().map(CompileError::Parse)),
+        }
     });
 
-    vm_outputs
+    if errors.is_empty() { Ok((names, classes)) } else { Err(errors) }
 }
 
 fn read_jack_classes_from_fs(program_path: &PathBuf) -> HashMap<String, String> {
@@ -0,0 +1,498 @@
+use std::{collections::HashSet, fmt::Display};
+
+use super::Span;
+use super::grammar::*;
+use super::visitor::{Visitor, walk_expression, walk_statement, walk_subroutine_call, walk_term};
+
+/// Derivation trees -> warnings about code which compiles but is most likely a mistake
+///
+/// Each rule can be enabled on its own, lints never prevent a program from compiling.
+pub fn lint(classes: &[Class], rules: &[Rule]) -> Vec<Lint> {
+    let mut linter = Linter { rules, lints: vec![] };
+    classes.iter().for_each(|class| linter.lint_class(class));
+    linter.lints
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Rule {
+    /// Local variables, parameters, fields and statics which are never read
+    UnusedVariable,
+    /// Statements following a `return`, `break` or `continue` within the same block
+    UnreachableCode,
+    /// `while (true)` and `for (;;)` loops without a `return` or `break`, `Sys.halt` excluded
+    InfiniteLoop,
+    /// Local variables and parameters named after a field or static of their class
+    ShadowedField,
+    /// `let x = x;`
+    SelfAssignment,
+    /// `a + b * c` computing `(a + b) * c`, operations applying from left to right
+    /// outside of precedence mode
+    OperatorPrecedence,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::UnusedVariable,
+        Rule::UnreachableCode,
+        Rule::InfiniteLoop,
+        Rule::ShadowedField,
+        Rule::SelfAssignment,
+        Rule::OperatorPrecedence,
+    ];
+
+    /// Rules the compiler checks along with the selected ones, unless turned off
+    pub const DEFAULT: [Rule; 1] = [Rule::OperatorPrecedence];
+}
+
+impl TryFrom<&str> for Rule {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "unused-variable" => Ok(Self::UnusedVariable),
+            "unreachable-code" => Ok(Self::UnreachableCode),
+            "infinite-loop" => Ok(Self::InfiniteLoop),
+            "shadowed-field" => Ok(Self::ShadowedField),
+            "self-assignment" => Ok(Self::SelfAssignment),
+            "operator-precedence" => Ok(Self::OperatorPrecedence),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnusedVariable => write!(f, "unused-variable"),
+            Self::UnreachableCode => write!(f, "unreachable-code"),
+            Self::InfiniteLoop => write!(f, "infinite-loop"),
+            Self::ShadowedField => write!(f, "shadowed-field"),
+            Self::SelfAssignment => write!(f, "self-assignment"),
+            Self::OperatorPrecedence => write!(f, "operator-precedence"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lint {
+    pub rule: Rule,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Display for Lint {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: warning: {} [{}]", self.span, self.message, self.rule)
+    }
+}
+
+struct Linter<'a> {
+    rules: &'a [Rule],
+    lints: Vec<Lint>,
+}
+
+impl<'a> Linter<'a> {
+    fn lint_class(self: &mut Self, class: &Class) {
+        let class_var_names: HashSet<&String> = class.vars.iter().map(|var| &var.name).collect();
+        /* class variables read by any initializer or subroutine */
+        let mut class_vars_read: HashSet<String> = HashSet::new();
+        class.vars.iter().filter_map(|var| var.exp.as_ref()).for_each(|exp| {
+            let mut reads = Reads::default();
+            reads.visit_expression(exp);
+            class_vars_read.extend(reads.vars_read);
+        });
+
+        class.routines.iter().for_each(|routine| {
+            let is_sys_halt = class.name == "Sys" && routine.name == "halt";
+            let mut reads = Reads::default();
+            reads.visit_subroutine_dec(routine);
+            let vars_read = reads.vars_read;
+
+            let params =
+                routine.parameters.iter().map(|param| (&param.name, &param.span, "parameter"));
+            let locals = routine
+                .body
+                .all_vars()
+                .into_iter()
+                .map(|var| (&var.name, &var.span, "local variable"));
+            let routine_vars: Vec<(&String, &Span, &str)> = params.chain(locals).collect();
+
+            routine_vars.iter().for_each(|(name, span, kind)| {
+                if class_var_names.contains(name) {
+                    let message = format!("{kind} `{name}` shadows a class variable");
+                    self.report(Rule::ShadowedField, span, message);
+                }
+
+                if !vars_read.contains(*name) {
+                    let message = format!("{kind} `{name}` is never read");
+                    self.report(Rule::UnusedVariable, span, message);
+                }
+            });
+
+            vars_read
+                .into_iter()
+                .filter(|name| !routine_vars.iter().any(|(var_name, _, _)| *var_name == name))
+                .for_each(|name| {
+                    class_vars_read.insert(name);
+                });
+
+            self.lint_statements(&routine.body.statements, is_sys_halt);
+        });
+
+        class.vars.iter().filter(|var| !class_vars_read.contains(&var.name)).for_each(|var| {
+            let message = format!("class variable `{}` is never read", var.name);
+            self.report(Rule::UnusedVariable, &var.span, message);
+        });
+
+        let mut groupings = Groupings::default();
+        let initializers = class.vars.iter().map(|var| (&var.exp, &var.span));
+        let values = class.consts.iter().map(|constant| (&constant.exp, &constant.span));
+        initializers.chain(values).for_each(|(exp, span)| {
+            if let Some(exp) = exp {
+                groupings.span = span.clone();
+                groupings.visit_expression(exp);
+            }
+        });
+        class.routines.iter().for_each(|routine| groupings.visit_subroutine_dec(routine));
+
+        groupings.found.into_iter().for_each(|(span, first, second)| {
+            let message =
+                format!("`{first}` applies before `{second}`, operations going from left to right");
+            self.report(Rule::OperatorPrecedence, &span, message);
+        });
+    }
+
+    fn lint_statements(self: &mut Self, statements: &[Statement], is_sys_halt: bool) {
+        let jump =
+            statements.iter().enumerate().find_map(|(position, statement)| match statement {
+                Statement::Return(_, span) => Some((position, "return", span)),
+                Statement::Break(span) => Some((position, "break", span)),
+                Statement::Continue(span) => Some((position, "continue", span)),
+                _ => None,
+            });
+        if let Some((position, keyword, span)) = jump
+            && position + 1 < statements.len()
+        {
+            let message = format!("statements after this {keyword} are never executed");
+            self.report(Rule::UnreachableCode, span, message);
+        }
+
+        statements.iter().for_each(|statement| match statement {
+            Statement::Let { var_name, array_access: None, exp, span } => {
+                if let Expression { term: Term::VarName(value_name, _), additional } = exp
+                    && additional.is_empty()
+                    && value_name == var_name
+                {
+                    self.report(
+                        Rule::SelfAssignment,
+                        span,
+                        format!("`{var_name}` is assigned to itself"),
+                    );
+                }
+            }
+            Statement::If { statements, else_statements, .. } => {
+                self.lint_statements(statements, is_sys_halt);
+                if let Some(else_statements) = else_statements {
+                    self.lint_statements(else_statements, is_sys_halt);
+                }
+            }
+            Statement::While { exp, statements, span } => {
+                let always_true = matches!(&exp.term, Term::KeywordConst(val, _) if val == "true")
+                    && exp.additional.is_empty();
+
+                if always_true && !is_sys_halt && !exits(statements) {
+                    let message =
+                        "this loop never ends, use Sys.halt to stop the program".to_string();
+                    self.report(Rule::InfiniteLoop, span, message);
+                }
+
+                self.lint_statements(statements, is_sys_halt);
+            }
+            Statement::For { init, exp, step, statements, span } => {
+                if exp.is_none() && !is_sys_halt && !exits(statements) {
+                    let message =
+                        "this loop never ends, use Sys.halt to stop the program".to_string();
+                    self.report(Rule::InfiniteLoop, span, message);
+                }
+
+                [init, step].into_iter().flatten().for_each(|statement| {
+                    self.lint_statements(std::slice::from_ref(statement), is_sys_halt)
+                });
+                self.lint_statements(statements, is_sys_halt);
+            }
+            Statement::Switch { cases, default, .. } => {
+                cases
+                    .iter()
+                    .map(|case| &case.statements)
+                    .chain(default)
+                    .for_each(|statements| self.lint_statements(statements, is_sys_halt));
+            }
+            _ => {}
+        });
+    }
+
+    fn report(self: &mut Self, rule: Rule, span: &Span, message: String) {
+        if self.rules.contains(&rule) {
+            self.lints.push(Lint { rule, span: span.clone(), message });
+        }
+    }
+}
+
+/* A return or `Sys.halt` call anywhere, or a break which does not belong to a nested loop
+ * or switch */
+fn exits(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::Return(_, _) | Statement::Break(_) => true,
+        Statement::Do(call) => halts(call),
+        Statement::If { statements, else_statements, .. } => {
+            exits(statements)
+                || else_statements.as_ref().is_some_and(|else_statements| exits(else_statements))
+        }
+        Statement::While { statements, .. } | Statement::For { statements, .. } => {
+            contains_return(statements)
+        }
+        Statement::Switch { cases, default, .. } => {
+            cases.iter().any(|case| contains_return(&case.statements))
+                || default.as_ref().is_some_and(|default| contains_return(default))
+        }
+        _ => false,
+    })
+}
+
+fn contains_return(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::Return(_, _) => true,
+        Statement::Do(call) => halts(call),
+        Statement::If { statements, else_statements, .. } => {
+            contains_return(statements)
+                || else_statements
+                    .as_ref()
+                    .is_some_and(|else_statements| contains_return(else_statements))
+        }
+        Statement::While { statements, .. } | Statement::For { statements, .. } => {
+            contains_return(statements)
+        }
+        Statement::Switch { cases, default, .. } => {
+            cases.iter().any(|case| contains_return(&case.statements))
+                || default.as_ref().is_some_and(|default| contains_return(default))
+        }
+        _ => false,
+    })
+}
+
+fn halts(call: &SubroutineCall) -> bool {
+    call.callee.as_deref() == Some("Sys") && call.routine_name == "halt"
+}
+
+/* Names of the variables whose value is read: array bases and method callees included */
+#[derive(Default)]
+struct Reads {
+    vars_read: HashSet<String>,
+}
+
+impl Visitor for Reads {
+    fn visit_statement(self: &mut Self, statement: &Statement) {
+        /* the base of an array element is read, a variable which is only updated is not */
+        if let Statement::Let { var_name, array_access: Some(_), .. }
+        | Statement::Update { var_name, array_access: Some(_), .. } = statement
+        {
+            self.vars_read.insert(var_name.clone());
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_term(self: &mut Self, term: &Term) {
+        if let Term::VarName(var_name, _) | Term::ArrayAccess { var_name, .. } = term {
+            self.vars_read.insert(var_name.clone());
+        }
+        walk_term(self, term);
+    }
+
+    fn visit_subroutine_call(self: &mut Self, call: &SubroutineCall) {
+        /* a class name never matches a variable in scope */
+        if let Some(callee) = &call.callee {
+            self.vars_read.insert(callee.clone());
+        }
+        walk_subroutine_call(self, call);
+    }
+}
+
+/* Expressions whose operations would be grouped otherwise in precedence mode: an operation
+ * binding tighter than the one before it, found at the position of its statement */
+#[derive(Default)]
+struct Groupings {
+    span: Span,
+    found: Vec<(Span, Operation, Operation)>,
+}
+
+impl Visitor for Groupings {
+    fn visit_statement(self: &mut Self, statement: &Statement) {
+        let span = match statement {
+            Statement::Let { span, .. }
+            | Statement::Update { span, .. }
+            | Statement::If { span, .. }
+            | Statement::While { span, .. }
+            | Statement::For { span, .. }
+            | Statement::Switch { span, .. }
+            | Statement::Return(_, span) => span.clone(),
+            Statement::Do(call) => call.span.clone(),
+            _ => self.span.clone(),
+        };
+        let outer = std::mem::replace(&mut self.span, span);
+        walk_statement(self, statement);
+        self.span = outer;
+    }
+
+    fn visit_expression(self: &mut Self, exp: &Expression) {
+        let ops: Vec<&Operation> = exp.additional.iter().map(|(op, _)| op).collect();
+        if let Some(pair) = ops.windows(2).find(|pair| pair[1].precedence() > pair[0].precedence())
+        {
+            self.found.push((self.span.clone(), pair[0].clone(), pair[1].clone()));
+        }
+        walk_expression(self, exp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::syntax_analyzer;
+    use super::*;
+
+    fn lint_source(source: &str, rules: &[Rule]) -> Vec<String> {
+        let class = syntax_analyzer::run(source.into(), "Main.jack").unwrap();
+        lint(&[class], rules).iter().map(|lint| lint.to_string()).collect()
+    }
+
+    #[test]
+    fn report_every_rule() {
+        let class = "class Main {
+  field int x, unused;
+  static int count;
+  method void run(int x, int y) {
+    var int z, w;
+    let z = x;
+    let count = count;
+    while (true) { let z = z + 1; }
+    if (z) { return; do Output.println(); }
+    return;
+  }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:4:23: warning: parameter `x` shadows a class variable [shadowed-field]",
+                "Main.jack:4:30: warning: parameter `y` is never read [unused-variable]",
+                "Main.jack:5:16: warning: local variable `w` is never read [unused-variable]",
+                "Main.jack:7:9: warning: `count` is assigned to itself [self-assignment]",
+                "Main.jack:8:5: warning: this loop never ends, use Sys.halt to stop the program [infinite-loop]",
+                "Main.jack:9:14: warning: statements after this return are never executed [unreachable-code]",
+                "Main.jack:2:13: warning: class variable `x` is never read [unused-variable]",
+                "Main.jack:2:16: warning: class variable `unused` is never read [unused-variable]",
+            ],
+            lint_source(class, &Rule::ALL)
+        );
+    }
+
+    #[test]
+    fn toggle_rules_individually() {
+        let class = "class Main {
+  function int run(int n) {
+    var int i;
+    let i = 0;
+    while (true) { if (i > n) { return i; } let i = i + 1; }
+    return n;
+  }
+}";
+
+        assert_eq!(Vec::<String>::new(), lint_source(class, &Rule::ALL));
+        assert_eq!(
+            vec![
+                "Main.jack:2:24: warning: parameter `n` shadows a class variable [shadowed-field]"
+            ],
+            lint_source(
+                &class.replace("class Main {", "class Main { field int n;"),
+                &[Rule::ShadowedField]
+            )
+        );
+        assert_eq!(Ok(Rule::SelfAssignment), Rule::try_from("self-assignment"));
+        assert_eq!(Err(()), Rule::try_from("unknown"));
+    }
+
+    #[test]
+    fn report_operations_grouped_otherwise_by_precedence() {
+        let class = "class Main {
+  const int SIZE = 2 + 3 * 4;
+  function int run(int n) {
+    if (n * 2 + 1 < 9) { return n - 1 + n; }
+    while ((n > 0) & (n < 9)) {
+      let n = n - 1;
+      do Output.printInt(n | n = 0);
+    }
+    return n < 1 = (n + 1 * 2 > 3);
+  }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:2:13: warning: `+` applies before `*`, operations going from left to right [operator-precedence]",
+                "Main.jack:7:10: warning: `|` applies before `=`, operations going from left to right [operator-precedence]",
+                "Main.jack:9:5: warning: `+` applies before `*`, operations going from left to right [operator-precedence]",
+            ],
+            lint_source(class, &[Rule::OperatorPrecedence])
+        );
+        assert_eq!(
+            Vec::<String>::new(),
+            lint_source(&format!("// pragma precedence\n{class}"), &[Rule::OperatorPrecedence])
+        );
+    }
+
+    #[test]
+    fn loops_exited_by_sys_halt() {
+        let class = "class Main {
+  function void run(int n) {
+    while (true) { do Sys.halt(); }
+    while (true) { if (n > 0) { do Sys.halt(); } }
+    for (;;) { while (n > 0) { do Sys.halt(); } }
+    while (true) { do Sys.wait(1); }
+  }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:6:5: warning: this loop never ends, use Sys.halt to stop the program [infinite-loop]"
+            ],
+            lint_source(class, &[Rule::InfiniteLoop])
+        );
+    }
+
+    #[test]
+    fn sys_halt_may_loop_forever() {
+        let class = "class Sys {
+  function void halt() { while (true) {} return; }
+}";
+
+        assert_eq!(Vec::<String>::new(), lint_source(class, &[Rule::InfiniteLoop]));
+    }
+
+    #[test]
+    fn loops_exited_by_a_break() {
+        let class = "class Main {
+  function int run(int n) {
+    var int i;
+    while (true) { for (;;) { return i; } let i = i + 1; if (i > n) { break; } }
+    for (;;) { while (i > 0) { break; } let i = i - 1; }
+    while (true) { let i = i + 1; continue; let i = 0; }
+    return i;
+  }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:5:5: warning: this loop never ends, use Sys.halt to stop the program [infinite-loop]",
+                "Main.jack:6:5: warning: this loop never ends, use Sys.halt to stop the program [infinite-loop]",
+                "Main.jack:6:35: warning: statements after this continue are never executed [unreachable-code]",
+            ],
+            lint_source(class, &Rule::ALL)
+        );
+    }
+}
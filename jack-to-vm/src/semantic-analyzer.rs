@@ -0,0 +1,804 @@
+use std::{collections::HashSet, fmt::Display};
+
+use super::Span;
+use super::code_generator::symbols::*;
+use super::grammar::*;
+
+/// Derivation trees -> name resolution and call checks over the whole program
+///
+/// Runs once every class of the program is parsed and before any vm code is generated,
+/// which assumes every name it meets resolves to a symbol. Reported are:
+/// - variables not declared in the subroutine nor in its class
+/// - fields and `this` used within a function or the initializer of a static
+/// - variables, constants, parameters and subroutines declared more than once
+/// - constant values and case values other than literals and constants, constants defined
+///   in terms of themselves or dividing by zero, and assignments to constants
+/// - calls to subroutines the callee class does not declare
+/// - calls with a wrong number of arguments
+/// - methods called as functions, and functions or constructors called as methods
+/// - callees which are neither a variable in scope nor a known class
+/// - `break` outside of a loop or switch, `continue` outside of a loop
+/// - case values repeated within a switch
+///
+/// Calls are resolved against `class_table`, which holds the subroutines of the program
+/// classes and of the libraries, such as the OS, the program is linked with.
+pub fn check(classes: &[Class], class_table: &ClassTable) -> Result<(), Vec<SemanticError>> {
+    let mut errors: Vec<SemanticError> = vec![];
+
+    classes.iter().for_each(|class| {
+        let mut analyzer = SemanticAnalyzer::new(class, class_table);
+        analyzer.check();
+        errors.extend(analyzer.errors);
+    });
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+/// A well-formed construct which does not make sense within the program
+#[derive(Debug, PartialEq, Clone)]
+pub struct SemanticError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl SemanticError {
+    pub fn new(span: &Span, message: String) -> Self {
+        Self { span: span.clone(), message }
+    }
+}
+
+impl Display for SemanticError {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+struct SemanticAnalyzer<'a> {
+    class: &'a Class,
+    class_table: &'a ClassTable,
+    class_symbols: ClassSymbols,
+    routine: Option<&'a SubroutineDec>,
+    routine_symbols: Option<RoutineSymbols>,
+    /* class variable whose initializer is being checked, outside of any subroutine */
+    initializer: Option<&'a ClassVarDec>,
+    loop_depth: usize,
+    switch_depth: usize,
+    errors: Vec<SemanticError>,
+}
+
+impl<'a> SemanticAnalyzer<'a> {
+    fn new(class: &'a Class, class_table: &'a ClassTable) -> Self {
+        let mut class_symbols = ClassSymbols::new(&class.vars);
+        class_symbols.add_constants(&class.name, class_table);
+
+        Self {
+            class,
+            class_table,
+            class_symbols,
+            routine: None,
+            routine_symbols: None,
+            initializer: None,
+            loop_depth: 0,
+            switch_depth: 0,
+            errors: vec![],
+        }
+    }
+
+    fn check(self: &mut Self) {
+        let vars = self.class.vars.iter().map(|var| (&var.name, &var.span));
+        let consts = self.class.consts.iter().map(|constant| (&constant.name, &constant.span));
+        self.check_duplicates(vars.chain(consts));
+        self.check_duplicates(
+            self.class.routines.iter().map(|routine| (&routine.name, &routine.span)),
+        );
+
+        self.class.consts.iter().for_each(|constant| self.check_const(constant));
+        self.class.vars.iter().for_each(|var| {
+            if let Some(exp) = &var.exp {
+                self.initializer = Some(var);
+                self.check_expression(exp);
+            }
+        });
+        self.initializer = None;
+        self.class.routines.iter().for_each(|routine| self.check_routine(routine));
+    }
+
+    /* Evaluation errors are only reported once the value is made of constants */
+    fn check_const(self: &mut Self, constant: &ConstDec) {
+        let Some(exp) = &constant.exp else { return };
+        let error_count = self.errors.len();
+        self.check_constant_expression(exp, &constant.span);
+        if self.errors.len() > error_count {
+            return;
+        }
+
+        let name = &constant.name;
+        match self.class_table.constant_value(&self.class.name, name) {
+            Err(ConstantError::Cycle(class_name, cycle_name))
+                if class_name == self.class.name && cycle_name == *name =>
+            {
+                self.error(&constant.span, format!("`{name}` is defined in terms of itself"));
+            }
+            Err(ConstantError::DivisionByZero) => {
+                self.error(&constant.span, format!("division by zero in the value of `{name}`"));
+            }
+            _ => {}
+        }
+    }
+
+    fn check_constant_expression(self: &mut Self, exp: &Expression, span: &Span) {
+        self.check_constant_term(&exp.term, span);
+        exp.additional.iter().for_each(|(_, term)| self.check_constant_term(term, span));
+    }
+
+    /* Literals, `true`, `false` and constants, terms without a position being reported at `span` */
+    fn check_constant_term(self: &mut Self, term: &Term, span: &Span) {
+        match term {
+            Term::IntConst(_) | Term::CharConst(_) => {}
+            Term::KeywordConst(val, _) if val == "true" || val == "false" => {}
+            Term::VarName(name, span) => match self.fetch_symbol_entry(name).map(|e| &e.kind) {
+                Some(Kind::Constant) => {}
+                Some(_) => self.error(span, format!("`{name}` is not a constant")),
+                None => self.error(span, format!("undeclared constant `{name}`")),
+            },
+            Term::ClassConst { class_name, name, span } => {
+                self.resolve_class_const(class_name, name, span)
+            }
+            Term::Expression(exp) => self.check_constant_expression(exp, span),
+            Term::Binary { left, right, .. } => {
+                self.check_constant_term(left, span);
+                self.check_constant_term(right, span);
+            }
+            Term::Unary { term, .. } => self.check_constant_term(term, span),
+            Term::KeywordConst(_, span)
+            | Term::ArrayAccess { span, .. }
+            | Term::Call(SubroutineCall { span, .. }) => self.not_constant(span),
+            Term::StrConst(_) => self.not_constant(span),
+        }
+    }
+
+    fn not_constant(self: &mut Self, span: &Span) {
+        let message = "expected a literal or a constant, the value being computed at compile time";
+        self.error(span, message.to_string());
+    }
+    fn check_routine(self: &mut Self, routine: &'a SubroutineDec) {
+        self.routine = Some(routine);
+        self.routine_symbols = Some(RoutineSymbols::new(routine, &self.class.name));
+
+        let params = routine.parameters.iter().map(|param| (&param.name, &param.span));
+        let vars = routine.body.vars.iter().map(|var| (&var.name, &var.span));
+        self.check_duplicates(params.chain(vars));
+
+        routine.body.statements.iter().for_each(|statement| self.check_statement(statement));
+    }
+
+    /* Every declaration after the first one of the same name is reported */
+    fn check_duplicates(self: &mut Self, decs: impl Iterator<Item = (&'a String, &'a Span)>) {
+        let mut declared: HashSet<&String> = HashSet::new();
+
+        decs.for_each(|(name, span)| {
+            if !declared.insert(name) {
+                self.error(span, format!("`{name}` is already declared"));
+            }
+        });
+    }
+
+    fn check_statement(self: &mut Self, statement: &Statement) {
+        match statement {
+            /* locals of enclosing blocks and parameters cannot be hidden, unlike class variables */
+            Statement::Var(vars) => vars.iter().for_each(|var| {
+                if self.fetch_symbol_entry(&var.name).is_some_and(|entry| {
+                    matches!(entry.kind, Kind::Local | Kind::Argument) && var.name != "this"
+                }) {
+                    self.error(&var.span, format!("`{}` is already declared", var.name));
+                }
+                self.routine_symbols.as_mut().unwrap().add_vars(std::slice::from_ref(var));
+            }),
+            Statement::Let { var_name, array_access, exp, span } => {
+                self.check_assignment(var_name, array_access, span);
+                self.check_expression(exp);
+            }
+            Statement::Update { var_name, array_access, exp, span, .. } => {
+                self.check_assignment(var_name, array_access, span);
+                if let Some(exp) = exp {
+                    self.check_expression(exp);
+                }
+            }
+            Statement::If { exp, statements, else_statements, .. } => {
+                self.check_expression(exp);
+                self.check_block(statements);
+                if let Some(else_statements) = else_statements {
+                    self.check_block(else_statements);
+                }
+            }
+            Statement::While { exp, statements, .. } => {
+                self.check_expression(exp);
+                self.check_loop_body(statements);
+            }
+            Statement::For { init, exp, step, statements, .. } => {
+                if let Some(init) = init {
+                    self.check_statement(init);
+                }
+                if let Some(exp) = exp {
+                    self.check_expression(exp);
+                }
+                if let Some(step) = step {
+                    self.check_statement(step);
+                }
+                self.check_loop_body(statements);
+            }
+            Statement::Switch { exp, cases, default, .. } => {
+                self.check_expression(exp);
+
+                let mut seen: HashSet<i16> = HashSet::new();
+                cases.iter().for_each(|case| {
+                    case.values.iter().for_each(|value| {
+                        let error_count = self.errors.len();
+                        self.check_constant_term(value, &case.span);
+                        if self.errors.len() > error_count {
+                            return;
+                        }
+
+                        match self.class_table.term_value(&self.class.name, value) {
+                            Ok(value) if !seen.insert(value) => {
+                                self.error(&case.span, format!("duplicate case value {value}"));
+                            }
+                            Err(ConstantError::DivisionByZero) => {
+                                self.error(&case.span, "division by zero in a case value".into());
+                            }
+                            _ => {}
+                        }
+                    });
+                });
+
+                self.switch_depth += 1;
+                cases.iter().for_each(|case| self.check_block(&case.statements));
+                if let Some(default) = default {
+                    self.check_block(default);
+                }
+                self.switch_depth -= 1;
+            }
+            Statement::Break(span) if self.loop_depth == 0 && self.switch_depth == 0 => {
+                self.error(span, "`break` outside of a loop or switch".to_string());
+            }
+            Statement::Continue(span) if self.loop_depth == 0 => {
+                self.error(span, "`continue` outside of a loop".to_string());
+            }
+            Statement::Break(_) | Statement::Continue(_) => {}
+            Statement::Return(exp_opt, _) => {
+                if let Some(exp) = exp_opt {
+                    self.check_expression(exp);
+                }
+            }
+            Statement::Do(call) => self.check_call(call),
+        }
+    }
+
+    fn check_assignment(
+        self: &mut Self,
+        var_name: &String,
+        array_access: &Option<Expression>,
+        span: &Span,
+    ) {
+        if self.fetch_symbol_entry(var_name).is_some_and(|e| e.kind == Kind::Constant) {
+            self.error(span, format!("cannot assign to constant `{var_name}`"));
+        } else {
+            self.resolve_variable(var_name, span);
+        }
+        if let Some(index_exp) = array_access {
+            self.check_expression(index_exp);
+        }
+    }
+
+    fn check_loop_body(self: &mut Self, statements: &[Statement]) {
+        self.loop_depth += 1;
+        self.check_block(statements);
+        self.loop_depth -= 1;
+    }
+
+    fn check_block(self: &mut Self, statements: &[Statement]) {
+        self.routine_symbols.as_mut().unwrap().enter_block();
+        statements.iter().for_each(|s| self.check_statement(s));
+        self.routine_symbols.as_mut().unwrap().exit_block();
+    }
+
+    fn check_expression(self: &mut Self, exp: &Expression) {
+        self.check_term(&exp.term);
+        exp.additional.iter().for_each(|(_, term)| self.check_term(term));
+    }
+
+    fn check_term(self: &mut Self, term: &Term) {
+        match term {
+            Term::VarName(var_name, span) => {
+                self.resolve_variable(var_name, span);
+            }
+            Term::ArrayAccess { var_name, exp, span } => {
+                if self.fetch_symbol_entry(var_name).is_some_and(|e| e.kind == Kind::Constant) {
+                    self.error(span, format!("constant `{var_name}` is not an array"));
+                } else {
+                    self.resolve_variable(var_name, span);
+                }
+                self.check_expression(exp);
+            }
+            Term::ClassConst { class_name, name, span } => {
+                self.resolve_class_const(class_name, name, span)
+            }
+            Term::KeywordConst(val, span) if val == "this" && self.in_function() => {
+                self.error(span, format!("`this` used in {}", self.context()));
+            }
+            Term::Expression(exp) => self.check_expression(exp),
+            Term::Binary { left, right, .. } => {
+                self.check_term(left);
+                self.check_term(right);
+            }
+            Term::Unary { term, .. } => self.check_term(term),
+            Term::Call(call) => self.check_call(call),
+            Term::IntConst(_)
+            | Term::StrConst(_)
+            | Term::CharConst(_)
+            | Term::KeywordConst(_, _) => {}
+        }
+    }
+
+    fn check_call(self: &mut Self, call: &SubroutineCall) {
+        match &call.callee {
+            /* method call on the current object */
+            None => {
+                let class_name = self.class.name.clone();
+                if let Some(routine) = self.find_routine(&class_name, call) {
+                    if routine.routine_type != RoutineType::Method {
+                        self.error(
+                            &call.span,
+                            format!("{class_name}.{} called as a method", call.routine_name),
+                        );
+                    } else if self.in_function() {
+                        self.error(
+                            &call.span,
+                            format!(
+                                "method {class_name}.{} called without an object in {}",
+                                call.routine_name,
+                                self.context()
+                            ),
+                        );
+                    }
+                }
+            }
+            Some(callee) if self.fetch_symbol_entry(callee).is_some() => {
+                /* method call on another object */
+                match self.resolve_variable(callee, &call.span) {
+                    Some(JackType::Class(class_name)) => {
+                        if let Some(routine) = self.find_routine(&class_name, call)
+                            && routine.routine_type != RoutineType::Method
+                        {
+                            self.error(
+                                &call.span,
+                                format!("{class_name}.{} called as a method", call.routine_name),
+                            );
+                        }
+                    }
+                    Some(_) => self.error(&call.span, format!("`{callee}` is not an object")),
+                    None => {}
+                }
+            }
+            Some(callee) if self.class_table.contains_class(callee) => {
+                /* function or constructor call */
+                if let Some(routine) = self.find_routine(callee, call)
+                    && routine.routine_type == RoutineType::Method
+                {
+                    self.error(
+                        &call.span,
+                        format!("method {callee}.{} called as a function", call.routine_name),
+                    );
+                }
+            }
+            Some(callee) => {
+                self.error(&call.span, format!("undeclared class or variable `{callee}`"));
+            }
+        }
+
+        call.expressions.iter().for_each(|exp| self.check_expression(exp));
+    }
+
+    /* The routine called on a class, checking the number of arguments */
+    fn find_routine(
+        self: &mut Self,
+        class_name: &str,
+        call: &SubroutineCall,
+    ) -> Option<&'a RoutineSignature> {
+        if !self.class_table.contains_class(class_name) {
+            self.error(&call.span, format!("undeclared class `{class_name}`"));
+            return None;
+        }
+
+        let Some(routine) = self.class_table.fetch_routine(class_name, &call.routine_name) else {
+            self.error(
+                &call.span,
+                format!("class {class_name} has no subroutine `{}`", call.routine_name),
+            );
+            return None;
+        };
+
+        if routine.parameters.len() != call.expressions.len() {
+            self.error(
+                &call.span,
+                format!(
+                    "{class_name}.{} expects {} argument(s), found {}",
+                    call.routine_name,
+                    routine.parameters.len(),
+                    call.expressions.len()
+                ),
+            );
+        }
+
+        Some(routine)
+    }
+
+    fn resolve_class_const(self: &mut Self, class_name: &String, name: &String, span: &Span) {
+        if self.fetch_symbol_entry(class_name).is_some() {
+            self.error(span, format!("`{class_name}` is a variable, not a class"));
+        } else if self.class_table.declares_enum(&self.class.name, class_name) {
+            if self.class_table.constant_class(&self.class.name, class_name, name).is_none() {
+                self.error(span, format!("enum {class_name} has no member `{name}`"));
+            }
+        } else if !self.class_table.contains_class(class_name) {
+            self.error(span, format!("undeclared class `{class_name}`"));
+        } else if self.class_table.fetch_constant(class_name, name).is_none() {
+            self.error(span, format!("class {class_name} has no constant `{name}`"));
+        }
+    }
+
+    /* Type of the variable, None when it cannot be accessed from the current subroutine */
+    fn resolve_variable(self: &mut Self, var_name: &String, span: &Span) -> Option<JackType> {
+        let Some(entry) = self.fetch_symbol_entry(var_name) else {
+            self.error(span, format!("undeclared variable `{var_name}`"));
+            return None;
+        };
+
+        if entry.kind == Kind::Field && self.in_function() {
+            let message = format!("field `{var_name}` used in {}", self.context());
+            self.error(span, message);
+            return None;
+        }
+
+        Some(entry.jtype.clone())
+    }
+
+    fn fetch_symbol_entry(self: &Self, var_name: &String) -> Option<&SymbolEntry> {
+        self.routine_symbols
+            .as_ref()
+            .and_then(|symbols| symbols.entries.get(var_name))
+            .or_else(|| self.class_symbols.entries.get(var_name))
+    }
+
+    /* Statics are set before any object exists */
+    fn in_function(self: &Self) -> bool {
+        match self.initializer {
+            Some(var) => var.var_type == ClassVarType::Static,
+            None => self.routine.is_some_and(|r| r.routine_type == RoutineType::Function),
+        }
+    }
+
+    /* Where the code being checked runs, such as `function Main.main` */
+    fn context(self: &Self) -> String {
+        match (self.initializer, self.routine) {
+            (Some(var), _) => format!("the initializer of static `{}`", var.name),
+            (None, routine) => {
+                format!("function {}.{}", self.class.name, routine.map_or("", |r| &r.name))
+            }
+        }
+    }
+
+    fn error(self: &mut Self, span: &Span, message: String) {
+        self.errors.push(SemanticError::new(span, message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::syntax_analyzer;
+    use super::*;
+
+    /* Stands for the OS classes the program is linked with */
+    const LIBRARY: [&str; 2] = [
+        "class Output { function void printInt(int i) { return; } }",
+        "class Array { function Array new(int size) { return size; } }",
+    ];
+
+    fn check_sources(sources: Vec<(&str, &str)>) -> Vec<String> {
+        let classes: Vec<Class> = sources
+            .into_iter()
+            .map(|(file, source)| syntax_analyzer::run(source.into(), file).unwrap())
+            .collect();
+
+        let mut class_table = ClassTable::default();
+        LIBRARY.iter().for_each(|source| {
+            class_table.add_class(&syntax_analyzer::run(source.to_string(), "Os.jack").unwrap())
+        });
+        classes.iter().for_each(|class| class_table.add_class(class));
+
+        match check(&classes, &class_table) {
+            Ok(()) => vec![],
+            Err(errors) => errors.iter().map(|err| err.to_string()).collect(),
+        }
+    }
+
+    const POINT: &str = "class Point {
+  field int x, y;
+  static int count;
+  constructor Point new(int ax, int ay) {
+    let x = ax;
+    let y = ay;
+    let count = count + 1;
+    return this;
+  }
+  method int getX() { return x; }
+  function int getCount() { return count; }
+}";
+
+    #[test]
+    fn accept_well_formed_program() {
+        let main = "class Main {
+  function void main() {
+    var Point p;
+    var Array a;
+    let p = Point.new(1, 2);
+    let a = Array.new(p.getX());
+    let a[0] = Point.getCount();
+    do Output.printInt(a[0]);
+    return;
+  }
+}";
+
+        assert_eq!(
+            Vec::<String>::new(),
+            check_sources(vec![("Point.jack", POINT), ("Main.jack", main)])
+        );
+    }
+
+    #[test]
+    fn report_undeclared_variables_and_misused_fields() {
+        let main = "class Main {
+  field int f;
+  function void main() {
+    var int a;
+    let b = a + c[1];
+    let a = f;
+    do d.run();
+    return this;
+  }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:5:9: undeclared variable `b`",
+                "Main.jack:5:17: undeclared variable `c`",
+                "Main.jack:6:13: field `f` used in function Main.main",
+                "Main.jack:7:8: undeclared class or variable `d`",
+                "Main.jack:8:12: `this` used in function Main.main",
+            ],
+            check_sources(vec![("Main.jack", main)])
+        );
+    }
+
+    #[test]
+    fn report_wrong_calls() {
+        let main = "class Main {
+  function void main() {
+    var Point p;
+    var int n;
+    let p = Point.new(1);
+    do Point.getX();
+    do p.getCount();
+    do p.move();
+    do n.run();
+    do draw();
+    do helper();
+    return;
+  }
+  method void draw() { return; }
+  function void helper() { return; }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:5:13: Point.new expects 2 argument(s), found 1",
+                "Main.jack:6:8: method Point.getX called as a function",
+                "Main.jack:7:8: Point.getCount called as a method",
+                "Main.jack:8:8: class Point has no subroutine `move`",
+                "Main.jack:9:8: `n` is not an object",
+                "Main.jack:10:8: method Main.draw called without an object in function Main.main",
+                "Main.jack:11:8: Main.helper called as a method",
+            ],
+            check_sources(vec![("Point.jack", POINT), ("Main.jack", main)])
+        );
+    }
+
+    #[test]
+    fn report_jumps_outside_of_loops() {
+        let main = "class Main {
+  function void main() {
+    var int i;
+    for (let i = 0; i < 10; let i = i + 1) { while (true) { break; } continue; }
+    if (i) { break; }
+    continue;
+    return;
+  }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:5:14: `break` outside of a loop or switch",
+                "Main.jack:6:5: `continue` outside of a loop",
+            ],
+            check_sources(vec![("Main.jack", main)])
+        );
+    }
+
+    #[test]
+    fn report_duplicate_case_values() {
+        let main = "class Main {
+  function void main() {
+    var int i;
+    switch (i) {
+      case 1: case -1: break;
+      case 'A': case 65: continue;
+      default: return;
+    }
+    return;
+  }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:6:7: duplicate case value 65",
+                "Main.jack:6:26: `continue` outside of a loop",
+            ],
+            check_sources(vec![("Main.jack", main)])
+        );
+    }
+
+    #[test]
+    fn report_invalid_constants() {
+        let main = "class Main {
+  const int A = B + 1;
+  const int B = A;
+  const int C = 1 / (2 - 2);
+  field int x;
+  const int D = x;
+  const int E = Keys.UP;
+  function void main() {
+    let A = Shapes.SIDES;
+    return;
+  }
+}";
+        let keys = "class Keys { enum Arrow { LEFT, UP } }";
+
+        assert_eq!(
+            vec![
+                "Main.jack:2:13: `A` is defined in terms of itself",
+                "Main.jack:3:13: `B` is defined in terms of itself",
+                "Main.jack:4:13: division by zero in the value of `C`",
+                "Main.jack:6:17: `x` is not a constant",
+                "Main.jack:9:9: cannot assign to constant `A`",
+                "Main.jack:9:13: undeclared class `Shapes`",
+            ],
+            check_sources(vec![("Main.jack", main), ("Keys.jack", keys)])
+        );
+    }
+
+    #[test]
+    fn qualify_enum_members_by_their_enum() {
+        let main = "class Main {
+  enum Dir { LEFT, RIGHT }
+  const int UP = 2;
+  const int NEXT = Dir.RIGHT + 1;
+  function int main(int d) {
+    switch (d) { case Dir.LEFT: return Dir.RIGHT; default: return Main.LEFT; }
+    let d = Dir.UP + Keys.UP;
+    return Arrow.UP;
+  }
+}";
+        let keys = "class Keys { enum Arrow { LEFT, UP } }";
+
+        assert_eq!(
+            vec![
+                "Main.jack:7:13: enum Dir has no member `UP`",
+                "Main.jack:8:12: undeclared class `Arrow`",
+            ],
+            check_sources(vec![("Main.jack", main), ("Keys.jack", keys)])
+        );
+    }
+
+    #[test]
+    fn scope_locals_to_their_block() {
+        let main = "class Main {
+  field int size;
+  method void run(int x) {
+    if (x) { var int y, size; let y = size; } else { var int y; let y = 1; }
+    while (x) { var boolean x; }
+    let x = y;
+    return;
+  }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:5:29: `x` is already declared",
+                "Main.jack:6:13: undeclared variable `y`",
+            ],
+            check_sources(vec![("Main.jack", main)])
+        );
+    }
+
+    #[test]
+    fn check_initializers() {
+        let main = "class Main {
+  field int size = Main.half(limit);
+  static int limit = size, origin = this;
+  static Main instance = Main.new();
+  static int next = run();
+  function int half(int x) { return x / 2; }
+  method int run() { return 0; }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:3:22: field `size` used in the initializer of static `limit`",
+                "Main.jack:3:37: `this` used in the initializer of static `origin`",
+                "Main.jack:5:21: method Main.run called without an object in the initializer of static `next`",
+            ],
+            check_sources(vec![("Main.jack", main)])
+        );
+    }
+
+    #[test]
+    fn report_duplicate_declarations() {
+        let main = "class Main {
+  field int a;
+  static boolean a;
+  method void run(int x, int y) {
+    var int y, z, z;
+    return;
+  }
+  function void run() { return; }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:3:18: `a` is already declared",
+                "Main.jack:8:17: `run` is already declared",
+                "Main.jack:5:13: `y` is already declared",
+                "Main.jack:5:19: `z` is already declared",
+            ],
+            check_sources(vec![("Main.jack", main)])
+        );
+    }
+
+    #[test]
+    fn resolve_callees_regardless_of_their_case() {
+        let vec = "class vec {
+  field int size;
+  constructor vec new() { let size = 0; return this; }
+  method int length() { return size; }
+}";
+        let main = "class Main {
+  function void main() {
+    var vec Player;
+    var Game game;
+    let Player = vec.new();
+    do Output.printInt(Player.length());
+    do Screen.clear();
+    do game.run();
+    return;
+  }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:7:8: undeclared class or variable `Screen`",
+                "Main.jack:8:8: undeclared class `Game`",
+            ],
+            check_sources(vec![("vec.jack", vec), ("Main.jack", main)])
+        );
+    }
+}
@@ -1,19 +1,53 @@
+use std::fmt::Display;
+
 #[path = "syntax-analyzer/parser.rs"]
-mod parser;
+pub mod parser;
 #[path = "syntax-analyzer/tokenizer.rs"]
-mod tokenizer;
+pub mod tokenizer;
+
+pub use tokenizer::Span;
+
+pub fn run(jack_class: String, file: &str) -> Result<super::grammar::Class, Vec<ParseError>> {
+    /* doc comments are kept, to be attached to the declarations they precede */
+    let tokens = tokenizer::tokenize_with_comments(&jack_class, file)?;
+    let derivation_tree = parser::parse(tokens)?;
 
-pub fn run(jack_class: String) -> super::grammar::Class {
-    let tokens = tokenizer::tokenize(&jack_class);
-    let derivation_tree = parser::parse(tokens);
+    Ok(derivation_tree)
+}
 
-    derivation_tree
+/// A token not fitting the Jack lexicon or grammar, along with what was expected in its place
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub span: Span,
+    pub expected: String,
+    pub found: String,
+}
+
+impl ParseError {
+    pub fn new(span: Span, expected: &str, found: &str) -> Self {
+        Self { span, expected: expected.to_string(), found: found.to_string() }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: expected {}, found {}", self.span, self.expected, self.found)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{parser, tokenizer};
+    use crate::Span;
     use crate::grammar::*;
 
+    /* Expected trees leave node positions out, these are covered separately */
+    fn parse_without_spans(source: &str) -> Result<Class, Vec<super::ParseError>> {
+        let mut tokens = tokenizer::tokenize(source, "Main.jack")?;
+        tokens.iter_mut().for_each(|token| token.span = Span::default());
+        parser::parse(tokens)
+    }
+
     #[test]
     fn parse_mininal_class() {
         let input_program = r#"
@@ -27,16 +61,22 @@ mod tests {
         let expected = Class {
             name: "Main".to_owned(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Void,
                 name: "main".to_owned(),
                 parameters: vec![],
-                body: SubroutineBody { vars: vec![], statements: vec![Statement::Return(None)] },
+                body: SubroutineBody {
+                    vars: vec![],
+                    statements: vec![Statement::Return(None, Span::default())],
+                },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
-        assert_eq!(expected, super::run(input_program.into()));
+        assert_eq!(Ok(expected), parse_without_spans(input_program));
     }
 
     #[test]
@@ -59,30 +99,43 @@ mod tests {
                 jack_type: JackType::Int,
                 name: "varA".to_owned(),
                 var_type: ClassVarType::Static,
+                span: Span::default(),
+                declaration_span: Span::default(),
+                exp: None,
+                doc: None,
             }],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Void,
                 name: "main".to_owned(),
                 parameters: vec![],
                 body: SubroutineBody {
-                    vars: vec![VarDec { jack_type: JackType::Int, name: "a".to_owned() }],
+                    vars: vec![VarDec {
+                        jack_type: JackType::Int,
+                        name: "a".to_owned(),
+                        span: Span::default(),
+                        declaration_span: Span::default(),
+                    }],
                     statements: vec![
                         Statement::Let {
                             var_name: "a".to_owned(),
                             array_access: None,
                             exp: Expression {
-                                term: Term::VarName("varA".to_owned()),
+                                term: Term::VarName("varA".to_owned(), Span::default()),
                                 additional: vec![(Operation::Plus, Term::IntConst(1))],
                             },
+                            span: Span::default(),
                         },
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
-        assert_eq!(expected, super::run(input_program.into()));
+        assert_eq!(Ok(expected), parse_without_spans(input_program));
     }
 
     #[test]
@@ -105,20 +158,37 @@ mod tests {
                     var_type: ClassVarType::Field,
                     jack_type: JackType::Int,
                     name: "x".into(),
+                    span: Span::default(),
+                    declaration_span: Span::default(),
+                    exp: None,
+                    doc: None,
                 },
                 ClassVarDec {
                     var_type: ClassVarType::Field,
                     jack_type: JackType::Int,
                     name: "y".into(),
+                    span: Span::default(),
+                    declaration_span: Span::default(),
+                    exp: None,
+                    doc: None,
                 },
             ],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Constructor,
                 return_type: ReturnType::Type(JackType::Class("Point".into())),
                 name: "new".into(),
                 parameters: vec![
-                    Parameter { jack_type: JackType::Int, name: "argX".into() },
-                    Parameter { jack_type: JackType::Int, name: "argY".into() },
+                    Parameter {
+                        jack_type: JackType::Int,
+                        name: "argX".into(),
+                        span: Span::default(),
+                    },
+                    Parameter {
+                        jack_type: JackType::Int,
+                        name: "argY".into(),
+                        span: Span::default(),
+                    },
                 ],
                 body: SubroutineBody {
                     vars: vec![],
@@ -127,28 +197,35 @@ mod tests {
                             var_name: "x".into(),
                             array_access: None,
                             exp: Expression {
-                                term: Term::VarName("argX".into()),
+                                term: Term::VarName("argX".into(), Span::default()),
                                 additional: vec![],
                             },
+                            span: Span::default(),
                         },
                         Statement::Let {
                             var_name: "y".into(),
                             array_access: None,
                             exp: Expression {
-                                term: Term::VarName("argY".into()),
+                                term: Term::VarName("argY".into(), Span::default()),
                                 additional: vec![],
                             },
+                            span: Span::default(),
                         },
-                        Statement::Return(Some(Expression {
-                            term: Term::KeywordConst("this".into()),
-                            additional: vec![],
-                        })),
+                        Statement::Return(
+                            Some(Expression {
+                                term: Term::KeywordConst("this".into(), Span::default()),
+                                additional: vec![],
+                            }),
+                            Span::default(),
+                        ),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
-        assert_eq!(expected, super::run(input_program.into()));
+        assert_eq!(Ok(expected), parse_without_spans(input_program));
     }
 
     #[test]
@@ -168,32 +245,46 @@ mod tests {
         let expected = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Int),
                 name: "run".into(),
-                parameters: vec![Parameter { name: "a".into(), jack_type: JackType::Int }],
+                parameters: vec![Parameter {
+                    name: "a".into(),
+                    jack_type: JackType::Int,
+                    span: Span::default(),
+                }],
                 body: SubroutineBody {
                     vars: vec![],
                     statements: vec![Statement::If {
                         exp: Expression {
-                            term: Term::VarName("a".into()),
+                            term: Term::VarName("a".into(), Span::default()),
                             additional: vec![(Operation::LessThan, Term::IntConst(2))],
                         },
-                        statements: vec![Statement::Return(Some(Expression {
-                            term: Term::VarName("a".into()),
-                            additional: vec![],
-                        }))],
-                        else_statements: Some(vec![Statement::Return(Some(Expression {
-                            term: Term::VarName("a".into()),
-                            additional: vec![(Operation::Plus, Term::IntConst(1))],
-                        }))]),
+                        statements: vec![Statement::Return(
+                            Some(Expression {
+                                term: Term::VarName("a".into(), Span::default()),
+                                additional: vec![],
+                            }),
+                            Span::default(),
+                        )],
+                        else_statements: Some(vec![Statement::Return(
+                            Some(Expression {
+                                term: Term::VarName("a".into(), Span::default()),
+                                additional: vec![(Operation::Plus, Term::IntConst(1))],
+                            }),
+                            Span::default(),
+                        )]),
+                        span: Span::default(),
                     }],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
-        assert_eq!(expected, super::run(input_program.into()));
+        assert_eq!(Ok(expected), parse_without_spans(input_program));
     }
 
     #[test]
@@ -209,22 +300,32 @@ mod tests {
         let expected = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Int),
                 name: "run".into(),
-                parameters: vec![Parameter { name: "n".into(), jack_type: JackType::Int }],
+                parameters: vec![Parameter {
+                    name: "n".into(),
+                    jack_type: JackType::Int,
+                    span: Span::default(),
+                }],
                 body: SubroutineBody {
                     vars: vec![],
-                    statements: vec![Statement::Return(Some(Expression {
-                        term: Term::VarName("n".into()),
-                        additional: vec![(Operation::Plus, Term::IntConst(1))],
-                    }))],
+                    statements: vec![Statement::Return(
+                        Some(Expression {
+                            term: Term::VarName("n".into(), Span::default()),
+                            additional: vec![(Operation::Plus, Term::IntConst(1))],
+                        }),
+                        Span::default(),
+                    )],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
-        assert_eq!(expected, super::run(input_program.into()));
+        assert_eq!(Ok(expected), parse_without_spans(input_program));
     }
 
     #[test]
@@ -240,25 +341,35 @@ mod tests {
         let expected = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Int),
                 name: "run".into(),
-                parameters: vec![Parameter { name: "n".into(), jack_type: JackType::Int }],
+                parameters: vec![Parameter {
+                    name: "n".into(),
+                    jack_type: JackType::Int,
+                    span: Span::default(),
+                }],
                 body: SubroutineBody {
                     vars: vec![],
-                    statements: vec![Statement::Return(Some(Expression {
-                        term: Term::Expression(Box::new(Expression {
-                            term: Term::VarName("n".into()),
-                            additional: vec![(Operation::Plus, Term::IntConst(1))],
-                        })),
-                        additional: vec![],
-                    }))],
+                    statements: vec![Statement::Return(
+                        Some(Expression {
+                            term: Term::Expression(Box::new(Expression {
+                                term: Term::VarName("n".into(), Span::default()),
+                                additional: vec![(Operation::Plus, Term::IntConst(1))],
+                            })),
+                            additional: vec![],
+                        }),
+                        Span::default(),
+                    )],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
-        assert_eq!(expected, super::run(input_program.into()));
+        assert_eq!(Ok(expected), parse_without_spans(input_program));
     }
 
     #[test]
@@ -274,6 +385,7 @@ mod tests {
         let expected = Class {
             name: "Example".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Int),
@@ -281,21 +393,26 @@ mod tests {
                 parameters: vec![],
                 body: SubroutineBody {
                     vars: vec![],
-                    statements: vec![Statement::Return(Some(Expression {
-                        term: Term::Expression(Box::new(Expression {
+                    statements: vec![Statement::Return(
+                        Some(Expression {
                             term: Term::Expression(Box::new(Expression {
-                                term: Term::IntConst(1),
-                                additional: vec![(Operation::Plus, Term::IntConst(2))],
+                                term: Term::Expression(Box::new(Expression {
+                                    term: Term::IntConst(1),
+                                    additional: vec![(Operation::Plus, Term::IntConst(2))],
+                                })),
+                                additional: vec![(Operation::Plus, Term::IntConst(3))],
                             })),
-                            additional: vec![(Operation::Plus, Term::IntConst(3))],
-                        })),
-                        additional: vec![],
-                    }))],
+                            additional: vec![],
+                        }),
+                        Span::default(),
+                    )],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
-        assert_eq!(expected, super::run(input_program.into()));
+        assert_eq!(Ok(expected), parse_without_spans(input_program));
     }
 
     #[test]
@@ -315,52 +432,71 @@ mod tests {
         let expected = Class {
             name: "Fibonacci".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Int),
                 name: "run".into(),
-                parameters: vec![Parameter { name: "n".into(), jack_type: JackType::Int }],
+                parameters: vec![Parameter {
+                    name: "n".into(),
+                    jack_type: JackType::Int,
+                    span: Span::default(),
+                }],
                 body: SubroutineBody {
                     vars: vec![],
                     statements: vec![Statement::If {
                         exp: Expression {
-                            term: Term::VarName("n".into()),
+                            term: Term::VarName("n".into(), Span::default()),
                             additional: vec![(Operation::LessThan, Term::IntConst(2))],
                         },
-                        statements: vec![Statement::Return(Some(Expression {
-                            term: Term::VarName("n".into()),
-                            additional: vec![],
-                        }))],
-                        else_statements: Some(vec![Statement::Return(Some(Expression {
-                            term: Term::Expression(Box::new(Expression {
-                                term: Term::Call(SubroutineCall {
-                                    callee: Some("Fibonacci".into()),
-                                    routine_name: "run".into(),
-                                    expressions: vec![Expression {
-                                        term: Term::VarName("n".into()),
-                                        additional: vec![(Operation::Minus, Term::IntConst(2))],
-                                    }],
-                                }),
-                                additional: vec![(
-                                    Operation::Plus,
-                                    Term::Call(SubroutineCall {
+                        statements: vec![Statement::Return(
+                            Some(Expression {
+                                term: Term::VarName("n".into(), Span::default()),
+                                additional: vec![],
+                            }),
+                            Span::default(),
+                        )],
+                        else_statements: Some(vec![Statement::Return(
+                            Some(Expression {
+                                term: Term::Expression(Box::new(Expression {
+                                    term: Term::Call(SubroutineCall {
                                         callee: Some("Fibonacci".into()),
                                         routine_name: "run".into(),
                                         expressions: vec![Expression {
-                                            term: Term::VarName("n".into()),
-                                            additional: vec![(Operation::Minus, Term::IntConst(1))],
+                                            term: Term::VarName("n".into(), Span::default()),
+                                            additional: vec![(Operation::Minus, Term::IntConst(2))],
                                         }],
+                                        span: Span::default(),
                                     }),
-                                )],
-                            })),
-                            additional: vec![],
-                        }))]),
+                                    additional: vec![(
+                                        Operation::Plus,
+                                        Term::Call(SubroutineCall {
+                                            callee: Some("Fibonacci".into()),
+                                            routine_name: "run".into(),
+                                            expressions: vec![Expression {
+                                                term: Term::VarName("n".into(), Span::default()),
+                                                additional: vec![(
+                                                    Operation::Minus,
+                                                    Term::IntConst(1),
+                                                )],
+                                            }],
+                                            span: Span::default(),
+                                        }),
+                                    )],
+                                })),
+                                additional: vec![],
+                            }),
+                            Span::default(),
+                        )]),
+                        span: Span::default(),
                     }],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
-        assert_eq!(expected, super::run(input_program.into()));
+        assert_eq!(Ok(expected), parse_without_spans(input_program));
     }
 
     #[test]
@@ -392,6 +528,7 @@ mod tests {
         let expected = Class {
             name: "Main".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Void,
@@ -399,10 +536,30 @@ mod tests {
                 parameters: vec![],
                 body: SubroutineBody {
                     vars: vec![
-                        VarDec { jack_type: JackType::Class("Array".into()), name: "a".into() },
-                        VarDec { jack_type: JackType::Int, name: "length".into() },
-                        VarDec { jack_type: JackType::Int, name: "i".into() },
-                        VarDec { jack_type: JackType::Int, name: "sum".into() },
+                        VarDec {
+                            jack_type: JackType::Class("Array".into()),
+                            name: "a".into(),
+                            span: Span::default(),
+                            declaration_span: Span::default(),
+                        },
+                        VarDec {
+                            jack_type: JackType::Int,
+                            name: "length".into(),
+                            span: Span::default(),
+                            declaration_span: Span::default(),
+                        },
+                        VarDec {
+                            jack_type: JackType::Int,
+                            name: "i".into(),
+                            span: Span::default(),
+                            declaration_span: Span::default(),
+                        },
+                        VarDec {
+                            jack_type: JackType::Int,
+                            name: "sum".into(),
+                            span: Span::default(),
+                            declaration_span: Span::default(),
+                        },
                     ],
                     statements: vec![
                         Statement::Let {
@@ -416,9 +573,11 @@ mod tests {
                                         term: Term::StrConst("How many numbers? ".into()),
                                         additional: vec![],
                                     }],
+                                    span: Span::default(),
                                 }),
                                 additional: vec![],
                             },
+                            span: Span::default(),
                         },
                         Statement::Let {
                             var_name: "a".into(),
@@ -428,31 +587,34 @@ mod tests {
                                     callee: Some("Array".into()),
                                     routine_name: "new".into(),
                                     expressions: vec![Expression {
-                                        term: Term::VarName("length".into()),
+                                        term: Term::VarName("length".into(), Span::default()),
                                         additional: vec![],
                                     }],
+                                    span: Span::default(),
                                 }),
                                 additional: vec![],
                             },
+                            span: Span::default(),
                         },
                         Statement::Let {
                             var_name: "i".into(),
                             array_access: None,
                             exp: Expression { term: Term::IntConst(0), additional: vec![] },
+                            span: Span::default(),
                         },
                         Statement::While {
                             exp: Expression {
-                                term: Term::VarName("i".into()),
+                                term: Term::VarName("i".into(), Span::default()),
                                 additional: vec![(
                                     Operation::LessThan,
-                                    Term::VarName("length".into()),
+                                    Term::VarName("length".into(), Span::default()),
                                 )],
                             },
                             statements: vec![
                                 Statement::Let {
                                     var_name: "a".into(),
                                     array_access: Some(Expression {
-                                        term: Term::VarName("i".into()),
+                                        term: Term::VarName("i".into(), Span::default()),
                                         additional: vec![],
                                     }),
                                     exp: Expression {
@@ -463,36 +625,45 @@ mod tests {
                                                 term: Term::StrConst("Enter a number: ".into()),
                                                 additional: vec![],
                                             }],
+                                            span: Span::default(),
                                         }),
                                         additional: vec![],
                                     },
+                                    span: Span::default(),
                                 },
                                 Statement::Let {
                                     var_name: "sum".into(),
                                     array_access: None,
                                     exp: Expression {
-                                        term: Term::VarName("sum".into()),
+                                        term: Term::VarName("sum".into(), Span::default()),
                                         additional: vec![(
                                             Operation::Plus,
                                             Term::ArrayAccess {
                                                 var_name: "a".into(),
                                                 exp: Box::new(Expression {
-                                                    term: Term::VarName("i".into()),
+                                                    term: Term::VarName(
+                                                        "i".into(),
+                                                        Span::default(),
+                                                    ),
                                                     additional: vec![],
                                                 }),
+                                                span: Span::default(),
                                             },
                                         )],
                                     },
+                                    span: Span::default(),
                                 },
                                 Statement::Let {
                                     var_name: "i".into(),
                                     array_access: None,
                                     exp: Expression {
-                                        term: Term::VarName("i".into()),
+                                        term: Term::VarName("i".into(), Span::default()),
                                         additional: vec![(Operation::Plus, Term::IntConst(1))],
                                     },
+                                    span: Span::default(),
                                 },
                             ],
+                            span: Span::default(),
                         },
                         Statement::Do(SubroutineCall {
                             callee: Some("Output".into()),
@@ -501,25 +672,29 @@ mod tests {
                                 term: Term::StrConst("The average is ".into()),
                                 additional: vec![],
                             }],
+                            span: Span::default(),
                         }),
                         Statement::Do(SubroutineCall {
                             callee: Some("Output".into()),
                             routine_name: "printInt".into(),
                             expressions: vec![Expression {
-                                term: Term::VarName("sum".into()),
+                                term: Term::VarName("sum".into(), Span::default()),
                                 additional: vec![(
                                     Operation::Divide,
-                                    Term::VarName("length".into()),
+                                    Term::VarName("length".into(), Span::default()),
                                 )],
                             }],
+                            span: Span::default(),
                         }),
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
-        assert_eq!(expected, super::run(input.into()));
+        assert_eq!(Ok(expected), parse_without_spans(input));
     }
 
     #[test]
@@ -540,7 +715,7 @@ mod tests {
             }
         "#;
 
-        assert_eq!(3, super::run(input.into()).routines.len());
+        assert_eq!(3, super::run(input.into(), "Main.jack").unwrap().routines.len());
     }
 
     #[test]
@@ -560,18 +735,25 @@ mod tests {
         let expected = Class {
             name: "Main".into(),
             vars: vec![],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 name: "main".into(),
                 return_type: ReturnType::Void,
                 parameters: vec![],
                 routine_type: RoutineType::Function,
                 body: SubroutineBody {
-                    vars: vec![VarDec { name: "a".into(), jack_type: JackType::Int }],
+                    vars: vec![VarDec {
+                        name: "a".into(),
+                        jack_type: JackType::Int,
+                        span: Span::default(),
+                        declaration_span: Span::default(),
+                    }],
                     statements: vec![
                         Statement::Let {
                             var_name: "a".into(),
                             array_access: None,
                             exp: Expression { term: Term::IntConst(5), additional: vec![] },
+                            span: Span::default(),
                         },
                         Statement::Let {
                             var_name: "a".into(),
@@ -579,25 +761,527 @@ mod tests {
                             exp: Expression {
                                 term: Term::Unary {
                                     op: Operation::Not,
-                                    term: Box::new(Term::VarName("a".into())),
+                                    term: Box::new(Term::VarName("a".into(), Span::default())),
                                 },
                                 additional: vec![],
                             },
+                            span: Span::default(),
                         },
                         Statement::Do(SubroutineCall {
                             callee: Some("Output".into()),
                             routine_name: "printInt".into(),
                             expressions: vec![Expression {
-                                term: Term::VarName("a".into()),
+                                term: Term::VarName("a".into(), Span::default()),
                                 additional: vec![],
                             }],
+                            span: Span::default(),
                         }),
-                        Statement::Return(None),
+                        Statement::Return(None, Span::default()),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
-        assert_eq!(expected, super::run(input_program.into()));
+        assert_eq!(Ok(expected), parse_without_spans(input_program));
+    }
+
+    #[test]
+    fn report_every_parse_error_with_its_location() {
+        let input_program = r#"
+            class Main {
+                field int x
+                field int y;
+
+                function void main() {
+                    var int a;
+                    let a = 1
+                    let a = a + ;
+                    do Output.printInt(a);
+                    if (a) { let = 2; }
+                    return;
+                }
+
+                method int get( {
+                    return x;
+                }
+            }
+        "#;
+
+        let errors: Vec<String> = super::run(input_program.into(), "Main.jack")
+            .unwrap_err()
+            .iter()
+            .map(|err| err.to_string())
+            .collect();
+
+        assert_eq!(
+            vec![
+                "Main.jack:4:17: expected `;`, found `field`",
+                "Main.jack:9:21: expected `;`, found `let`",
+                "Main.jack:9:33: expected a term, found `;`",
+                "Main.jack:11:34: expected a variable name, found `=`",
+                "Main.jack:15:33: expected a type, found `{`",
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn locate_declarations_and_references() {
+        let input_program = "class Main {\n  field int x;\n  method void set(int v) {\n    let x = v;\n    do draw(this);\n    return;\n  }\n}";
+        let class = super::run(input_program.into(), "Main.jack").unwrap();
+        let routine = &class.routines[0];
+        let at = |line, column| Span { file: "Main.jack".into(), line, column };
+
+        assert_eq!(at(2, 13), class.vars[0].span);
+        assert_eq!(at(3, 15), routine.span);
+        assert_eq!(at(3, 23), routine.parameters[0].span);
+
+        match &routine.body.statements[..] {
+            [Statement::Let { span, exp, .. }, Statement::Do(call), Statement::Return(None, _)] => {
+                assert_eq!(at(4, 9), *span);
+                assert_eq!(Term::VarName("v".into(), at(4, 13)), exp.term);
+                assert_eq!(at(5, 8), call.span);
+                assert_eq!(Term::KeywordConst("this".into(), at(5, 13)), call.expressions[0].term);
+            }
+            statements => panic!("Unexpected statements {:?}", statements),
+        }
+    }
+
+    #[test]
+    fn attach_doc_comments_to_declarations() {
+        let input_program = "class Screen {
+    /** Drawing color, true for black */
+    static boolean color, other;
+
+    /* not documentation */
+    field int size;
+
+    /**
+     * Draws a pixel.
+     *
+     * Coordinates are checked.
+     */
+    // unrelated
+    function void drawPixel(int x, int y) { return; }
+}";
+        let class = super::run(input_program.into(), "Screen.jack").unwrap();
+        let docs: Vec<Option<&str>> = class.vars.iter().map(|var| var.doc.as_deref()).collect();
+
+        assert_eq!(
+            vec![
+                Some("Drawing color, true for black"),
+                Some("Drawing color, true for black"),
+                None
+            ],
+            docs
+        );
+        assert_eq!(
+            Some("Draws a pixel.\n\nCoordinates are checked."),
+            class.routines[0].doc.as_deref()
+        );
+    }
+
+    #[test]
+    fn parse_integer_constants_in_every_base() {
+        let input_program = "class Main {\n  function int main() {\n    return 0x1F + 0b101 + 0xFFFF + -32768;\n  }\n}";
+        let class = super::run(input_program.into(), "Main.jack").unwrap();
+
+        match &class.routines[0].body.statements[..] {
+            [Statement::Return(Some(exp), _)] => {
+                assert_eq!(Term::IntConst(31), exp.term);
+                assert_eq!(
+                    vec![
+                        (Operation::Plus, Term::IntConst(5)),
+                        (Operation::Plus, Term::IntConst(0xFFFF)),
+                        (
+                            Operation::Plus,
+                            Term::Unary {
+                                op: Operation::Minus,
+                                term: Term::IntConst(32768).into()
+                            }
+                        ),
+                    ],
+                    exp.additional
+                );
+            }
+            statements => panic!("Unexpected statements {statements:?}"),
+        }
+    }
+
+    #[test]
+    fn report_integer_constants_out_of_range() {
+        let input_program = "class Main {
+  function void main() {
+    let a = 70000;
+    let a = 32768;
+    let a = -32769 + 0b2;
+    let a = -0x10000;
+    return;
+  }
+}";
+
+        let errors: Vec<String> = super::run(input_program.into(), "Main.jack")
+            .unwrap_err()
+            .iter()
+            .map(|err| err.to_string())
+            .collect();
+
+        assert_eq!(
+            vec![
+                "Main.jack:5:22: expected a decimal, `0x` hexadecimal or `0b` binary integer, found `0b2`",
+            ],
+            errors
+        );
+
+        let errors: Vec<String> = super::run(input_program.replace(" + 0b2", ""), "Main.jack")
+            .unwrap_err()
+            .iter()
+            .map(|err| err.to_string())
+            .collect();
+
+        assert_eq!(
+            vec![
+                "Main.jack:3:13: expected an integer between -32768 and 32767, found `70000`",
+                "Main.jack:4:13: expected an integer between -32768 and 32767, found `32768`",
+                "Main.jack:5:14: expected an integer between -32768 and 32767, found `32769`",
+                "Main.jack:6:14: expected a 16 bit integer, up to 0xFFFF or 0b1111111111111111, found `0x10000`",
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn parse_for_loop_with_break_and_continue() {
+        let input_program = "class Main {
+  function void main() {
+    for (let i = 0; i < 3; let i = i + 1) { if (i) { continue; } break; }
+    for (;;) {}
+    return;
+  }
+}";
+        let class = super::run(input_program.into(), "Main.jack").unwrap();
+        let at = |line, column| Span { file: "Main.jack".into(), line, column };
+        let var = |name: &str, column| Term::VarName(name.into(), at(3, column));
+
+        assert_eq!(
+            vec![
+                Statement::For {
+                    init: Some(
+                        Statement::Let {
+                            var_name: "i".into(),
+                            array_access: None,
+                            exp: Expression { term: Term::IntConst(0), additional: vec![] },
+                            span: at(3, 14),
+                        }
+                        .into()
+                    ),
+                    exp: Some(Expression {
+                        term: var("i", 21),
+                        additional: vec![(Operation::LessThan, Term::IntConst(3))],
+                    }),
+                    step: Some(
+                        Statement::Let {
+                            var_name: "i".into(),
+                            array_access: None,
+                            exp: Expression {
+                                term: var("i", 36),
+                                additional: vec![(Operation::Plus, Term::IntConst(1))],
+                            },
+                            span: at(3, 32),
+                        }
+                        .into()
+                    ),
+                    statements: vec![
+                        Statement::If {
+                            exp: Expression { term: var("i", 49), additional: vec![] },
+                            statements: vec![Statement::Continue(at(3, 54))],
+                            else_statements: None,
+                            span: at(3, 45),
+                        },
+                        Statement::Break(at(3, 66)),
+                    ],
+                    span: at(3, 5),
+                },
+                Statement::For {
+                    init: None,
+                    exp: None,
+                    step: None,
+                    statements: vec![],
+                    span: at(4, 5),
+                },
+                Statement::Return(None, at(5, 5)),
+            ],
+            class.routines[0].body.statements
+        );
+    }
+
+    #[test]
+    fn parse_initializers() {
+        let input_program = "class Main {
+  static int a = 1, b;
+}";
+        let class = super::run(input_program.into(), "Main.jack").unwrap();
+        let at = |line, column| Span { file: "Main.jack".into(), line, column };
+        let var = |name: &str, column, exp| ClassVarDec {
+            var_type: ClassVarType::Static,
+            jack_type: JackType::Int,
+            name: name.into(),
+            span: at(2, column),
+            declaration_span: at(2, 3),
+            exp,
+            doc: None,
+        };
+
+        assert_eq!(
+            vec![
+                var("a", 14, Some(Expression { term: Term::IntConst(1), additional: vec![] })),
+                var("b", 21, None),
+            ],
+            class.vars
+        );
+    }
+
+    #[test]
+    fn parse_precedence_mode() {
+        let input_program = "// pragma precedence
+class Main {
+  static int a = 1 + 2 * 3 - 4, b = (1 + 2) * 3;
+}";
+        let class = super::run(input_program.into(), "Main.jack").unwrap();
+        let binary =
+            |left, op, right| Term::Binary { left: Box::new(left), op, right: Box::new(right) };
+        let tree = |term| Some(Expression { term, additional: vec![] });
+
+        assert_eq!(
+            tree(binary(
+                binary(
+                    Term::IntConst(1),
+                    Operation::Plus,
+                    binary(Term::IntConst(2), Operation::Multiply, Term::IntConst(3)),
+                ),
+                Operation::Minus,
+                Term::IntConst(4),
+            )),
+            class.vars[0].exp
+        );
+        assert_eq!(
+            tree(binary(
+                Term::Expression(Box::new(
+                    tree(binary(Term::IntConst(1), Operation::Plus, Term::IntConst(2))).unwrap()
+                )),
+                Operation::Multiply,
+                Term::IntConst(3),
+            )),
+            class.vars[1].exp
+        );
+
+        /* the pragma is kept by the tokenizer which discards comments */
+        let tokens = tokenizer::tokenize(input_program, "Main.jack").unwrap();
+        assert_eq!(Ok(class), parser::parse(tokens));
+
+        /* the pragma only applies before the class */
+        let input_program = "class Main { // pragma precedence
+  static int a = 1 + 2;
+}";
+        let class = super::run(input_program.into(), "Main.jack").unwrap();
+        assert_eq!(
+            Some(Expression {
+                term: Term::IntConst(1),
+                additional: vec![(Operation::Plus, Term::IntConst(2))],
+            }),
+            class.vars[0].exp
+        );
+    }
+
+    #[test]
+    fn parse_declarations_within_blocks() {
+        let input_program = "class Main {
+  function void main(int x) {
+    var int a;
+    while (x) { var int b; }
+    var char c;
+  }
+}";
+        let class = super::run(input_program.into(), "Main.jack").unwrap();
+        let at = |line, column| Span { file: "Main.jack".into(), line, column };
+        let var = |jack_type, name: &str, line, column, declaration_column| VarDec {
+            jack_type,
+            name: name.into(),
+            span: at(line, column),
+            declaration_span: at(line, declaration_column),
+        };
+
+        assert_eq!(vec![var(JackType::Int, "a", 3, 13, 5)], class.routines[0].body.vars);
+        assert_eq!(
+            vec![
+                Statement::While {
+                    exp: Expression {
+                        term: Term::VarName("x".into(), at(4, 12)),
+                        additional: vec![],
+                    },
+                    statements: vec![Statement::Var(vec![var(JackType::Int, "b", 4, 25, 17)])],
+                    span: at(4, 5),
+                },
+                Statement::Var(vec![var(JackType::Char, "c", 5, 14, 5)]),
+            ],
+            class.routines[0].body.statements
+        );
+    }
+
+    #[test]
+    fn parse_compound_assignments() {
+        let input_program = "class Main {
+  function void main(Array a, int x) {
+    let a[x] *= x - -1;
+    let x--;
+    let x = x--1;
+    return;
+  }
+}";
+        let class = super::run(input_program.into(), "Main.jack").unwrap();
+        let at = |line, column| Span { file: "Main.jack".into(), line, column };
+        let x = |line, column| Term::VarName("x".into(), at(line, column));
+        let minus_one = || Term::Unary { op: Operation::Minus, term: Box::new(Term::IntConst(1)) };
+
+        assert_eq!(
+            vec![
+                Statement::Update {
+                    var_name: "a".into(),
+                    array_access: Some(Expression { term: x(3, 11), additional: vec![] }),
+                    op: Operation::Multiply,
+                    exp: Some(Expression {
+                        term: x(3, 17),
+                        additional: vec![(Operation::Minus, minus_one())],
+                    }),
+                    span: at(3, 9),
+                },
+                Statement::Update {
+                    var_name: "x".into(),
+                    array_access: None,
+                    op: Operation::Minus,
+                    exp: None,
+                    span: at(4, 9),
+                },
+                /* `--` within expressions is a subtraction followed by a negation */
+                Statement::Let {
+                    var_name: "x".into(),
+                    array_access: None,
+                    exp: Expression {
+                        term: x(5, 13),
+                        additional: vec![(Operation::Minus, minus_one())],
+                    },
+                    span: at(5, 9),
+                },
+                Statement::Return(None, at(6, 5)),
+            ],
+            class.routines[0].body.statements
+        );
+
+        let separated = "class Main { function void main(int x) { let x + = 1; return; } }";
+        let errors: Vec<String> = super::run(separated.into(), "Main.jack")
+            .unwrap_err()
+            .iter()
+            .map(|err| err.to_string())
+            .collect();
+        assert_eq!(vec!["Main.jack:1:48: expected `=`, found `+`"], errors);
+    }
+
+    #[test]
+    fn parse_else_if_chain_and_switch() {
+        let input_program = "class Main {
+  function void main(int x) {
+    if (x) { return; } else if (x) { return; } else { return; }
+    switch (x) { case 1: case -2: return; default: return; }
+  }
+}";
+        let class = super::run(input_program.into(), "Main.jack").unwrap();
+        let at = |line, column| Span { file: "Main.jack".into(), line, column };
+        let x = |line, column| Expression {
+            term: Term::VarName("x".into(), at(line, column)),
+            additional: vec![],
+        };
+
+        assert_eq!(
+            vec![
+                Statement::If {
+                    exp: x(3, 9),
+                    statements: vec![Statement::Return(None, at(3, 14))],
+                    else_statements: Some(vec![Statement::If {
+                        exp: x(3, 33),
+                        statements: vec![Statement::Return(None, at(3, 38))],
+                        else_statements: Some(vec![Statement::Return(None, at(3, 55))]),
+                        span: at(3, 29),
+                    }]),
+                    span: at(3, 5),
+                },
+                Statement::Switch {
+                    exp: x(4, 13),
+                    cases: vec![Case {
+                        values: vec![
+                            Term::IntConst(1),
+                            Term::Unary { op: Operation::Minus, term: Term::IntConst(2).into() },
+                        ],
+                        statements: vec![Statement::Return(None, at(4, 35))],
+                        span: at(4, 18),
+                    }],
+                    default: Some(vec![Statement::Return(None, at(4, 52))]),
+                    span: at(4, 5),
+                },
+            ],
+            class.routines[0].body.statements
+        );
+    }
+
+    #[test]
+    fn parse_constants_and_enums() {
+        let input_program = "class Keys {
+  /** Last key */
+  const int LAST = Keys.LEFT;
+  enum Arrow { LEFT = 130, UP, }
+}";
+        let class = super::run(input_program.into(), "Keys.jack").unwrap();
+        let at = |line, column| Span { file: "Keys.jack".into(), line, column };
+
+        assert_eq!(
+            vec![
+                ConstDec {
+                    jack_type: JackType::Int,
+                    name: "LAST".into(),
+                    exp: Some(Expression {
+                        term: Term::ClassConst {
+                            class_name: "Keys".into(),
+                            name: "LEFT".into(),
+                            span: at(3, 20),
+                        },
+                        additional: vec![],
+                    }),
+                    span: at(3, 13),
+                    declaration_span: at(3, 3),
+                    enum_name: None,
+                    doc: Some("Last key".into()),
+                },
+                ConstDec {
+                    jack_type: JackType::Int,
+                    name: "LEFT".into(),
+                    exp: Some(Expression { term: Term::IntConst(130), additional: vec![] }),
+                    span: at(4, 16),
+                    declaration_span: at(4, 3),
+                    enum_name: Some("Arrow".into()),
+                    doc: None,
+                },
+                ConstDec {
+                    jack_type: JackType::Int,
+                    name: "UP".into(),
+                    exp: None,
+                    span: at(4, 28),
+                    declaration_span: at(4, 3),
+                    enum_name: Some("Arrow".into()),
+                    doc: None,
+                },
+            ],
+            class.consts
+        );
     }
 }
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
+
 use super::super::grammar::*;
-use super::tokenizer::Token;
+use super::ParseError;
+use super::tokenizer::{Span, Token, TokenKind, escape, int_value, is_precedence_pragma};
 
 /// Tokens -> recursive application of grammar rules -> derivation tree
 ///
@@ -46,400 +49,579 @@ use super::tokenizer::Token;
 /// </class>
 ///
 /// The XML representation is simply a human readable one.
-pub fn parse(tokens: Vec<Token>) -> Class {
+///
+/// The parser stops at the first token not fitting the grammar, records what was expected
+/// in its place, and resumes right after: at the next statement within a subroutine body,
+/// at the next declaration within a class. Every error of the class is then reported at once.
+pub fn parse(tokens: Vec<Token>) -> Result<Class, Vec<ParseError>> {
     let mut parser = Parser::new(tokens);
-    parser.eval_class()
+
+    match parser.eval_class() {
+        Ok(class) if parser.errors.is_empty() => Ok(class),
+        Ok(_) => Err(parser.errors),
+        Err(err) => {
+            parser.errors.push(err);
+            Err(parser.errors)
+        }
+    }
 }
 
+type ParseResult<T> = Result<T, ParseError>;
+
+const STATEMENT_KEYWORDS: [&str; 10] =
+    ["var", "let", "if", "while", "for", "switch", "break", "continue", "do", "return"];
+const DECLARATION_KEYWORDS: [&str; 7] =
+    ["static", "field", "const", "enum", "constructor", "function", "method"];
+const OPERATIONS: [&str; 11] = ["+", "-", "*", "/", "&", "|", "<", ">", "=", "&&", "||"];
+
 struct Parser {
     index: usize,
     tokens: Vec<Token>,
+    /* doc comments, by index of the token they precede */
+    docs: HashMap<usize, String>,
+    /* operations grouped by precedence rather than applied from left to right */
+    precedence: bool,
+    errors: Vec<ParseError>,
 }
 
 impl Parser {
+    /* Comments are set aside: `/** */` ones document the declaration following them,
+     * a `// pragma precedence` one before the class selects precedence mode */
     fn new(tokens: Vec<Token>) -> Self {
-        Self { index: 0, tokens }
+        let mut code_tokens: Vec<Token> = vec![];
+        let mut docs: HashMap<usize, String> = HashMap::new();
+        let mut precedence = false;
+
+        tokens.into_iter().for_each(|token| match token.kind {
+            TokenKind::Comment(text) if text.starts_with("/**") && text != "/**/" => {
+                docs.insert(code_tokens.len(), doc_text(&text));
+            }
+            TokenKind::Comment(text) if code_tokens.is_empty() && is_precedence_pragma(&text) => {
+                precedence = true;
+            }
+            TokenKind::Comment(_) => {}
+            _ => code_tokens.push(token),
+        });
+
+        Self { index: 0, tokens: code_tokens, docs, precedence, errors: vec![] }
     }
 
     /* ================================= */
     /* ======= Program Structure ======= */
     /* ================================= */
 
-    /* 'class' className '{' classVarDec* subroutineDec* '}' */
-    fn eval_class(self: &mut Self) -> Class {
-        self.advance();
-        let class_name = self.eval_class_name();
-        self.advance();
-        self.advance();
+    /* 'class' className '{' (classVarDec | constDec | enumDec)* subroutineDec* '}' */
+    fn eval_class(self: &mut Self) -> ParseResult<Class> {
+        self.expect_keyword("class")?;
+        let class_name = self.eval_class_name()?;
+        self.expect_symbol("{")?;
 
         let mut class_var_decs: Vec<ClassVarDec> = vec![];
+        let mut const_decs: Vec<ConstDec> = vec![];
         let mut subroutine_decs: Vec<SubroutineDec> = vec![];
 
-        while let Token::Keyword(val) = self.current() {
-            if !["static", "field", "constructor", "function", "method"].contains(&val.as_str()) {
-                break;
-            }
+        while self.current().is_some() && !self.at_symbol("}") {
+            let start = self.index;
 
-            match val.as_str() {
-                "static" | "field" => {
-                    self.eval_class_var_dec().into_iter().for_each(|c| class_var_decs.push(c));
-                    self.advance();
+            let result = match self.current_keyword() {
+                Some("static" | "field") => {
+                    self.eval_class_var_dec().map(|decs| class_var_decs.extend(decs))
                 }
-                "constructor" | "function" | "method" => {
-                    subroutine_decs.push(self.eval_subroutine_dec());
+                Some("const") => self.eval_const_dec().map(|dec| const_decs.push(dec)),
+                Some("enum") => self.eval_enum_dec().map(|decs| const_decs.extend(decs)),
+                Some("constructor" | "function" | "method") => {
+                    self.eval_subroutine_dec().map(|dec| subroutine_decs.push(dec))
                 }
-                _ => {}
-            }
+                _ => Err(self.error("a class variable or subroutine declaration")),
+            };
 
-            if self.index == self.tokens.len() - 1 {
-                break;
+            if let Err(err) = result {
+                self.errors.push(err);
+                self.synchronize_declaration(start);
             }
         }
 
-        Class { name: class_name, vars: class_var_decs, routines: subroutine_decs }
+        self.expect_symbol("}")?;
+
+        Ok(Class {
+            name: class_name,
+            vars: class_var_decs,
+            consts: const_decs,
+            routines: subroutine_decs,
+        })
     }
 
-    /* ('static'|'field') type varName (',' varName)* ';' */
-    fn eval_class_var_dec(self: &mut Self) -> Vec<ClassVarDec> {
+    /* ('static'|'field') type varName ('=' expression)? (',' varName ('=' expression)?)* ';' */
+    fn eval_class_var_dec(self: &mut Self) -> ParseResult<Vec<ClassVarDec>> {
+        let doc = self.docs.get(&self.index).cloned();
+        let declaration_span = self.span();
+        let var_type: ClassVarType = self.take_text().try_into().unwrap();
+        let jack_type: JackType = self.eval_type()?.try_into().unwrap();
+
         let mut class_var_decs: Vec<ClassVarDec> = vec![];
-        let var_type = format!("{}", self.tokens[self.index]);
 
-        self.advance();
-        let jack_type = self.eval_type();
+        loop {
+            let span = self.span();
+            let name = self.eval_var_name()?;
+            let exp = if self.at_symbol("=") {
+                self.advance();
+                Some(self.eval_expression()?)
+            } else {
+                None
+            };
+            class_var_decs.push(ClassVarDec {
+                var_type: var_type.clone(),
+                jack_type: jack_type.clone(),
+                name,
+                span,
+                declaration_span: declaration_span.clone(),
+                exp,
+                doc: doc.clone(),
+            });
+
+            if !self.at_symbol(",") {
+                break;
+            }
+            self.advance();
+        }
 
-        self.advance();
-        let var_name = self.eval_var_name();
+        self.expect_symbol(";")?;
 
-        class_var_decs.push(ClassVarDec {
-            var_type: var_type.try_into().unwrap(),
-            jack_type: jack_type.try_into().unwrap(),
-            name: var_name,
-        });
+        Ok(class_var_decs)
+    }
 
+    /* 'const' ('int'|'char'|'boolean') constName '=' expression ';' */
+    fn eval_const_dec(self: &mut Self) -> ParseResult<ConstDec> {
+        let doc = self.docs.get(&self.index).cloned();
+        let declaration_span = self.span();
         self.advance();
 
-        while let Token::Symbol(val) = self.current() {
-            if val == ";" {
-                break;
-            }
+        if !matches!(self.current_keyword(), Some("int" | "char" | "boolean")) {
+            return Err(self.error("`int`, `char` or `boolean`"));
+        }
+        let jack_type: JackType = self.take_text().try_into().unwrap();
+
+        let span = self.span();
+        let name = self.expect_identifier("a constant name")?;
+        self.expect_symbol("=")?;
+        let exp = self.eval_expression()?;
+        self.expect_symbol(";")?;
+
+        Ok(ConstDec {
+            jack_type,
+            name,
+            exp: Some(exp),
+            span,
+            declaration_span,
+            enum_name: None,
+            doc,
+        })
+    }
 
-            if let Token::Symbol(_) = self.current() {
+    /* 'enum' enumName '{' constName ('=' expression)? (',' constName ('=' expression)?)* ','? '}' */
+    fn eval_enum_dec(self: &mut Self) -> ParseResult<Vec<ConstDec>> {
+        let declaration_span = self.span();
+        self.advance();
+        let enum_name = self.expect_identifier("an enum name")?;
+        self.expect_symbol("{")?;
+
+        let mut members: Vec<ConstDec> = vec![];
+        loop {
+            let doc = self.docs.get(&self.index).cloned();
+            let span = self.span();
+            let name = self.expect_identifier("a constant name")?;
+            let exp = if self.at_symbol("=") {
                 self.advance();
+                Some(self.eval_expression()?)
+            } else {
+                None
+            };
 
-                class_var_decs.push(ClassVarDec {
-                    var_type: class_var_decs[0].var_type.clone(),
-                    jack_type: class_var_decs[0].jack_type.clone(),
-                    name: self.eval_var_name(),
-                });
-
-                self.advance();
+            members.push(ConstDec {
+                jack_type: JackType::Int,
+                name,
+                exp,
+                span,
+                declaration_span: declaration_span.clone(),
+                enum_name: Some(enum_name.clone()),
+                doc,
+            });
+
+            if !self.at_symbol(",") {
+                break;
+            }
+            self.advance();
+            if self.at_symbol("}") {
+                break;
             }
         }
+        self.expect_symbol("}")?;
 
-        class_var_decs
+        Ok(members)
     }
 
     /* ('constructor'|'function'|'method') ('void'|type) subroutineName '(' parameterList ')' subroutineBody */
-    fn eval_subroutine_dec(self: &mut Self) -> SubroutineDec {
-        let routine_type = format!("{}", self.current());
-        self.advance();
+    fn eval_subroutine_dec(self: &mut Self) -> ParseResult<SubroutineDec> {
+        let doc = self.docs.get(&self.index).cloned();
+        let routine_type: RoutineType = self.take_text().try_into().unwrap();
 
-        let return_type = match self.current() {
-            Token::Keyword(val) => match val.as_str() {
-                "void" => val.to_owned(),
-                "int" | "char" | "boolean" => self.eval_type(),
-                _ => "".to_string(),
-            },
-            Token::Identifier(_) => self.eval_type(),
-            _ => "".to_string(),
-        };
+        let return_type =
+            if self.at_keyword("void") { self.take_text() } else { self.eval_type()? };
 
-        self.advance();
-        let routine_name = if let Token::Identifier(_) = self.current() {
-            self.eval_subroutine_name()
-        } else {
-            "".to_string() // panic, actually
-        };
+        let span = self.span();
+        let routine_name = self.eval_subroutine_name()?;
 
-        self.advance();
-        self.advance();
-        let mut parameters: Vec<Parameter> = vec![];
+        self.expect_symbol("(")?;
+        let parameters = self.eval_parameter_list()?;
+        self.expect_symbol(")")?;
 
-        match self.current() {
-            Token::Symbol(_) => { /* do nothing */ }
-            _ => parameters = self.eval_parameter_list(),
-        }
+        let routine_body = self.eval_subroutine_body()?;
 
-        self.advance();
-        let mut routine_body: SubroutineBody = SubroutineBody::default();
-
-        if let Token::Keyword(val) = self.next() {
-            if ["var", "let", "if", "do", "while", "return"].contains(&val.as_str()) {
-                self.advance();
-                routine_body = self.eval_subroutine_body();
-            }
-        }
-
-        if self.index < self.tokens.len() - 1 {
-            self.advance();
-        }
-
-        SubroutineDec {
-            routine_type: routine_type.try_into().unwrap(),
+        Ok(SubroutineDec {
+            routine_type,
             return_type: return_type.try_into().unwrap(),
             name: routine_name,
-            parameters: parameters,
+            parameters,
             body: routine_body,
-        }
+            span,
+            doc,
+        })
     }
 
     /* 'int'|'char'|'boolean'|className */
-    fn eval_type(self: &mut Self) -> String {
-        match self.current() {
-            Token::Keyword(val) => match val.as_str() {
-                "int" | "char" | "boolean" => format!("{}", self.tokens[self.index]),
-                _ => panic!("Should have been int|char|boolean"),
-            },
-            Token::Identifier(_) => self.eval_class_name(),
-            _ => panic!("Should have been className"),
+    fn eval_type(self: &mut Self) -> ParseResult<String> {
+        match self.current_kind() {
+            Some(TokenKind::Keyword(val)) if ["int", "char", "boolean"].contains(&val.as_str()) => {
+                Ok(self.take_text())
+            }
+            Some(TokenKind::Identifier(_)) => self.eval_class_name(),
+            _ => Err(self.error("a type")),
         }
     }
 
     /* identifier */
-    fn eval_subroutine_name(self: &mut Self) -> String {
-        format!("{}", self.current())
+    fn eval_subroutine_name(self: &mut Self) -> ParseResult<String> {
+        self.expect_identifier("a subroutine name")
     }
 
     /* ( (type varName) (',' type varName)* )? */
-    fn eval_parameter_list(self: &mut Self) -> Vec<Parameter> {
+    fn eval_parameter_list(self: &mut Self) -> ParseResult<Vec<Parameter>> {
         let mut parameters: Vec<Parameter> = vec![];
 
-        let param_type = self.eval_type();
-
-        self.advance();
-        let param_name = self.eval_var_name();
-
-        parameters.push(Parameter { jack_type: param_type.try_into().unwrap(), name: param_name });
-
-        self.advance();
+        if self.at_symbol(")") {
+            return Ok(parameters);
+        }
 
-        while let Token::Symbol(_) = self.current() {
-            if self.current() == &Token::Symbol(")".into()) {
+        loop {
+            let param_type = self.eval_type()?;
+            let span = self.span();
+            let param_name = self.eval_var_name()?;
+            parameters.push(Parameter {
+                jack_type: param_type.try_into().unwrap(),
+                name: param_name,
+                span,
+            });
+
+            if !self.at_symbol(",") {
                 break;
             }
-
-            self.advance();
-            let param_type = self.eval_type();
-            self.advance();
-            let param_name = self.eval_var_name();
-            parameters
-                .push(Parameter { jack_type: param_type.try_into().unwrap(), name: param_name });
             self.advance();
         }
 
-        parameters
+        Ok(parameters)
     }
 
     /* identifier */
-    fn eval_var_name(self: &mut Self) -> String {
-        format!("{}", self.tokens[self.index])
+    fn eval_var_name(self: &mut Self) -> ParseResult<String> {
+        self.expect_identifier("a variable name")
     }
 
     /* identifier */
-    fn eval_class_name(self: &mut Self) -> String {
-        format!("{}", self.tokens[self.index])
+    fn eval_class_name(self: &mut Self) -> ParseResult<String> {
+        self.expect_identifier("a class name")
     }
 
     /* '{' varDec* statements '}' */
-    fn eval_subroutine_body(self: &mut Self) -> SubroutineBody {
+    fn eval_subroutine_body(self: &mut Self) -> ParseResult<SubroutineBody> {
+        self.expect_symbol("{")?;
+
         let mut vars: Vec<VarDec> = vec![];
-        let mut statements: Vec<Statement> = vec![];
 
-        while let Token::Keyword(val) = self.current() {
-            match val.as_str() {
-                "var" => {
-                    self.eval_var_dec().into_iter().for_each(|v| vars.push(v));
-                    self.advance();
-                }
-                "let" | "if" | "do" | "while" | "return" => {
-                    self.eval_statements().into_iter().for_each(|s| statements.push(s));
+        while self.at_keyword("var") {
+            let start = self.index;
+            match self.eval_var_dec() {
+                Ok(var_decs) => vars.extend(var_decs),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.synchronize_statement(start);
                 }
-                _ => {}
             }
         }
 
-        SubroutineBody { vars, statements }
+        let statements = self.eval_statements();
+        self.expect_symbol("}")?;
+
+        Ok(SubroutineBody { vars, statements })
     }
 
     /* 'var' type varName (',' varName)* ';' */
-    fn eval_var_dec(self: &mut Self) -> Vec<VarDec> {
-        let mut var_decs: Vec<VarDec> = vec![];
-
+    fn eval_var_dec(self: &mut Self) -> ParseResult<Vec<VarDec>> {
+        let declaration_span = self.span();
         self.advance();
-        let var_type = self.eval_type();
+        let var_type: JackType = self.eval_type()?.try_into().unwrap();
 
-        self.advance();
-        let var_name = self.eval_var_name();
-
-        var_decs.push(VarDec { jack_type: var_type.try_into().unwrap(), name: var_name });
+        let mut var_decs: Vec<VarDec> = vec![];
 
-        self.advance();
+        loop {
+            let span = self.span();
+            var_decs.push(VarDec {
+                jack_type: var_type.clone(),
+                name: self.eval_var_name()?,
+                span,
+                declaration_span: declaration_span.clone(),
+            });
 
-        while let Token::Symbol(val) = self.current() {
-            if val == ";" {
+            if !self.at_symbol(",") {
                 break;
             }
-
-            if let Token::Symbol(_) = self.current() {
-                self.advance();
-                let var_name = self.eval_var_name();
-
-                var_decs
-                    .push(VarDec { jack_type: var_decs[0].jack_type.to_owned(), name: var_name });
-
-                self.advance();
-            }
+            self.advance();
         }
 
-        var_decs
+        self.expect_symbol(";")?;
+
+        Ok(var_decs)
     }
 
     /* ============================== */
     /* ========= Statements ========= */
     /* ============================== */
 
-    /* statement* */
+    /* statement*
+     * Statements are parsed up to the closing '}' of the enclosing block, or up to the next
+     * case of a switch: a statement failing to parse is recorded and skipped */
     fn eval_statements(self: &mut Self) -> Vec<Statement> {
         let mut statements: Vec<Statement> = vec![];
 
-        while let Token::Keyword(val) = self.current() {
-            if ["if", "let", "do", "while", "return"].contains(&val.as_str()) {
-                statements.push(self.eval_statement());
-            }
+        while self.current().is_some()
+            && !self.at_symbol("}")
+            && !self.at_keyword("case")
+            && !self.at_keyword("default")
+        {
+            let start = self.index;
 
-            self.advance();
+            match self.eval_statement() {
+                Ok(statement) => statements.push(statement),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.synchronize_statement(start);
+                }
+            }
         }
 
         statements
     }
 
-    /* letStatement|ifStatement|whileStatement|doStatement|returnStatement */
-    fn eval_statement(self: &mut Self) -> Statement {
-        match self.current() {
-            Token::Keyword(val) => match val.as_str() {
-                "return" => self.eval_return_statement(),
-                "if" => self.eval_if_statement(),
-                "let" => self.eval_let_statement(),
-                "do" => self.eval_do_statement(),
-                "while" => self.eval_while_statement(),
-                _ => panic!("Should have been a Statement"),
-            },
-            _ => panic!("Should have been a Statement"),
+    /* varDec|letStatement|ifStatement|whileStatement|forStatement|switchStatement
+     * |breakStatement|continueStatement|doStatement|returnStatement */
+    fn eval_statement(self: &mut Self) -> ParseResult<Statement> {
+        match self.current_keyword() {
+            Some("var") => Ok(Statement::Var(self.eval_var_dec()?)),
+            Some("return") => self.eval_return_statement(),
+            Some("if") => self.eval_if_statement(),
+            Some("let") => self.eval_let_statement(";"),
+            Some("do") => self.eval_do_statement(";"),
+            Some("while") => self.eval_while_statement(),
+            Some("for") => self.eval_for_statement(),
+            Some("switch") => self.eval_switch_statement(),
+            Some("break") | Some("continue") => self.eval_jump_statement(),
+            _ => Err(self.error("a statement")),
         }
     }
 
     /* 'return' expression? ';' */
-    fn eval_return_statement(self: &mut Self) -> Statement {
+    fn eval_return_statement(self: &mut Self) -> ParseResult<Statement> {
+        let span = self.span();
         self.advance();
-        if let Token::Symbol(val) = self.current()
-            && val.as_str() == ";"
-        {
-            Statement::Return(None)
-        } else {
-            let exps = self.eval_expression();
-            self.advance();
-            Statement::Return(Some(exps))
-        }
+
+        let exp = if self.at_symbol(";") { None } else { Some(self.eval_expression()?) };
+        self.expect_symbol(";")?;
+
+        Ok(Statement::Return(exp, span))
     }
 
-    /* 'if' '(' expression ')' '{' statements '}' ( 'else' '{' statements '}' )? */
-    fn eval_if_statement(self: &mut Self) -> Statement {
+    /* 'if' '(' expression ')' '{' statements '}' ( 'else' ('{' statements '}' | ifStatement) )?
+     * `else if` standing for an else block holding the if statement alone */
+    fn eval_if_statement(self: &mut Self) -> ParseResult<Statement> {
+        let span = self.span();
         self.advance();
-        self.advance();
-        let exp = self.eval_expression();
+        self.expect_symbol("(")?;
+        let exp = self.eval_expression()?;
+        self.expect_symbol(")")?;
 
-        self.advance();
-        self.advance();
-        self.advance();
-        let statements = self.eval_statements();
+        let statements = self.eval_block()?;
 
-        /* TODO: avoid having to use either next or ahead(2) depending
-         * on whether the previous statement is `let` or `return` */
-        let else_statements = if let Token::Keyword(val) = self.next()
-            && val.as_str() == "else"
-        {
-            self.advance();
-            self.advance();
-            self.advance();
-            Some(self.eval_statements())
-        } else if let Token::Keyword(val) = self.ahead(2)
-            && val.as_str() == "else"
-        {
-            self.advance();
+        let else_statements = if self.at_keyword("else") {
             self.advance();
-            self.advance();
-            self.advance();
-            Some(self.eval_statements())
+            if self.at_keyword("if") {
+                Some(vec![self.eval_if_statement()?])
+            } else {
+                Some(self.eval_block()?)
+            }
         } else {
             None
         };
 
-        Statement::If { exp, statements, else_statements }
+        Ok(Statement::If { exp, statements, else_statements, span })
     }
 
-    /* 'let' varName ('[' expression ']')? '=' expression ';' */
-    fn eval_let_statement(self: &mut Self) -> Statement {
+    /* 'let' varName ('[' expression ']')? ('=' | op '=') expression ';' | 'let' varName ('++' | '--') ';'
+     * the step of a for statement ending with ')' instead */
+    fn eval_let_statement(self: &mut Self, terminator: &str) -> ParseResult<Statement> {
         self.advance();
-        let var_name = self.eval_var_name();
+        let span = self.span();
+        let var_name = self.eval_var_name()?;
 
-        self.advance();
-
-        let array_access = if self.current() == &Token::Symbol("[".to_string()) {
-            self.advance();
-            let exp = self.eval_expression();
+        let array_access = if self.at_symbol("[") {
             self.advance();
+            let exp = self.eval_expression()?;
+            self.expect_symbol("]")?;
             Some(exp)
         } else {
             None
         };
 
-        if array_access.is_some() {
+        if let Some((op, has_exp)) = self.compound_assignment() {
             self.advance();
+            self.advance();
+            let exp = if has_exp { Some(self.eval_expression()?) } else { None };
+            self.expect_symbol(terminator)?;
+            return Ok(Statement::Update { var_name, array_access, op, exp, span });
         }
 
-        self.advance();
-        let exp = self.eval_expression();
-        self.advance();
+        self.expect_symbol("=")?;
+        let exp = self.eval_expression()?;
+        self.expect_symbol(terminator)?;
 
-        Statement::Let { var_name, array_access, exp }
+        Ok(Statement::Let { var_name, array_access, exp, span })
     }
 
     /* 'while' '(' expression ')' '{' statements '}' */
-    fn eval_while_statement(self: &mut Self) -> Statement {
-        self.advance();
+    fn eval_while_statement(self: &mut Self) -> ParseResult<Statement> {
+        let span = self.span();
         self.advance();
-        let exp = self.eval_expression();
+        self.expect_symbol("(")?;
+        let exp = self.eval_expression()?;
+        self.expect_symbol(")")?;
+
+        let statements = self.eval_block()?;
+
+        Ok(Statement::While { exp, statements, span })
+    }
 
+    /* 'for' '(' (letStatement | doStatement | ';') expression? ';' (letStatement | doStatement)? ')'
+     * '{' statements '}', the step statement going without its ';' */
+    fn eval_for_statement(self: &mut Self) -> ParseResult<Statement> {
+        let span = self.span();
         self.advance();
+        self.expect_symbol("(")?;
+        let init = self.eval_for_clause(";")?;
+        let exp = if self.at_symbol(";") { None } else { Some(self.eval_expression()?) };
+        self.expect_symbol(";")?;
+        let step = self.eval_for_clause(")")?;
+
+        let statements = self.eval_block()?;
+
+        Ok(Statement::For { init, exp, step, statements, span })
+    }
+
+    /* (letStatement | doStatement)? terminator */
+    fn eval_for_clause(self: &mut Self, terminator: &str) -> ParseResult<Option<Box<Statement>>> {
+        match self.current_keyword() {
+            Some("let") => Ok(Some(self.eval_let_statement(terminator)?.into())),
+            Some("do") => Ok(Some(self.eval_do_statement(terminator)?.into())),
+            _ => {
+                self.expect_symbol(terminator)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /* 'switch' '(' expression ')' '{' ('case' term ':')+ statements)* ('default' ':' statements)? '}' */
+    fn eval_switch_statement(self: &mut Self) -> ParseResult<Statement> {
+        let span = self.span();
         self.advance();
+        self.expect_symbol("(")?;
+        let exp = self.eval_expression()?;
+        self.expect_symbol(")")?;
+        self.expect_symbol("{")?;
+
+        let mut cases: Vec<Case> = vec![];
+        while self.at_keyword("case") {
+            let span = self.span();
+            let mut values: Vec<Term> = vec![];
+
+            while self.at_keyword("case") {
+                self.advance();
+                values.push(self.eval_term()?);
+                self.expect_symbol(":")?;
+            }
+
+            cases.push(Case { values, statements: self.eval_statements(), span });
+        }
+
+        let default = if self.at_keyword("default") {
+            self.advance();
+            self.expect_symbol(":")?;
+            Some(self.eval_statements())
+        } else {
+            None
+        };
+
+        if !self.at_symbol("}") {
+            return Err(self.error(if default.is_some() {
+                "`}`"
+            } else {
+                "`case`, `default` or `}`"
+            }));
+        }
         self.advance();
 
-        let statements = self.eval_statements();
+        Ok(Statement::Switch { exp, cases, default, span })
+    }
+
+    /* ('break' | 'continue') ';' */
+    fn eval_jump_statement(self: &mut Self) -> ParseResult<Statement> {
+        let span = self.span();
+        let statement = match self.take_text().as_str() {
+            "break" => Statement::Break(span),
+            _ => Statement::Continue(span),
+        };
+        self.expect_symbol(";")?;
 
-        Statement::While { exp, statements }
+        Ok(statement)
     }
 
     /* 'do' subroutineCall ';' */
-    fn eval_do_statement(self: &mut Self) -> Statement {
-        self.advance();
-        let subroutine_call = self.eval_subroutine_call();
+    fn eval_do_statement(self: &mut Self, terminator: &str) -> ParseResult<Statement> {
         self.advance();
 
-        if let Term::Call(call) = subroutine_call {
-            Statement::Do(call)
-        } else {
-            panic!("Should return a Statement")
+        if !matches!(self.current_kind(), Some(TokenKind::Identifier(_))) {
+            return Err(self.error("a subroutine call"));
         }
+
+        let subroutine_call = self.eval_subroutine_call()?;
+        self.expect_symbol(terminator)?;
+
+        Ok(Statement::Do(subroutine_call))
+    }
+
+    /* '{' statements '}' */
+    fn eval_block(self: &mut Self) -> ParseResult<Vec<Statement>> {
+        self.expect_symbol("{")?;
+        let statements = self.eval_statements();
+        self.expect_symbol("}")?;
+
+        Ok(statements)
     }
 
     /* =============================== */
@@ -447,173 +629,381 @@ impl Parser {
     /* =============================== */
 
     /* term (op term)* */
-    fn eval_expression(self: &mut Self) -> Expression {
-        let term = self.eval_term();
+    fn eval_expression(self: &mut Self) -> ParseResult<Expression> {
+        if self.precedence {
+            let term = self.eval_binary(0)?;
+            return Ok(Expression { term, additional: vec![] });
+        }
+
+        let term = self.eval_term()?;
         let mut additional: Vec<(Operation, Term)> = vec![];
 
-        while let Token::Symbol(val) = self.next() {
-            if ["+", "-", "*", "/", "&", "|", "<", ">", "="].contains(&val.as_str()) {
-                self.advance();
-                let op = self.eval_op();
-                self.advance();
-                let op_term = self.eval_term();
-                additional.push((op, op_term));
-            } else {
-                break;
-            }
+        while let Some(TokenKind::Symbol(val)) = self.current_kind()
+            && OPERATIONS.contains(&val.as_str())
+        {
+            let op = self.eval_op();
+            let op_term = self.eval_term()?;
+            additional.push((op, op_term));
+        }
+
+        Ok(Expression { term, additional })
+    }
+
+    /* Precedence climbing: the operations binding tighter than `min_precedence` make
+     * the right operand, those of the same precedence apply from left to right */
+    fn eval_binary(self: &mut Self, min_precedence: u8) -> ParseResult<Term> {
+        let mut left = self.eval_term()?;
+
+        while let Some(TokenKind::Symbol(val)) = self.current_kind()
+            && OPERATIONS.contains(&val.as_str())
+            && let Ok(op) = Operation::try_from(val.clone())
+            && op.precedence() >= min_precedence
+        {
+            self.advance();
+            let right = self.eval_binary(op.precedence() + 1)?;
+            left = Term::Binary { left: Box::new(left), op, right: Box::new(right) };
         }
 
-        Expression { term, additional }
+        Ok(left)
     }
 
     /*
-     * integerConstant | stringConstant | keywordConstant | varName
+     * integerConstant | stringConstant | charConstant | keywordConstant | varName
      * | varName '[' expression ']' | '(' expression ')' | (unaryOp term)
      * | subroutineCall
      */
-    fn eval_term(self: &mut Self) -> Term {
-        match self.current() {
-            Token::IntConst(val) => Term::IntConst(val.parse().unwrap()),
-            Token::StrConst(val) => Term::StrConst(val.to_owned()),
-            Token::Keyword(_) => self.eval_keyword_constant(),
+    fn eval_term(self: &mut Self) -> ParseResult<Term> {
+        match self.current_kind() {
+            Some(TokenKind::IntConst(_)) => self.eval_int_const(false),
+            Some(TokenKind::StrConst(val)) => {
+                let term = Term::StrConst(val.to_owned());
+                self.advance();
+                Ok(term)
+            }
+            Some(TokenKind::CharConst(val)) => {
+                let term = Term::CharConst(*val);
+                self.advance();
+                Ok(term)
+            }
+            Some(TokenKind::Keyword(val))
+                if ["true", "false", "null", "this"].contains(&val.as_str()) =>
+            {
+                Ok(self.eval_keyword_constant())
+            }
             /* '(' expression ')' | (unaryOp term) */
-            Token::Symbol(val) => match val.as_str() {
-                "(" => {
-                    self.advance();
-                    let exp = self.eval_expression();
+            Some(TokenKind::Symbol(val)) if val == "(" => {
+                self.advance();
+                let exp = self.eval_expression()?;
+                self.expect_symbol(")")?;
+                Ok(Term::Expression(Box::new(exp)))
+            }
+            Some(TokenKind::Symbol(val)) if val == "-" || val == "~" => {
+                let unary_op = self.eval_unary_op();
+                let term = match self.current_kind() {
+                    Some(TokenKind::IntConst(_)) => {
+                        self.eval_int_const(unary_op == Operation::Minus)?
+                    }
+                    _ => self.eval_term()?,
+                };
+                Ok(Term::Unary { op: unary_op, term: Box::new(term) })
+            }
+            /* varName | varName '[' expression ']' | className '.' constName | subroutineCall */
+            Some(TokenKind::Identifier(_)) => match self.next_kind() {
+                Some(TokenKind::Symbol(val)) if val == "." && !self.at_call_after(2) => {
+                    let span = self.span();
+                    let class_name = self.take_text();
                     self.advance();
-                    Term::Expression(Box::new(exp))
+                    let name = self.expect_identifier("a constant or subroutine name")?;
+
+                    Ok(Term::ClassConst { class_name, name, span })
+                }
+                Some(TokenKind::Symbol(val)) if val == "(" || val == "." => {
+                    Ok(Term::Call(self.eval_subroutine_call()?))
                 }
-                "-" | "~" => {
-                    let unary_op = self.eval_unary_op();
+                Some(TokenKind::Symbol(val)) if val == "[" => {
+                    let span = self.span();
+                    let var_name = self.eval_var_name()?;
                     self.advance();
-                    let term = self.eval_term();
-                    Term::Unary { op: unary_op, term: Box::new(term) }
+                    let exp = self.eval_expression()?;
+                    self.expect_symbol("]")?;
+
+                    Ok(Term::ArrayAccess { var_name, exp: exp.into(), span })
                 }
-                _ => panic!("Not a term"),
-            },
-            Token::Identifier(_) => {
-                /* varName | varName '[' expression ']' | subroutineCall */
-                match self.next() {
-                    Token::Symbol(val) => match val.as_str() {
-                        "(" | "." => self.eval_subroutine_call(),
-                        "[" => {
-                            let var_name = self.eval_var_name();
-                            self.advance();
-
-                            self.advance();
-                            let exp = self.eval_expression();
-                            self.advance();
-
-                            Term::ArrayAccess { var_name: var_name, exp: exp.into() }
-                        }
-                        _ => Term::VarName(self.eval_var_name()),
-                    },
-                    _ => panic!("Not a term"),
+                _ => {
+                    let span = self.span();
+                    Ok(Term::VarName(self.eval_var_name()?, span))
                 }
+            },
+            _ => Err(self.error("a term")),
+        }
+    }
+
+    /* Decimal constants range up to 32767, or 32768 right after a minus sign.
+     * Hexadecimal and binary ones spell a 16 bit pattern, 0x8000 being -32768 */
+    fn eval_int_const(self: &mut Self, negated: bool) -> ParseResult<Term> {
+        let Some(TokenKind::IntConst(text)) = self.current_kind() else {
+            return Err(self.error("an integer"));
+        };
+
+        let (max, expected) = if text.chars().all(|c| c.is_ascii_digit()) {
+            (if negated { 32768 } else { 32767 }, "an integer between -32768 and 32767")
+        } else {
+            (0xFFFF, "a 16 bit integer, up to 0xFFFF or 0b1111111111111111")
+        };
+
+        match int_value(text).filter(|val| *val <= max) {
+            Some(val) => {
+                self.advance();
+                Ok(Term::IntConst(val))
             }
+            None => Err(self.error(expected)),
         }
     }
 
     /* subroutineName '(' expressionList ')' |
      * (className | varName) '.' subroutineName '(' expressionList ')' */
-    fn eval_subroutine_call(self: &mut Self) -> Term {
-        let callee = if let Token::Symbol(val) = self.next()
-            && val.as_str() == "."
-            && let Token::Identifier(val) = self.current()
+    fn eval_subroutine_call(self: &mut Self) -> ParseResult<SubroutineCall> {
+        let span = self.span();
+        let callee = if let Some(TokenKind::Symbol(val)) = self.next_kind()
+            && val == "."
         {
-            let name = if val.chars().nth(0).unwrap().is_ascii_uppercase() {
-                self.eval_class_name()
-            } else {
-                self.eval_var_name()
-            };
-
-            self.advance();
+            let name = self.expect_identifier("a class or variable name")?;
             self.advance();
             Some(name)
         } else {
             None
         };
 
-        let routine_name = format!("{}", self.current());
-
-        self.advance();
+        let routine_name = self.eval_subroutine_name()?;
 
-        let exps = if let Token::Symbol(val) = self.next()
-            && val.as_str() == ")"
-        {
-            vec![]
-        } else {
-            self.eval_expression_list()
-        };
+        self.expect_symbol("(")?;
+        let exps = self.eval_expression_list()?;
+        self.expect_symbol(")")?;
 
-        self.advance();
-
-        Term::Call(SubroutineCall { callee: callee, routine_name: routine_name, expressions: exps })
+        Ok(SubroutineCall { callee, routine_name, expressions: exps, span })
     }
 
     /* '+' | '-' | '*' | '/' | '&' | '|' | '<' | '>' | '=' */
     fn eval_op(self: &mut Self) -> Operation {
-        Operation::try_from(format!("{}", self.current())).unwrap()
+        Operation::try_from(self.take_text()).unwrap()
     }
 
     /* '-' | '~' */
     fn eval_unary_op(self: &mut Self) -> Operation {
-        Operation::try_from(format!("{}", self.current())).unwrap()
+        Operation::try_from(self.take_text()).unwrap()
     }
 
     /* 'true' | 'false' | 'null' | 'this' */
     fn eval_keyword_constant(self: &mut Self) -> Term {
-        Term::KeywordConst(format!("{}", self.current()))
+        let span = self.span();
+        Term::KeywordConst(self.take_text(), span)
     }
 
     /* (expression (',' expression)* )? */
-    fn eval_expression_list(self: &mut Self) -> Vec<Expression> {
+    fn eval_expression_list(self: &mut Self) -> ParseResult<Vec<Expression>> {
         let mut exps: Vec<Expression> = vec![];
 
-        self.advance();
-        exps.push(self.eval_expression());
+        if self.at_symbol(")") {
+            return Ok(exps);
+        }
 
-        while let Token::Symbol(val) = self.next() {
-            if val.as_str() == ")" {
-                break;
-            }
+        exps.push(self.eval_expression()?);
 
-            if val.as_str() == "," {
-                self.advance();
-                self.advance();
-                exps.push(self.eval_expression());
+        while self.at_symbol(",") {
+            self.advance();
+            exps.push(self.eval_expression()?);
+        }
+
+        Ok(exps)
+    }
+
+    /* ================================== */
+    /* ========= Error Recovery ========= */
+    /* ================================== */
+
+    /* Skip past the next ';', or up to the next statement keyword or '}' */
+    fn synchronize_statement(self: &mut Self, start: usize) {
+        if self.index == start {
+            self.advance();
+        }
+
+        while let Some(kind) = self.current_kind() {
+            match kind {
+                TokenKind::Symbol(val) if val == ";" => {
+                    self.advance();
+                    return;
+                }
+                TokenKind::Symbol(val) if val == "}" => return,
+                TokenKind::Keyword(val) if STATEMENT_KEYWORDS.contains(&val.as_str()) => return,
+                TokenKind::Keyword(val) if val == "case" || val == "default" => return,
+                _ => self.advance(),
             }
         }
+    }
+
+    /* Skip up to the next class variable or subroutine declaration,
+     * or up to the '}' closing the class, skipping over subroutine bodies */
+    fn synchronize_declaration(self: &mut Self, start: usize) {
+        if self.index == start {
+            self.advance();
+        }
 
-        exps
+        let mut depth: usize = 0;
+
+        while let Some(kind) = self.current_kind() {
+            match kind {
+                TokenKind::Keyword(val) if DECLARATION_KEYWORDS.contains(&val.as_str()) => return,
+                TokenKind::Symbol(val) if val == "}" && depth == 0 => return,
+                TokenKind::Symbol(val) if val == "}" => depth -= 1,
+                TokenKind::Symbol(val) if val == "{" => depth += 1,
+                _ => {}
+            }
+
+            self.advance();
+        }
     }
 
     /* ================================== */
     /* ========= Helper Methods ========= */
     /* ================================== */
 
-    fn current(self: &Self) -> &Token {
-        &self.tokens[self.index]
+    fn current(self: &Self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn current_kind(self: &Self) -> Option<&TokenKind> {
+        self.current().map(|token| &token.kind)
     }
 
-    fn next(self: &Self) -> &Token {
-        &self.tokens[self.index + 1]
+    fn next_kind(self: &Self) -> Option<&TokenKind> {
+        self.tokens.get(self.index + 1).map(|token| &token.kind)
     }
 
-    fn ahead(self: &Self, pos: usize) -> &Token {
-        &self.tokens[self.index + pos]
+    /* Whether the token `offset` tokens ahead is a name followed by '(' */
+    fn at_call_after(self: &Self, offset: usize) -> bool {
+        let kind = |offset: usize| self.tokens.get(self.index + offset).map(|token| &token.kind);
+        matches!(kind(offset), Some(TokenKind::Identifier(_)))
+            && matches!(kind(offset + 1), Some(TokenKind::Symbol(val)) if val == "(")
+    }
+
+    /* `+=`, `-=`, `*=`, `/=`, `&=` or `|=` followed by an expression, `++` or `--` without one.
+     * They are two symbol tokens with nothing in between, `--` standing for two minus signs
+     * within expressions */
+    fn compound_assignment(self: &Self) -> Option<(Operation, bool)> {
+        let (Some(first), Some(second)) = (self.current(), self.tokens.get(self.index + 1)) else {
+            return None;
+        };
+        let (TokenKind::Symbol(op), TokenKind::Symbol(next)) = (&first.kind, &second.kind) else {
+            return None;
+        };
+        if second.span.line != first.span.line || second.span.column != first.span.column + 1 {
+            return None;
+        }
+
+        match (op.as_str(), next.as_str()) {
+            ("+" | "-" | "*" | "/" | "&" | "|", "=") | ("+", "+") | ("-", "-") => {
+                Some((Operation::try_from(op.clone()).ok()?, next == "="))
+            }
+            _ => None,
+        }
+    }
+
+    fn current_keyword(self: &Self) -> Option<&str> {
+        match self.current_kind() {
+            Some(TokenKind::Keyword(val)) => Some(val.as_str()),
+            _ => None,
+        }
+    }
+
+    fn at_keyword(self: &Self, keyword: &str) -> bool {
+        self.current_keyword() == Some(keyword)
+    }
+
+    fn at_symbol(self: &Self, symbol: &str) -> bool {
+        matches!(self.current_kind(), Some(TokenKind::Symbol(val)) if val == symbol)
     }
 
     fn advance(self: &mut Self) {
         self.index += 1;
     }
+
+    /* Position of the current token, or of the last one once all tokens are consumed */
+    fn span(self: &Self) -> Span {
+        match self.current() {
+            Some(token) => token.span.clone(),
+            None => self.tokens.last().map(|token| token.span.clone()).unwrap_or_default(),
+        }
+    }
+
+    /* Text of the current token, moving past it */
+    fn take_text(self: &mut Self) -> String {
+        let text = format!("{}", self.current().unwrap());
+        self.advance();
+        text
+    }
+
+    fn expect_keyword(self: &mut Self, keyword: &str) -> ParseResult<()> {
+        if self.at_keyword(keyword) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{keyword}`")))
+        }
+    }
+
+    fn expect_symbol(self: &mut Self, symbol: &str) -> ParseResult<()> {
+        if self.at_symbol(symbol) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{symbol}`")))
+        }
+    }
+
+    fn expect_identifier(self: &mut Self, expected: &str) -> ParseResult<String> {
+        match self.current_kind() {
+            Some(TokenKind::Identifier(_)) => Ok(self.take_text()),
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    /* Error at the current token, or right after the last one once all tokens are consumed */
+    fn error(self: &Self, expected: &str) -> ParseError {
+        match self.current() {
+            Some(token) => {
+                let found = match &token.kind {
+                    TokenKind::StrConst(val) => format!("\"{}\"", escape(val, '"')),
+                    kind => format!("`{kind}`"),
+                };
+                ParseError::new(token.span.clone(), expected, &found)
+            }
+            None => ParseError::new(self.span(), expected, "end of file"),
+        }
+    }
+}
+
+/* `/** Line\n * next line */` -> "Line\nnext line" */
+fn doc_text(comment: &str) -> String {
+    let content = comment.trim_start_matches("/**").trim_end_matches("*/");
+    let lines: Vec<&str> = content
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            line.strip_prefix('*').map_or(line, |line| line.trim_start())
+        })
+        .collect();
+
+    lines.join("\n").trim().to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use super::super::super::grammar::*;
-    use super::Token::*;
+    use super::super::tokenizer::{Span, Token, TokenKind, TokenKind::*};
+
+    fn spanned(kinds: Vec<TokenKind>) -> Vec<Token> {
+        kinds.into_iter().map(|kind| Token { kind, span: Span::default() }).collect()
+    }
 
     #[test]
     fn parse_basic_class() {
@@ -676,13 +1066,22 @@ mod tests {
                     var_type: ClassVarType::Field,
                     jack_type: JackType::Int,
                     name: "a".to_owned(),
+                    span: Span::default(),
+                    declaration_span: Span::default(),
+                    exp: None,
+                    doc: None,
                 },
                 ClassVarDec {
                     var_type: ClassVarType::Field,
                     jack_type: JackType::Int,
                     name: "b".to_owned(),
+                    span: Span::default(),
+                    declaration_span: Span::default(),
+                    exp: None,
+                    doc: None,
                 },
             ],
+            consts: vec![],
             routines: vec![SubroutineDec {
                 routine_type: RoutineType::Function,
                 return_type: ReturnType::Type(JackType::Int),
@@ -693,28 +1092,35 @@ mod tests {
                     statements: vec![
                         Statement::While {
                             exp: Expression {
-                                term: Term::VarName("a".to_owned()),
+                                term: Term::VarName("a".to_owned(), Span::default()),
                                 additional: vec![(Operation::LessThan, Term::IntConst(10))],
                             },
                             statements: vec![Statement::Let {
                                 var_name: "a".to_owned(),
                                 array_access: None,
                                 exp: Expression {
-                                    term: Term::VarName("a".to_owned()),
+                                    term: Term::VarName("a".to_owned(), Span::default()),
                                     additional: vec![(Operation::Plus, Term::IntConst(1))],
                                 },
+                                span: Span::default(),
                             }],
+                            span: Span::default(),
                         },
-                        Statement::Return(Some(Expression {
-                            term: Term::VarName("a".to_owned()),
-                            additional: vec![],
-                        })),
+                        Statement::Return(
+                            Some(Expression {
+                                term: Term::VarName("a".to_owned(), Span::default()),
+                                additional: vec![],
+                            }),
+                            Span::default(),
+                        ),
                     ],
                 },
+                span: Span::default(),
+                doc: None,
             }],
         };
 
-        assert_eq!(expected, super::parse(token_stream));
+        assert_eq!(Ok(expected), super::parse(spanned(token_stream)));
     }
 
     #[test]
@@ -726,9 +1132,10 @@ mod tests {
             Symbol("}".into()),
         ];
 
-        let expected = Class { name: "Main".into(), vars: vec![], routines: vec![] };
+        let expected =
+            Class { name: "Main".into(), vars: vec![], consts: vec![], routines: vec![] };
 
-        assert_eq!(expected, super::Parser::new(tokens).eval_class());
+        assert_eq!(Ok(expected), super::Parser::new(spanned(tokens)).eval_class());
     }
 
     #[test]
@@ -747,15 +1154,23 @@ mod tests {
                 var_type: ClassVarType::Static,
                 jack_type: JackType::Int,
                 name: "a".into(),
+                span: Span::default(),
+                declaration_span: Span::default(),
+                exp: None,
+                doc: None,
             },
             ClassVarDec {
                 var_type: ClassVarType::Static,
                 jack_type: JackType::Int,
                 name: "b".into(),
+                span: Span::default(),
+                declaration_span: Span::default(),
+                exp: None,
+                doc: None,
             },
         ];
 
-        assert_eq!(expected, super::Parser::new(tokens).eval_class_var_dec());
+        assert_eq!(Ok(expected), super::Parser::new(spanned(tokens)).eval_class_var_dec());
     }
 
     #[test]
@@ -781,22 +1196,32 @@ mod tests {
             routine_type: RoutineType::Function,
             return_type: ReturnType::Type(JackType::Int),
             name: "incr".into(),
-            parameters: vec![Parameter { jack_type: JackType::Int, name: "a".into() }],
+            parameters: vec![Parameter {
+                jack_type: JackType::Int,
+                name: "a".into(),
+                span: Span::default(),
+            }],
             body: SubroutineBody {
                 vars: vec![],
-                statements: vec![Statement::Return(Some(Expression {
-                    term: Term::VarName("a".into()),
-                    additional: vec![(Operation::Plus, Term::IntConst(1))],
-                }))],
+                statements: vec![Statement::Return(
+                    Some(Expression {
+                        term: Term::VarName("a".into(), Span::default()),
+                        additional: vec![(Operation::Plus, Term::IntConst(1))],
+                    }),
+                    Span::default(),
+                )],
             },
+            span: Span::default(),
+            doc: None,
         };
 
-        assert_eq!(expected, super::Parser::new(tokens).eval_subroutine_dec());
+        assert_eq!(Ok(expected), super::Parser::new(spanned(tokens)).eval_subroutine_dec());
     }
 
     #[test]
     fn parse_subroutine_body() {
-        let tokens: Vec<super::Token> = vec![
+        let tokens = vec![
+            Symbol("{".into()),
             Keyword("var".into()),
             Keyword("int".into()),
             Identifier("localA".into()),
@@ -810,22 +1235,33 @@ mod tests {
             Symbol("=".into()),
             IntConst("1".into()),
             Symbol(";".into()),
-            Symbol("}".into()), /* only required due to `eval_subroutine_body` implementation */
+            Symbol("}".into()),
         ];
 
         let expected = SubroutineBody {
             vars: vec![
-                VarDec { jack_type: JackType::Int, name: "localA".into() },
-                VarDec { jack_type: JackType::Boolean, name: "localB".into() },
+                VarDec {
+                    jack_type: JackType::Int,
+                    name: "localA".into(),
+                    span: Span::default(),
+                    declaration_span: Span::default(),
+                },
+                VarDec {
+                    jack_type: JackType::Boolean,
+                    name: "localB".into(),
+                    span: Span::default(),
+                    declaration_span: Span::default(),
+                },
             ],
             statements: vec![Statement::Let {
                 var_name: "localA".into(),
                 array_access: None,
                 exp: Expression { term: Term::IntConst(1), additional: vec![] },
+                span: Span::default(),
             }],
         };
 
-        assert_eq!(expected, super::Parser::new(tokens).eval_subroutine_body());
+        assert_eq!(Ok(expected), super::Parser::new(spanned(tokens)).eval_subroutine_body());
     }
 
     #[test]
@@ -844,28 +1280,32 @@ mod tests {
             var_name: "a".into(),
             array_access: None,
             exp: Expression {
-                term: Term::VarName("a".into()),
+                term: Term::VarName("a".into(), Span::default()),
                 additional: vec![(Operation::Plus, Term::IntConst(1))],
             },
+            span: Span::default(),
         };
 
-        let actual = super::Parser::new(tokens).eval_let_statement();
+        let actual = super::Parser::new(spanned(tokens)).eval_let_statement(";");
 
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
     }
 
     #[test]
     fn parse_return_statement() {
         let tokens = vec![Keyword("return".into()), Identifier("a".into()), Symbol(";".into())];
 
-        let expected = Statement::Return(Some(Expression {
-            term: Term::VarName("a".into()),
-            additional: vec![],
-        }));
+        let expected = Statement::Return(
+            Some(Expression {
+                term: Term::VarName("a".into(), Span::default()),
+                additional: vec![],
+            }),
+            Span::default(),
+        );
 
-        let actual = super::Parser::new(tokens).eval_return_statement();
+        let actual = super::Parser::new(spanned(tokens)).eval_return_statement();
 
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
     }
 
     #[test]
@@ -900,30 +1340,33 @@ mod tests {
 
         let expected = Statement::If {
             exp: Expression {
-                term: Term::VarName("a".into()),
+                term: Term::VarName("a".into(), Span::default()),
                 additional: vec![(Operation::LessThan, Term::IntConst(5))],
             },
             statements: vec![Statement::Let {
                 var_name: "a".into(),
                 array_access: None,
                 exp: Expression {
-                    term: Term::VarName("a".into()),
+                    term: Term::VarName("a".into(), Span::default()),
                     additional: vec![(Operation::Plus, Term::IntConst(1))],
                 },
+                span: Span::default(),
             }],
             else_statements: Some(vec![Statement::Let {
                 var_name: "a".into(),
                 array_access: None,
                 exp: Expression {
-                    term: Term::VarName("a".into()),
+                    term: Term::VarName("a".into(), Span::default()),
                     additional: vec![(Operation::Multiply, Term::IntConst(2))],
                 },
+                span: Span::default(),
             }]),
+            span: Span::default(),
         };
 
-        let actual = super::Parser::new(tokens).eval_if_statement();
+        let actual = super::Parser::new(spanned(tokens)).eval_if_statement();
 
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
     }
 
     #[test]
@@ -948,22 +1391,24 @@ mod tests {
 
         let expected = Statement::While {
             exp: Expression {
-                term: Term::VarName("a".into()),
+                term: Term::VarName("a".into(), Span::default()),
                 additional: vec![(Operation::LessThan, Term::IntConst(5))],
             },
             statements: vec![Statement::Let {
                 var_name: "a".into(),
                 array_access: None,
                 exp: Expression {
-                    term: Term::VarName("a".into()),
+                    term: Term::VarName("a".into(), Span::default()),
                     additional: vec![(Operation::Plus, Term::IntConst(1))],
                 },
+                span: Span::default(),
             }],
+            span: Span::default(),
         };
 
-        let actual = super::Parser::new(tokens).eval_while_statement();
+        let actual = super::Parser::new(spanned(tokens)).eval_while_statement();
 
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
     }
 
     #[test]
@@ -983,9 +1428,13 @@ mod tests {
         let expected = Statement::Do(SubroutineCall {
             callee: Some("AnotherClass".into()),
             routine_name: "incr".into(),
-            expressions: vec![Expression { term: Term::VarName("a".into()), additional: vec![] }],
+            expressions: vec![Expression {
+                term: Term::VarName("a".into(), Span::default()),
+                additional: vec![],
+            }],
+            span: Span::default(),
         });
 
-        assert_eq!(expected, super::Parser::new(tokens).eval_do_statement());
+        assert_eq!(Ok(expected), super::Parser::new(spanned(tokens)).eval_do_statement(";"));
     }
 }
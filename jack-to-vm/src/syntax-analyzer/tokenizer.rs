@@ -1,148 +1,382 @@
-use std::fmt::Display;
+use std::{fmt::Display, rc::Rc};
 
-#[derive(Debug, PartialEq)]
-pub enum Token {
+use super::ParseError;
+
+/// Smallest meaningful unit of Jack source code, along with its position
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Lexical category of a token, holding its text
+#[derive(Debug, PartialEq, Clone)]
+pub enum TokenKind {
     Keyword(String),
     Symbol(String),
     Identifier(String),
+    /* Escape sequences resolved */
     StrConst(String),
+    CharConst(char),
     IntConst(String),
+    /* `// ...`, `/* ... */` or `/** ... */`, delimiters included: only kept on demand,
+     * save for a precedence pragma before any code */
+    Comment(String),
+}
+
+/// Position of the first character of a token: lines and columns start at 1
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Span {
+    pub file: Rc<str>,
+    pub line: usize,
+    pub column: usize,
 }
 
 impl Display for Token {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl Display for TokenKind {
     fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Keyword(val) => write!(f, "{val}"),
             Self::Symbol(val) => write!(f, "{val}"),
             Self::Identifier(val) => write!(f, "{val}"),
             Self::StrConst(val) => write!(f, "{val}"),
+            Self::CharConst(val) => write!(f, "'{}'", escape(&val.to_string(), '\'')),
             Self::IntConst(val) => write!(f, "{val}"),
+            Self::Comment(val) => write!(f, "{val}"),
         }
     }
 }
 
-pub fn tokenize(input: &str) -> Vec<Token> {
-    let input_without_comments = remove_comments(input);
-    let candidates = identify_candidate_tokens(input_without_comments);
+impl Display for Span {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
 
-    candidates
-        .iter()
-        .map(|candidate| {
-            if candidate.starts_with('\"') && candidate.ends_with('\"') {
-                return Token::StrConst(candidate.trim_matches('\"').to_string());
-            }
+/// Source -> tokens, each one carrying the position it was found at.
+///
+/// Whitespace and comments only separate tokens and are discarded, except for a
+/// `// pragma precedence` comment before any code, which selects how the class parses.
+/// Characters which cannot start any token are reported, and scanning goes on
+/// so that every lexical error of a file is reported at once.
+pub fn tokenize(input: &str, file: &str) -> Result<Vec<Token>, Vec<ParseError>> {
+    let mut scanner = Scanner::new(input, file, false);
+    scanner.scan();
+
+    if scanner.errors.is_empty() { Ok(scanner.tokens) } else { Err(scanner.errors) }
+}
 
-            if lexicon::KEYWORDS.contains(&candidate.as_str()) {
-                return Token::Keyword(candidate.to_string());
-            }
+/// Same as `tokenize`, comments being kept as `TokenKind::Comment` tokens
+/// for tools which print the source back, such as the formatter.
+pub fn tokenize_with_comments(input: &str, file: &str) -> Result<Vec<Token>, Vec<ParseError>> {
+    let mut scanner = Scanner::new(input, file, true);
+    scanner.scan();
 
-            if lexicon::SYMBOLS.contains(&candidate.chars().collect::<Vec<char>>()[0]) {
-                return Token::Symbol(candidate.to_string());
-            }
+    if scanner.errors.is_empty() { Ok(scanner.tokens) } else { Err(scanner.errors) }
+}
 
-            match candidate.parse::<usize>() {
-                Ok(_) => Token::IntConst(candidate.to_string()),
-                Err(_) => Token::Identifier(candidate.to_string()),
-            }
-        })
-        .collect()
+/// Whether the comment is a `// pragma precedence` one, operations of a class preceded
+/// by it being grouped by precedence rather than applied from left to right
+pub fn is_precedence_pragma(comment: &str) -> bool {
+    comment.strip_prefix("//").map(str::trim) == Some("pragma precedence")
+}
+
+/// Code of `c` in the Hack character set: printable ASCII characters keep their code and
+/// a newline is 128. The set has no tab, which is shown as a space instead.
+/// None for characters which the Hack platform cannot represent.
+pub fn hack_code(c: char) -> Option<u16> {
+    match c {
+        ' '..='~' => Some(c as u16),
+        '\n' => Some(128),
+        '\t' => Some(32),
+        _ => None,
+    }
+}
+
+/// Value of an integer constant, decimal, `0x` hexadecimal or `0b` binary,
+/// or None when it does not fit in 16 bits
+pub fn int_value(text: &str) -> Option<usize> {
+    let (digits, radix) = int_digits(text)?;
+    usize::from_str_radix(digits, radix).ok().filter(|val| *val <= 0xFFFF)
 }
 
-fn remove_comments(input: &str) -> String {
-    let mut output: String = String::new();
-    let mut index: usize = 0;
-    let chars: Vec<char> = input.chars().collect();
-    let len = chars.len();
+/* `0x1F` -> ("1F", 16), None when the digits do not belong to the radix */
+fn int_digits(text: &str) -> Option<(&str, u32)> {
+    let (digits, radix) = if let Some(digits) = text.strip_prefix("0x") {
+        (digits, 16)
+    } else if let Some(digits) = text.strip_prefix("0b") {
+        (digits, 2)
+    } else {
+        (text, 10)
+    };
 
-    while index < len - 1 {
-        if [&chars[index], &chars[index + 1]] == [&'/', &'/'] {
-            while &chars[index] != &'\n' {
-                index += 1;
-            }
+    (!digits.is_empty() && digits.chars().all(|c| c.is_digit(radix))).then_some((digits, radix))
+}
+
+/// Value of a string or character constant -> its source text, without the quotes
+pub fn escape(value: &str, quote: char) -> String {
+    value.chars().fold(String::new(), |mut text, c| {
+        match c {
+            '\n' => text.push_str("\\n"),
+            '\t' => text.push_str("\\t"),
+            '\\' => text.push_str("\\\\"),
+            c if c == quote => text.extend(['\\', c]),
+            c => text.push(c),
         }
+        text
+    })
+}
 
-        if [&chars[index], &chars[index + 1]] == [&'/', &'*'] {
-            while [&chars[index], &chars[index + 1]] != [&'*', &'/'] {
-                index += 1;
-            }
-            index += 2;
+struct Scanner {
+    chars: Vec<char>,
+    index: usize,
+    line: usize,
+    column: usize,
+    file: Rc<str>,
+    keep_comments: bool,
+    tokens: Vec<Token>,
+    errors: Vec<ParseError>,
+}
+
+impl Scanner {
+    fn new(input: &str, file: &str, keep_comments: bool) -> Self {
+        Self {
+            chars: input.chars().collect(),
+            index: 0,
+            line: 1,
+            column: 1,
+            file: file.into(),
+            keep_comments,
+            tokens: vec![],
+            errors: vec![],
         }
+    }
 
-        if index < len - 2
-            && [&chars[index], &chars[index + 1], &chars[index + 2]] == [&'/', &'*', &'*']
-        {
-            while [&chars[index], &chars[index + 1]] != [&'*', &'/'] {
-                index += 1;
+    fn scan(self: &mut Self) {
+        while let Some(c) = self.current() {
+            let span = self.span();
+
+            match c {
+                ' ' | '\t' | '\r' | '\n' => self.advance(),
+                '/' if self.next() == Some('/') => self.skip_line_comment(span),
+                '/' if self.next() == Some('*') => self.skip_block_comment(span),
+                '"' => self.scan_string(span),
+                '&' | '|' if self.next() == Some(c) => {
+                    self.advance();
+                    self.advance();
+                    self.push(TokenKind::Symbol(format!("{c}{c}")), span);
+                }
+                '\'' => self.scan_char_const(span),
+                c if lexicon::SYMBOLS.contains(&c) => {
+                    self.advance();
+                    self.push(TokenKind::Symbol(c.to_string()), span);
+                }
+                c if c.is_ascii_digit() => {
+                    let text = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                    match int_digits(&text) {
+                        Some(_) => self.push(TokenKind::IntConst(text), span),
+                        None => self.error(span, INTEGER_FORMATS, &format!("`{text}`")),
+                    }
+                }
+                c if c.is_ascii_alphabetic() || c == '_' => {
+                    let word = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                    if lexicon::KEYWORDS.contains(&word.as_str()) {
+                        self.push(TokenKind::Keyword(word), span);
+                    } else {
+                        self.push(TokenKind::Identifier(word), span);
+                    }
+                }
+                c => {
+                    self.advance();
+                    self.error(span, "a token", &format!("`{c}`"));
+                }
             }
         }
+    }
 
-        output.push(chars[index]);
-        index += 1;
+    fn skip_line_comment(self: &mut Self, span: Span) {
+        let text = self.take_while(|c| c != '\n');
+        self.push_comment(text, span);
+    }
 
-        if index + 1 == len {
-            output.push(chars[index]);
-            break;
+    /* Covers both `/* */` and `/** */` doc comments */
+    fn skip_block_comment(self: &mut Self, span: Span) {
+        let start = self.index;
+        self.advance();
+        self.advance();
+
+        loop {
+            match self.current() {
+                None => return self.error(span, "`*/` closing the comment", "end of file"),
+                Some('*') if self.next() == Some('/') => {
+                    self.advance();
+                    self.advance();
+                    let text = self.chars[start..self.index].iter().collect();
+                    return self.push_comment(text, span);
+                }
+                Some(_) => self.advance(),
+            }
         }
     }
 
-    output.lines().map(|l| l.trim().trim_matches('\t').trim_matches('\n').to_string()).collect()
-}
+    /* String constants cannot span multiple lines */
+    fn scan_string(self: &mut Self, span: Span) {
+        self.advance();
+        let mut value = String::new();
 
-fn identify_candidate_tokens(input_without_comments: String) -> Vec<String> {
-    let mut index: usize = 0;
-    let mut within_string_literal = false;
-    let chars: Vec<char> = input_without_comments.chars().collect();
-    let mut candidate: Vec<char> = vec![];
-    let mut candidates: Vec<String> = vec![];
-
-    while index < chars.len() {
-        if chars[index] == '\"' {
-            within_string_literal = !within_string_literal;
-            candidate.push(chars[index]);
-            index += 1;
-            continue;
+        loop {
+            match self.current() {
+                Some('"') => {
+                    self.advance();
+                    return self.push(TokenKind::StrConst(value), span);
+                }
+                Some('\n') => return self.error(span, "`\"` closing the string", "end of line"),
+                None => return self.error(span, "`\"` closing the string", "end of file"),
+                Some(_) => {
+                    if let Some(c) = self.scan_char() {
+                        value.push(c);
+                    }
+                }
+            }
         }
+    }
 
-        if within_string_literal {
-            candidate.push(chars[index]);
-            index += 1;
-            continue;
-        }
+    /* A single character or escape sequence between `'` */
+    fn scan_char_const(self: &mut Self, span: Span) {
+        self.advance();
 
-        if lexicon::SYMBOLS.contains(&chars[index]) {
-            if !candidate.is_empty() {
-                candidates.push(candidate.iter().collect());
-                candidate = vec![];
+        let value = match self.current() {
+            Some('\'') => {
+                self.advance();
+                return self.error(span, "a character", "`''`");
+            }
+            Some('\n') | None => return self.error(span, "a character", "end of line"),
+            Some(_) => self.scan_char(),
+        };
+
+        if self.current() == Some('\'') {
+            self.advance();
+            if let Some(c) = value {
+                self.push(TokenKind::CharConst(c), span);
             }
-            candidates.push(chars[index].to_string());
-            index += 1;
-            continue;
+        } else {
+            /* Skipped up to the closing quote of the line, if any */
+            let rest = self.take_while(|c| c != '\'' && c != '\n');
+            match self.current() {
+                Some('\'') => self.advance(),
+                _ => return self.error(span, "`'` closing the character", "end of line"),
+            }
+            self.error(span, "a single character", &format!("`{rest}` after it"));
         }
+    }
 
-        if chars[index] == ' ' {
-            if !candidate.is_empty() {
-                candidates.push(candidate.iter().collect());
-                candidate = vec![];
+    /* One character of a constant, escape sequences resolved, or None once reported */
+    fn scan_char(self: &mut Self) -> Option<char> {
+        let span = self.span();
+        let c = self.current()?;
+        self.advance();
+
+        let c = match c {
+            '\\' => {
+                let escaped = match self.current() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some(c @ ('"' | '\'' | '\\')) => c,
+                    Some(c) if c != '\n' => {
+                        self.advance();
+                        self.error(span, ESCAPE_SEQUENCES, &format!("`\\{}`", c.escape_debug()));
+                        return None;
+                    }
+                    _ => {
+                        self.error(span, ESCAPE_SEQUENCES, "end of line");
+                        return None;
+                    }
+                };
+                self.advance();
+                escaped
             }
-            index += 1;
-            continue;
+            c => c,
+        };
+
+        if hack_code(c).is_none() {
+            let found = format!("`{}`", c.escape_debug());
+            self.error(span, "a character of the Hack character set", &found);
+            return None;
         }
 
-        candidate.push(chars[index]);
-        index += 1;
+        Some(c)
+    }
+
+    fn take_while(self: &mut Self, predicate: impl Fn(char) -> bool) -> String {
+        let mut value = String::new();
+
+        while let Some(c) = self.current()
+            && predicate(c)
+        {
+            value.push(c);
+            self.advance();
+        }
+
+        value
+    }
+
+    fn current(self: &Self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn next(self: &Self) -> Option<char> {
+        self.chars.get(self.index + 1).copied()
     }
 
-    candidates
+    fn advance(self: &mut Self) {
+        if self.current() == Some('\n') {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        self.index += 1;
+    }
+
+    fn span(self: &Self) -> Span {
+        Span { file: self.file.clone(), line: self.line, column: self.column }
+    }
+
+    fn push(self: &mut Self, kind: TokenKind, span: Span) {
+        self.tokens.push(Token { kind, span });
+    }
+
+    fn push_comment(self: &mut Self, text: String, span: Span) {
+        /* the parser reads the pragma whatever the tokens are for */
+        if self.keep_comments || (self.tokens.is_empty() && is_precedence_pragma(&text)) {
+            self.push(TokenKind::Comment(text), span);
+        }
+    }
+
+    fn error(self: &mut Self, span: Span, expected: &str, found: &str) {
+        self.errors.push(ParseError::new(span, expected, found));
+    }
 }
 
+const INTEGER_FORMATS: &str = "a decimal, `0x` hexadecimal or `0b` binary integer";
+const ESCAPE_SEQUENCES: &str = "an escape sequence among `\\n`, `\\t`, `\\\"`, `\\'` and `\\\\`";
+
 mod lexicon {
-    pub const KEYWORDS: [&str; 21] = [
+    pub const KEYWORDS: [&str; 29] = [
         "class",
         "constructor",
         "function",
         "method",
         "field",
         "static",
+        "const",
+        "enum",
         "var",
         "int",
         "char",
@@ -157,23 +391,34 @@ mod lexicon {
         "if",
         "else",
         "while",
+        "for",
+        "break",
+        "continue",
+        "switch",
+        "case",
+        "default",
         "return",
     ];
 
-    pub const SYMBOLS: [char; 19] = [
+    pub const SYMBOLS: [char; 20] = [
         '{', '}', '[', ']', '(', ')', '.', ',', ';', '+', '-', '*', '/', '&', '|', '<', '>', '=',
-        '~',
+        '~', ':',
     ];
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        Token::{self, *},
-        tokenize,
+        Span,
+        TokenKind::{self, *},
+        hack_code, tokenize, tokenize_with_comments,
     };
 
-    fn simple_source_expected_tokens() -> Vec<Token> {
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        tokenize(source, "Test.jack").unwrap().into_iter().map(|token| token.kind).collect()
+    }
+
+    fn simple_source_expected_tokens() -> Vec<TokenKind> {
         vec![
             Keyword("if".to_string()),
             Symbol("(".to_string()),
@@ -199,7 +444,7 @@ mod tests {
     #[test]
     fn tokenize_simple_source() {
         let source = r#"if (x < 0) { let sign = "negative"; let anotherSign = "positive"; }"#;
-        assert_eq!(simple_source_expected_tokens(), tokenize(source))
+        assert_eq!(simple_source_expected_tokens(), kinds(source))
     }
 
     #[test]
@@ -209,7 +454,7 @@ mod tests {
             let anotherSign = "positive";
 		}"#;
 
-        assert_eq!(simple_source_expected_tokens(), tokenize(source))
+        assert_eq!(simple_source_expected_tokens(), kinds(source))
     }
 
     #[test]
@@ -220,12 +465,12 @@ mod tests {
             let anotherSign = /* another comment */ "positive";
 		}"#;
 
-        assert_eq!(simple_source_expected_tokens(), tokenize(source))
+        assert_eq!(simple_source_expected_tokens(), kinds(source))
     }
 
     #[test]
     fn tokenize_main_class_example() {
-        let expected: Vec<Token> = vec![
+        let expected: Vec<TokenKind> = vec![
             Keyword("class".to_string()),
             Identifier("Main".to_string()),
             Symbol("{".to_string()),
@@ -279,6 +524,118 @@ mod tests {
             }
         "#;
 
-        assert_eq!(expected, tokenize(source));
+        assert_eq!(expected, kinds(source));
+    }
+
+    #[test]
+    fn keep_comments_on_demand() {
+        let source = "let a = 1; // one\n/** doc\n * block */ return;";
+        let tokens = tokenize_with_comments(source, "Test.jack").unwrap();
+
+        assert_eq!(
+            vec![
+                Keyword("let".to_string()),
+                Identifier("a".to_string()),
+                Symbol("=".to_string()),
+                IntConst("1".to_string()),
+                Symbol(";".to_string()),
+                Comment("// one".to_string()),
+                Comment("/** doc\n * block */".to_string()),
+                Keyword("return".to_string()),
+                Symbol(";".to_string()),
+            ],
+            tokens.iter().map(|token| token.kind.clone()).collect::<Vec<TokenKind>>()
+        );
+        assert_eq!((2, 1), (tokens[6].span.line, tokens[6].span.column));
+        assert_eq!(7, kinds(source).len());
+    }
+
+    #[test]
+    fn tokenize_short_circuit_operators() {
+        assert_eq!(
+            vec![
+                Identifier("a".to_string()),
+                Symbol("&&".to_string()),
+                Identifier("b".to_string()),
+                Symbol("||".to_string()),
+                Symbol("&".to_string()),
+                Identifier("c".to_string()),
+                Symbol("|".to_string()),
+            ],
+            kinds("a&&b || &c|")
+        );
+    }
+
+    #[test]
+    fn tokens_separated_by_tabs_and_newlines_only() {
+        let source = "var\tint\ti;\nreturn\nx;";
+
+        assert_eq!(
+            vec![
+                Keyword("var".to_string()),
+                Keyword("int".to_string()),
+                Identifier("i".to_string()),
+                Symbol(";".to_string()),
+                Keyword("return".to_string()),
+                Identifier("x".to_string()),
+                Symbol(";".to_string()),
+            ],
+            kinds(source)
+        );
+    }
+
+    #[test]
+    fn tokens_carry_their_position() {
+        let tokens =
+            tokenize("class Main {\n\t/* comment */ field int x;\n}", "Main.jack").unwrap();
+        let spans: Vec<(usize, usize)> =
+            tokens.iter().map(|t| (t.span.line, t.span.column)).collect();
+
+        assert_eq!(
+            vec![(1, 1), (1, 7), (1, 12), (2, 16), (2, 22), (2, 26), (2, 27), (3, 1)],
+            spans
+        );
+        assert_eq!(Span { file: "Main.jack".into(), line: 2, column: 16 }, tokens[3].span);
+    }
+
+    #[test]
+    fn report_lexical_errors_with_position() {
+        let errors = tokenize("let a = #;\nlet s = \"open;", "Main.jack").unwrap_err();
+
+        assert_eq!(
+            vec![
+                "Main.jack:1:9: expected a token, found `#`",
+                "Main.jack:2:9: expected `\"` closing the string, found end of file",
+            ],
+            errors.iter().map(|e| e.to_string()).collect::<Vec<String>>()
+        );
+    }
+
+    #[test]
+    fn tokenize_char_constants_and_escape_sequences() {
+        let source = r#"let c = 'a'; let q = '\''; let s = "say \"hi\"\n\t\\ 'ok'";"#;
+
+        assert_eq!(CharConst('a'), kinds(source)[3]);
+        assert_eq!(CharConst('\''), kinds(source)[8]);
+        assert_eq!(StrConst("say \"hi\"\n\t\\ 'ok'".to_string()), kinds(source)[13]);
+        assert_eq!("'\\''", CharConst('\'').to_string());
+        assert_eq!(Some(128), hack_code('\n'));
+    }
+
+    #[test]
+    fn report_unrepresentable_characters() {
+        let source = "let s = \"café\\q\";\nlet c = '';\nlet d = 'ab';\nlet e = '\u{1F600}';";
+        let errors = tokenize(source, "Main.jack").unwrap_err();
+
+        assert_eq!(
+            vec![
+                "Main.jack:1:13: expected a character of the Hack character set, found `é`",
+                "Main.jack:1:14: expected an escape sequence among `\\n`, `\\t`, `\\\"`, `\\'` and `\\\\`, found `\\q`",
+                "Main.jack:2:9: expected a character, found `''`",
+                "Main.jack:3:9: expected a single character, found `b` after it",
+                "Main.jack:4:10: expected a character of the Hack character set, found `\u{1F600}`",
+            ],
+            errors.iter().map(|e| e.to_string()).collect::<Vec<String>>()
+        );
     }
 }
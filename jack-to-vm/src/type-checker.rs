@@ -0,0 +1,501 @@
+use super::Span;
+use super::code_generator::symbols::*;
+use super::grammar::*;
+use super::semantic_analyzer::SemanticError;
+
+/// Derivation trees -> type mismatches, checked on demand for stricter builds
+///
+/// Jack values are plain 16 bit words at runtime, so types only document intent.
+/// The checker runs after the semantic analysis and reports:
+/// - values assigned to variables, or passed as arguments, of an incompatible type
+/// - results of void subroutines used in expressions
+/// - `return` without a value in non-void subroutines, and with a value in void ones
+/// - subroutines not returning on every path
+///
+/// Compatibility follows how Jack code is commonly written:
+/// - `int`, `char` and `boolean` are interchangeable
+/// - `Array` stands for raw memory and is compatible with any type
+/// - `null` is compatible with any type, array elements may hold values of any type
+pub fn check(classes: &[Class], class_table: &ClassTable) -> Result<(), Vec<SemanticError>> {
+    let mut errors: Vec<SemanticError> = vec![];
+
+    classes.iter().for_each(|class| {
+        let mut type_checker = TypeChecker::new(class, class_table);
+        type_checker.check();
+        errors.extend(type_checker.errors);
+    });
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+/* Type of the value an expression evaluates to */
+#[derive(Debug, PartialEq, Clone)]
+enum ValueType {
+    Known(JackType),
+    Null,
+    Void,
+    Unknown,
+}
+
+struct TypeChecker<'a> {
+    class: &'a Class,
+    class_table: &'a ClassTable,
+    class_symbols: ClassSymbols,
+    routine: Option<&'a SubroutineDec>,
+    routine_symbols: Option<RoutineSymbols>,
+    errors: Vec<SemanticError>,
+}
+
+impl<'a> TypeChecker<'a> {
+    fn new(class: &'a Class, class_table: &'a ClassTable) -> Self {
+        let mut class_symbols = ClassSymbols::new(&class.vars);
+        class_symbols.add_constants(&class.name, class_table);
+
+        Self {
+            class,
+            class_table,
+            class_symbols,
+            routine: None,
+            routine_symbols: None,
+            errors: vec![],
+        }
+    }
+
+    fn check(self: &mut Self) {
+        self.class.vars.iter().for_each(|var| {
+            if let Some(exp) = &var.exp {
+                let value_type = self.expression_type(exp);
+                if !compatible(&var.jack_type, &value_type) {
+                    let message = format!(
+                        "cannot assign {} to `{}` of type {}",
+                        value_type_name(&value_type),
+                        var.name,
+                        var.jack_type
+                    );
+                    self.error(&var.span, message);
+                }
+            }
+        });
+        self.class.routines.iter().for_each(|routine| self.check_routine(routine));
+    }
+
+    fn check_routine(self: &mut Self, routine: &'a SubroutineDec) {
+        self.routine = Some(routine);
+        self.routine_symbols = Some(RoutineSymbols::new(routine, &self.class.name));
+
+        routine.body.statements.iter().for_each(|statement| self.check_statement(statement));
+
+        if !always_returns(&routine.body.statements) {
+            let message = format!("not every path of {} ends with a return", self.routine_name());
+            self.error(&routine.span, message);
+        }
+    }
+
+    fn check_statement(self: &mut Self, statement: &Statement) {
+        match statement {
+            Statement::Var(vars) => self.routine_symbols.as_mut().unwrap().add_vars(vars),
+            Statement::Let { var_name, array_access, exp, span } => {
+                let value_type = self.expression_type(exp);
+
+                match array_access {
+                    Some(index_exp) => {
+                        self.expression_type(index_exp);
+                    }
+                    None => {
+                        if let Some(var_type) = self.variable_type(var_name)
+                            && !compatible(&var_type, &value_type)
+                        {
+                            let message = format!(
+                                "cannot assign {} to `{var_name}` of type {}",
+                                value_type_name(&value_type),
+                                var_type
+                            );
+                            self.error(span, message);
+                        }
+                    }
+                }
+            }
+            /* operations yield an int, which class variables other than arrays cannot hold */
+            Statement::Update { var_name, array_access, op, exp, span } => {
+                if let Some(exp) = exp {
+                    self.expression_type(exp);
+                }
+
+                match array_access {
+                    Some(index_exp) => {
+                        self.expression_type(index_exp);
+                    }
+                    None => {
+                        if let Some(var_type) = self.variable_type(var_name)
+                            && !compatible(&var_type, &ValueType::Known(JackType::Int))
+                        {
+                            let operator = match exp {
+                                Some(_) => format!("{op}="),
+                                None => format!("{op}{op}"),
+                            };
+                            let message = format!(
+                                "cannot apply `{operator}` to `{var_name}` of type {}",
+                                var_type
+                            );
+                            self.error(span, message);
+                        }
+                    }
+                }
+            }
+            Statement::If { exp, statements, else_statements, .. } => {
+                self.expression_type(exp);
+                self.check_block(statements);
+                if let Some(else_statements) = else_statements {
+                    self.check_block(else_statements);
+                }
+            }
+            Statement::While { exp, statements, .. } => {
+                self.expression_type(exp);
+                self.check_block(statements);
+            }
+            Statement::For { init, exp, step, statements, .. } => {
+                if let Some(init) = init {
+                    self.check_statement(init);
+                }
+                if let Some(exp) = exp {
+                    self.expression_type(exp);
+                }
+                if let Some(step) = step {
+                    self.check_statement(step);
+                }
+                self.check_block(statements);
+            }
+            Statement::Switch { exp, cases, default, .. } => {
+                self.expression_type(exp);
+                cases.iter().for_each(|case| self.check_block(&case.statements));
+                if let Some(default) = default {
+                    self.check_block(default);
+                }
+            }
+            Statement::Break(_) | Statement::Continue(_) => {}
+            Statement::Return(exp_opt, span) => self.check_return(exp_opt, span),
+            Statement::Do(call) => {
+                self.call_type(call);
+            }
+        }
+    }
+
+    fn check_block(self: &mut Self, statements: &[Statement]) {
+        self.routine_symbols.as_mut().unwrap().enter_block();
+        statements.iter().for_each(|s| self.check_statement(s));
+        self.routine_symbols.as_mut().unwrap().exit_block();
+    }
+
+    fn check_return(self: &mut Self, exp_opt: &Option<Expression>, span: &Span) {
+        let return_type = &self.routine.unwrap().return_type;
+
+        match (return_type, exp_opt) {
+            (ReturnType::Void, Some(_)) => {
+                let message = format!("void {} cannot return a value", self.routine_name());
+                self.error(span, message);
+            }
+            (ReturnType::Type(jack_type), None) => {
+                let message =
+                    format!("{} must return a value of type {}", self.routine_name(), jack_type);
+                self.error(span, message);
+            }
+            (ReturnType::Type(jack_type), Some(exp)) => {
+                let value_type = self.expression_type(exp);
+                if !compatible(jack_type, &value_type) {
+                    let message = format!(
+                        "{} must return a value of type {}, found {}",
+                        self.routine_name(),
+                        jack_type,
+                        value_type_name(&value_type)
+                    );
+                    self.error(span, message);
+                }
+            }
+            (ReturnType::Void, None) => {}
+        }
+    }
+
+    fn expression_type(self: &mut Self, exp: &Expression) -> ValueType {
+        let first_type = self.operand_type(&exp.term);
+
+        let Some((last_op, _)) = exp.additional.last() else {
+            return first_type;
+        };
+
+        exp.additional.iter().for_each(|(_, term)| {
+            self.operand_type(term);
+        });
+
+        operation_type(last_op)
+    }
+
+    /* Type of a term whose value is used, reporting void calls */
+    fn operand_type(self: &mut Self, term: &Term) -> ValueType {
+        let value_type = self.term_type(term);
+
+        if let (ValueType::Void, Term::Call(call)) = (&value_type, term) {
+            let message = format!("void {} has no value to use", self.callee_name(call));
+            self.error(&call.span, message);
+            return ValueType::Unknown;
+        }
+
+        value_type
+    }
+
+    fn term_type(self: &mut Self, term: &Term) -> ValueType {
+        match term {
+            Term::IntConst(_) => ValueType::Known(JackType::Int),
+            Term::StrConst(_) => ValueType::Known(JackType::Class("String".into())),
+            Term::CharConst(_) => ValueType::Known(JackType::Char),
+            Term::KeywordConst(val, _) => match val.as_str() {
+                "true" | "false" => ValueType::Known(JackType::Boolean),
+                "this" => ValueType::Known(JackType::Class(self.class.name.clone())),
+                _ => ValueType::Null,
+            },
+            Term::VarName(var_name, _) => {
+                self.variable_type(var_name).map_or(ValueType::Unknown, ValueType::Known)
+            }
+            Term::ClassConst { class_name, name, .. } => self
+                .class_table
+                .fetch_qualified_constant(&self.class.name, class_name, name)
+                .map_or(ValueType::Unknown, |entry| ValueType::Known(entry.jtype)),
+            Term::ArrayAccess { exp, .. } => {
+                self.expression_type(exp);
+                ValueType::Unknown
+            }
+            Term::Expression(exp) => self.expression_type(exp),
+            Term::Binary { left, op, right } => {
+                self.operand_type(left);
+                self.operand_type(right);
+                operation_type(op)
+            }
+            Term::Unary { op, term } => match (op, self.operand_type(term)) {
+                (Operation::Not, ValueType::Known(JackType::Boolean)) => {
+                    ValueType::Known(JackType::Boolean)
+                }
+                _ => ValueType::Known(JackType::Int),
+            },
+            Term::Call(call) => self.call_type(call),
+        }
+    }
+
+    /* Return type of the called subroutine, checking the type of each argument */
+    fn call_type(self: &mut Self, call: &SubroutineCall) -> ValueType {
+        let arg_types: Vec<ValueType> =
+            call.expressions.iter().map(|exp| self.expression_type(exp)).collect();
+
+        let Some(signature) = self
+            .callee_class(call)
+            .and_then(|class_name| self.class_table.fetch_routine(&class_name, &call.routine_name))
+        else {
+            return ValueType::Unknown;
+        };
+
+        signature.parameters.iter().zip(&arg_types).enumerate().for_each(
+            |(i, (param_type, arg_type))| {
+                if !compatible(param_type, arg_type) {
+                    let message = format!(
+                        "argument {} of {} must be of type {}, found {}",
+                        i + 1,
+                        self.callee_name(call),
+                        param_type,
+                        value_type_name(arg_type)
+                    );
+                    self.error(&call.span, message);
+                }
+            },
+        );
+
+        match &signature.return_type {
+            ReturnType::Void => ValueType::Void,
+            ReturnType::Type(jack_type) => ValueType::Known(jack_type.clone()),
+        }
+    }
+
+    /* The class declaring the called subroutine */
+    fn callee_class(self: &Self, call: &SubroutineCall) -> Option<String> {
+        match &call.callee {
+            None => Some(self.class.name.clone()),
+            Some(callee) => match self.variable_type(callee) {
+                Some(JackType::Class(class_name)) => Some(class_name),
+                Some(_) => None,
+                None => Some(callee.clone()),
+            },
+        }
+    }
+
+    fn callee_name(self: &Self, call: &SubroutineCall) -> String {
+        let class_name = self.callee_class(call).unwrap_or_default();
+        format!("{class_name}.{}", call.routine_name)
+    }
+
+    fn variable_type(self: &Self, var_name: &String) -> Option<JackType> {
+        self.routine_symbols
+            .as_ref()
+            .and_then(|symbols| symbols.entries.get(var_name))
+            .or_else(|| self.class_symbols.entries.get(var_name))
+            .map(|entry| entry.jtype.clone())
+    }
+
+    fn routine_name(self: &Self) -> String {
+        format!("{}.{}", self.class.name, self.routine.map_or("", |routine| &routine.name))
+    }
+
+    fn error(self: &mut Self, span: &Span, message: String) {
+        self.errors.push(SemanticError::new(span, message));
+    }
+}
+
+fn compatible(expected: &JackType, found: &ValueType) -> bool {
+    let is_primitive = |jack_type: &JackType| !matches!(jack_type, JackType::Class(_));
+    let is_array = |jack_type: &JackType| *jack_type == JackType::Class("Array".into());
+
+    match found {
+        ValueType::Known(found) => {
+            expected == found
+                || (is_primitive(expected) && is_primitive(found))
+                || is_array(expected)
+                || is_array(found)
+        }
+        /* void values are reported where they are used */
+        ValueType::Null | ValueType::Void | ValueType::Unknown => true,
+    }
+}
+
+/* A subroutine ends when its last statement returns, or is an if returning on both branches */
+fn always_returns(statements: &[Statement]) -> bool {
+    match statements.last() {
+        Some(Statement::Return(_, _)) => true,
+        Some(Statement::If { statements, else_statements: Some(else_statements), .. }) => {
+            always_returns(statements) && always_returns(else_statements)
+        }
+        Some(Statement::Switch { cases, default: Some(default), .. }) => {
+            cases.iter().all(|case| always_returns(&case.statements)) && always_returns(default)
+        }
+        _ => false,
+    }
+}
+
+/* Comparisons and `&&`, `||` result in a boolean, other operations in an int */
+fn operation_type(op: &Operation) -> ValueType {
+    match op {
+        Operation::LessThan
+        | Operation::GreaterThan
+        | Operation::Equals
+        | Operation::LogicalAnd
+        | Operation::LogicalOr => ValueType::Known(JackType::Boolean),
+        _ => ValueType::Known(JackType::Int),
+    }
+}
+
+fn value_type_name(value_type: &ValueType) -> String {
+    match value_type {
+        ValueType::Known(jack_type) => jack_type.to_string(),
+        ValueType::Null => "null".into(),
+        ValueType::Void => "void".into(),
+        ValueType::Unknown => "unknown".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::syntax_analyzer;
+    use super::*;
+
+    fn check_source(source: &str) -> Vec<String> {
+        let class = syntax_analyzer::run(source.into(), "Main.jack").unwrap();
+        let mut class_table = ClassTable::default();
+        class_table.add_class(&class);
+
+        match check(&[class], &class_table) {
+            Ok(()) => vec![],
+            Err(errors) => errors.iter().map(|err| err.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn accept_weakly_typed_idioms() {
+        let main = "class Main {
+  field Array memory;
+  constructor Main new() {
+    var char c;
+    var boolean done;
+    let memory = 0;
+    let c = 65;
+    let done = c = 65;
+    let memory[0] = this;
+    let memory[1] += c;
+    let c++;
+    let done |= true;
+    return this;
+  }
+  method Main self() { return null; }
+  function int sign(int x) {
+    if (x < 0) { return -1; } else { return ~(x = 0); }
+  }
+}";
+
+        assert_eq!(Vec::<String>::new(), check_source(main));
+    }
+
+    #[test]
+    fn report_type_mismatches() {
+        let main = "class Main {
+  function void main() {
+    var int n;
+    var Main m;
+    let n = Main.new();
+    let m = 5;
+    do Main.take(n, \"text\");
+    let n = Main.nothing() + 1;
+    let m += 1;
+    let m--;
+    let m = (n > 0) && (n < 9);
+    return;
+  }
+  constructor Main new() { return this; }
+  function void take(Main m, int n) { return; }
+  function void nothing() { return 1; }
+  function int count() { return; }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:5:9: cannot assign Main to `n` of type int",
+                "Main.jack:6:9: cannot assign int to `m` of type Main",
+                "Main.jack:7:8: argument 1 of Main.take must be of type Main, found int",
+                "Main.jack:7:8: argument 2 of Main.take must be of type int, found String",
+                "Main.jack:8:13: void Main.nothing has no value to use",
+                "Main.jack:9:9: cannot apply `+=` to `m` of type Main",
+                "Main.jack:10:9: cannot apply `--` to `m` of type Main",
+                "Main.jack:11:9: cannot assign boolean to `m` of type Main",
+                "Main.jack:16:29: void Main.nothing cannot return a value",
+                "Main.jack:17:26: Main.count must return a value of type int",
+            ],
+            check_source(main)
+        );
+    }
+
+    #[test]
+    fn report_missing_returns() {
+        let main = "class Main {
+  function int abs(int x) {
+    if (x < 0) { return -x; }
+  }
+  function int loop() {
+    while (true) { return 1; }
+  }
+  function int sign(int x) {
+    if (x < 0) { return -1; } else { if (x > 0) { return 1; } else { return 0; } }
+  }
+}";
+
+        assert_eq!(
+            vec![
+                "Main.jack:2:16: not every path of Main.abs ends with a return",
+                "Main.jack:5:16: not every path of Main.loop ends with a return",
+            ],
+            check_source(main)
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use super::grammar::*;
+
+/// Depth first traversal of the statements, expressions and terms of a derivation tree.
+///
+/// Every method visits the children of its node by default, through the `walk_*` function
+/// of the same name: implementors only override the nodes they are interested in, calling
+/// the matching `walk_*` function whenever the traversal should go on below them.
+pub trait Visitor {
+    fn visit_class(self: &mut Self, class: &Class) {
+        walk_class(self, class);
+    }
+
+    fn visit_subroutine_dec(self: &mut Self, routine: &SubroutineDec) {
+        walk_subroutine_dec(self, routine);
+    }
+
+    fn visit_statement(self: &mut Self, statement: &Statement) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(self: &mut Self, exp: &Expression) {
+        walk_expression(self, exp);
+    }
+
+    fn visit_term(self: &mut Self, term: &Term) {
+        walk_term(self, term);
+    }
+
+    fn visit_subroutine_call(self: &mut Self, call: &SubroutineCall) {
+        walk_subroutine_call(self, call);
+    }
+}
+
+/// Visits the initializers of the class variables, then every subroutine of the class
+pub fn walk_class<V: Visitor + ?Sized>(visitor: &mut V, class: &Class) {
+    class.vars.iter().filter_map(|var| var.exp.as_ref()).for_each(|exp| {
+        visitor.visit_expression(exp);
+    });
+    class.routines.iter().for_each(|routine| visitor.visit_subroutine_dec(routine));
+}
+
+/// Visits every statement of the subroutine body
+pub fn walk_subroutine_dec<V: Visitor + ?Sized>(visitor: &mut V, routine: &SubroutineDec) {
+    routine.body.statements.iter().for_each(|statement| visitor.visit_statement(statement));
+}
+
+/// Visits the expressions, nested statements and calls of the statement, in source order
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::Var(_) => {}
+        Statement::Let { array_access, exp, .. } => {
+            if let Some(index_exp) = array_access {
+                visitor.visit_expression(index_exp);
+            }
+            visitor.visit_expression(exp);
+        }
+        Statement::Update { array_access, exp, .. } => {
+            if let Some(index_exp) = array_access {
+                visitor.visit_expression(index_exp);
+            }
+            if let Some(exp) = exp {
+                visitor.visit_expression(exp);
+            }
+        }
+        Statement::If { exp, statements, else_statements, .. } => {
+            visitor.visit_expression(exp);
+            statements.iter().for_each(|statement| visitor.visit_statement(statement));
+            if let Some(else_statements) = else_statements {
+                else_statements.iter().for_each(|statement| visitor.visit_statement(statement));
+            }
+        }
+        Statement::While { exp, statements, .. } => {
+            visitor.visit_expression(exp);
+            statements.iter().for_each(|statement| visitor.visit_statement(statement));
+        }
+        Statement::For { init, exp, step, statements, .. } => {
+            if let Some(init) = init {
+                visitor.visit_statement(init);
+            }
+            if let Some(exp) = exp {
+                visitor.visit_expression(exp);
+            }
+            if let Some(step) = step {
+                visitor.visit_statement(step);
+            }
+            statements.iter().for_each(|statement| visitor.visit_statement(statement));
+        }
+        Statement::Switch { exp, cases, default, .. } => {
+            visitor.visit_expression(exp);
+            cases.iter().for_each(|case| {
+                case.values.iter().for_each(|value| visitor.visit_term(value));
+                case.statements.iter().for_each(|statement| visitor.visit_statement(statement));
+            });
+            if let Some(default) = default {
+                default.iter().for_each(|statement| visitor.visit_statement(statement));
+            }
+        }
+        Statement::Break(_) | Statement::Continue(_) => {}
+        Statement::Return(exp_opt, _) => {
+            if let Some(exp) = exp_opt {
+                visitor.visit_expression(exp);
+            }
+        }
+        Statement::Do(call) => visitor.visit_subroutine_call(call),
+    }
+}
+
+/// Visits every term of the expression
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, exp: &Expression) {
+    visitor.visit_term(&exp.term);
+    exp.additional.iter().for_each(|(_, term)| visitor.visit_term(term));
+}
+
+/// Visits the expressions, terms and calls nested in the term
+pub fn walk_term<V: Visitor + ?Sized>(visitor: &mut V, term: &Term) {
+    match term {
+        Term::ArrayAccess { exp, .. } | Term::Expression(exp) => visitor.visit_expression(exp),
+        Term::Unary { term, .. } => visitor.visit_term(term),
+        Term::Binary { left, right, .. } => {
+            visitor.visit_term(left);
+            visitor.visit_term(right);
+        }
+        Term::Call(call) => visitor.visit_subroutine_call(call),
+        Term::IntConst(_)
+        | Term::StrConst(_)
+        | Term::CharConst(_)
+        | Term::KeywordConst(_, _)
+        | Term::VarName(_, _)
+        | Term::ClassConst { .. } => {}
+    }
+}
+
+/// Visits every argument of the call
+pub fn walk_subroutine_call<V: Visitor + ?Sized>(visitor: &mut V, call: &SubroutineCall) {
+    call.expressions.iter().for_each(|exp| visitor.visit_expression(exp));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::syntax_analyzer;
+    use super::*;
+
+    /* Names of the subroutines called, along with the number of integer constants */
+    #[derive(Default)]
+    struct Calls {
+        names: Vec<String>,
+        int_consts: usize,
+    }
+
+    impl Visitor for Calls {
+        fn visit_term(self: &mut Self, term: &Term) {
+            if let Term::IntConst(_) = term {
+                self.int_consts += 1;
+            }
+            walk_term(self, term);
+        }
+
+        fn visit_subroutine_call(self: &mut Self, call: &SubroutineCall) {
+            self.names.push(call.routine_name.clone());
+            walk_subroutine_call(self, call);
+        }
+    }
+
+    #[test]
+    fn visit_nested_nodes_in_source_order() {
+        let class = "class Main {
+  function void main() {
+    var Array a;
+    let a[1] = Math.max(2, -Math.min(3, 4));
+    while (a[0] < 5) { if (true) { do draw(); } else { return; } }
+    return;
+  }
+}";
+        let class = syntax_analyzer::run(class.into(), "Main.jack").unwrap();
+        let mut calls = Calls::default();
+        calls.visit_class(&class);
+
+        assert_eq!(vec!["max", "min", "draw"], calls.names);
+        assert_eq!(6, calls.int_consts);
+    }
+}
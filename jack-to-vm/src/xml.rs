@@ -0,0 +1,665 @@
+use super::grammar::*;
+use super::syntax_analyzer::tokenizer::{Token, TokenKind};
+
+/// Tokens -> `XxxT.xml` token file, as emitted by the nand2tetris analyzer tools
+///
+/// `if (x < 0)` evaluates to:
+///
+/// <tokens>
+/// <keyword> if </keyword>
+/// <symbol> ( </symbol>
+/// <identifier> x </identifier>
+/// <symbol> &lt; </symbol>
+/// <integerConstant> 0 </integerConstant>
+/// <symbol> ) </symbol>
+/// </tokens>
+pub fn tokens(tokens: &[Token]) -> Vec<String> {
+    let mut lines = vec!["<tokens>".to_string()];
+
+    tokens.iter().for_each(|token| {
+        let tag = match token.kind {
+            TokenKind::Keyword(_) => "keyword",
+            TokenKind::Symbol(_) => "symbol",
+            TokenKind::Identifier(_) => "identifier",
+            TokenKind::StrConst(_) => "stringConstant",
+            TokenKind::IntConst(_) => "integerConstant",
+            TokenKind::CharConst(val) => {
+                return lines.push(terminal("charConstant", &val.to_string()));
+            }
+            /* the reference tools drop comments */
+            TokenKind::Comment(_) => return,
+        };
+        lines.push(terminal(tag, &token.kind.to_string()));
+    });
+
+    lines.push("</tokens>".to_string());
+    lines
+}
+
+/// Derivation tree -> `Xxx.xml` parse tree, as emitted by the nand2tetris analyzer tools
+///
+/// Non-terminal nodes are named after the grammar rules, nested by two spaces.
+/// `subroutineCall`, `type`, `className` and other rules made of a single token or
+/// inlined in their parent have no node of their own, as in the reference files.
+pub fn class(class: &Class) -> Vec<String> {
+    let mut writer = Writer { lines: vec![], depth: 0 };
+    writer.write_class(class);
+    writer.lines
+}
+
+struct Writer {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl Writer {
+    /* 'class' className '{' (classVarDec | constDec | enumDec)* subroutineDec* '}' */
+    fn write_class(self: &mut Self, class: &Class) {
+        self.open("class");
+        self.keyword("class");
+        self.identifier(&class.name);
+        self.symbol("{");
+
+        class.declarations().into_iter().for_each(|declaration| match declaration {
+            ClassDeclaration::Vars(decs) => {
+                self.open("classVarDec");
+                self.keyword(match decs[0].var_type {
+                    ClassVarType::Static => "static",
+                    ClassVarType::Field => "field",
+                });
+                self.write_type(&decs[0].jack_type);
+                /* varName ('=' expression)? (',' varName ('=' expression)?)* */
+                decs.iter().enumerate().for_each(|(i, dec)| {
+                    if i > 0 {
+                        self.symbol(",");
+                    }
+                    self.identifier(&dec.name);
+                    if let Some(exp) = &dec.exp {
+                        self.symbol("=");
+                        self.write_expression(exp);
+                    }
+                });
+                self.symbol(";");
+                self.close("classVarDec");
+            }
+            ClassDeclaration::Consts(decs) => match &decs[0].enum_name {
+                Some(enum_name) => self.write_enum_dec(enum_name, decs),
+                None => self.write_const_dec(&decs[0]),
+            },
+        });
+
+        class.routines.iter().for_each(|routine| self.write_subroutine_dec(routine));
+
+        self.symbol("}");
+        self.close("class");
+    }
+
+    /* 'const' type constName '=' expression ';' */
+    fn write_const_dec(self: &mut Self, constant: &ConstDec) {
+        self.open("constDec");
+        self.keyword("const");
+        self.write_type(&constant.jack_type);
+        self.identifier(&constant.name);
+        self.symbol("=");
+        self.write_expression(constant.exp.as_ref().unwrap());
+        self.symbol(";");
+        self.close("constDec");
+    }
+
+    /* 'enum' enumName '{' constName ('=' expression)? (',' constName ('=' expression)?)* '}' */
+    fn write_enum_dec(self: &mut Self, enum_name: &str, members: &[ConstDec]) {
+        self.open("enumDec");
+        self.keyword("enum");
+        self.identifier(enum_name);
+        self.symbol("{");
+        members.iter().enumerate().for_each(|(i, member)| {
+            if i > 0 {
+                self.symbol(",");
+            }
+            self.identifier(&member.name);
+            if let Some(exp) = &member.exp {
+                self.symbol("=");
+                self.write_expression(exp);
+            }
+        });
+        self.symbol("}");
+        self.close("enumDec");
+    }
+
+    /* ('constructor'|'function'|'method') ('void'|type) subroutineName '(' parameterList ')' subroutineBody */
+    fn write_subroutine_dec(self: &mut Self, routine: &SubroutineDec) {
+        self.open("subroutineDec");
+        self.keyword(&routine.routine_type.to_string());
+        match &routine.return_type {
+            ReturnType::Void => self.keyword("void"),
+            ReturnType::Type(jack_type) => self.write_type(jack_type),
+        }
+        self.identifier(&routine.name);
+
+        self.symbol("(");
+        self.open("parameterList");
+        routine.parameters.iter().enumerate().for_each(|(i, param)| {
+            if i > 0 {
+                self.symbol(",");
+            }
+            self.write_type(&param.jack_type);
+            self.identifier(&param.name);
+        });
+        self.close("parameterList");
+        self.symbol(")");
+
+        self.open("subroutineBody");
+        self.symbol("{");
+        routine
+            .body
+            .vars
+            .chunk_by(|a, b| a.declaration_span == b.declaration_span)
+            .for_each(|decs| self.write_var_dec(decs));
+        self.write_statements(&routine.body.statements);
+        self.symbol("}");
+        self.close("subroutineBody");
+
+        self.close("subroutineDec");
+    }
+
+    /* 'var' type varName (',' varName)* ';' */
+    fn write_var_dec(self: &mut Self, decs: &[VarDec]) {
+        self.open("varDec");
+        self.keyword("var");
+        self.write_type(&decs[0].jack_type);
+        self.write_var_names(decs.iter().map(|dec| &dec.name));
+        self.symbol(";");
+        self.close("varDec");
+    }
+
+    /* 'int'|'char'|'boolean'|className */
+    fn write_type(self: &mut Self, jack_type: &JackType) {
+        match jack_type {
+            JackType::Int => self.keyword("int"),
+            JackType::Char => self.keyword("char"),
+            JackType::Boolean => self.keyword("boolean"),
+            JackType::Class(class_name) => self.identifier(class_name),
+        }
+    }
+
+    /* varName (',' varName)* */
+    fn write_var_names<'a>(self: &mut Self, names: impl Iterator<Item = &'a String>) {
+        names.enumerate().for_each(|(i, name)| {
+            if i > 0 {
+                self.symbol(",");
+            }
+            self.identifier(name);
+        });
+    }
+
+    /* statement* */
+    fn write_statements(self: &mut Self, statements: &[Statement]) {
+        self.open("statements");
+        statements.iter().for_each(|statement| self.write_statement(statement));
+        self.close("statements");
+    }
+
+    fn write_statement(self: &mut Self, statement: &Statement) {
+        self.write_terminated_statement(statement, ";");
+    }
+
+    /* The step of a for statement ends with ')' rather than ';' */
+    fn write_terminated_statement(self: &mut Self, statement: &Statement, terminator: &str) {
+        match statement {
+            /* within the statements, unlike the declarations at the top of the subroutine body */
+            Statement::Var(vars) => self.write_var_dec(vars),
+            /* 'let' varName ('[' expression ']')? '=' expression ';' */
+            Statement::Let { var_name, array_access, exp, .. } => {
+                self.open("letStatement");
+                self.keyword("let");
+                self.identifier(var_name);
+                if let Some(index_exp) = array_access {
+                    self.symbol("[");
+                    self.write_expression(index_exp);
+                    self.symbol("]");
+                }
+                self.symbol("=");
+                self.write_expression(exp);
+                self.symbol(terminator);
+                self.close("letStatement");
+            }
+            /* 'let' varName ('[' expression ']')? (op '=' expression | '++' | '--') ';'
+             * the two symbols of the operator written as one */
+            Statement::Update { var_name, array_access, op, exp, .. } => {
+                self.open("letStatement");
+                self.keyword("let");
+                self.identifier(var_name);
+                if let Some(index_exp) = array_access {
+                    self.symbol("[");
+                    self.write_expression(index_exp);
+                    self.symbol("]");
+                }
+                match exp {
+                    Some(exp) => {
+                        self.symbol(&format!("{op}="));
+                        self.write_expression(exp);
+                    }
+                    None => self.symbol(&format!("{op}{op}")),
+                }
+                self.symbol(terminator);
+                self.close("letStatement");
+            }
+            /* 'if' '(' expression ')' '{' statements '}' ('else' '{' statements '}')?
+             * `else if` being written as the `else { if ... }` it stands for */
+            Statement::If { exp, statements, else_statements, .. } => {
+                self.open("ifStatement");
+                self.keyword("if");
+                self.write_condition(exp);
+                self.write_block(statements);
+                if let Some(else_statements) = else_statements {
+                    self.keyword("else");
+                    self.write_block(else_statements);
+                }
+                self.close("ifStatement");
+            }
+            /* 'while' '(' expression ')' '{' statements '}' */
+            Statement::While { exp, statements, .. } => {
+                self.open("whileStatement");
+                self.keyword("while");
+                self.write_condition(exp);
+                self.write_block(statements);
+                self.close("whileStatement");
+            }
+            /* 'switch' '(' expression ')' '{' (('case' term ':')+ statements)*
+             * ('default' ':' statements)? '}' */
+            Statement::Switch { exp, cases, default, .. } => {
+                self.open("switchStatement");
+                self.keyword("switch");
+                self.write_condition(exp);
+                self.symbol("{");
+                cases.iter().for_each(|case| {
+                    self.open("switchCase");
+                    case.values.iter().for_each(|value| {
+                        self.keyword("case");
+                        self.write_term(value);
+                        self.symbol(":");
+                    });
+                    self.write_statements(&case.statements);
+                    self.close("switchCase");
+                });
+                if let Some(default) = default {
+                    self.open("switchDefault");
+                    self.keyword("default");
+                    self.symbol(":");
+                    self.write_statements(default);
+                    self.close("switchDefault");
+                }
+                self.symbol("}");
+                self.close("switchStatement");
+            }
+            /* 'for' '(' statement? expression? ';' statement? ')' '{' statements '}' */
+            Statement::For { init, exp, step, statements, .. } => {
+                self.open("forStatement");
+                self.keyword("for");
+                self.symbol("(");
+                match init {
+                    Some(init) => self.write_statement(init),
+                    None => self.symbol(";"),
+                }
+                if let Some(exp) = exp {
+                    self.write_expression(exp);
+                }
+                self.symbol(";");
+                match step {
+                    Some(step) => self.write_terminated_statement(step, ")"),
+                    None => self.symbol(")"),
+                }
+                self.write_block(statements);
+                self.close("forStatement");
+            }
+            Statement::Break(_) => {
+                self.open("breakStatement");
+                self.keyword("break");
+                self.symbol(";");
+                self.close("breakStatement");
+            }
+            Statement::Continue(_) => {
+                self.open("continueStatement");
+                self.keyword("continue");
+                self.symbol(";");
+                self.close("continueStatement");
+            }
+            /* 'return' expression? ';' */
+            Statement::Return(exp_opt, _) => {
+                self.open("returnStatement");
+                self.keyword("return");
+                if let Some(exp) = exp_opt {
+                    self.write_expression(exp);
+                }
+                self.symbol(";");
+                self.close("returnStatement");
+            }
+            /* 'do' subroutineCall ';' */
+            Statement::Do(call) => {
+                self.open("doStatement");
+                self.keyword("do");
+                self.write_subroutine_call(call);
+                self.symbol(terminator);
+                self.close("doStatement");
+            }
+        }
+    }
+
+    fn write_condition(self: &mut Self, exp: &Expression) {
+        self.symbol("(");
+        self.write_expression(exp);
+        self.symbol(")");
+    }
+
+    fn write_block(self: &mut Self, statements: &[Statement]) {
+        self.symbol("{");
+        self.write_statements(statements);
+        self.symbol("}");
+    }
+
+    /* term (op term)* */
+    fn write_expression(self: &mut Self, exp: &Expression) {
+        self.open("expression");
+        self.write_operands(&exp.term);
+        exp.additional.iter().for_each(|(op, term)| {
+            self.symbol(&op.to_string());
+            self.write_term(term);
+        });
+        self.close("expression");
+    }
+
+    /* The operands of a precedence tree: the left ones apply first and are flattened like
+     * other expressions, grouped right ones are nested as terms holding an expression */
+    fn write_operands(self: &mut Self, term: &Term) {
+        match term {
+            Term::Binary { left, op, right } => {
+                self.write_operands(left);
+                self.symbol(&op.to_string());
+                self.write_term(right);
+            }
+            term => self.write_term(term),
+        }
+    }
+
+    /* integerConstant | stringConstant | keywordConstant | varName | varName '[' expression ']'
+     * | className '.' constName | subroutineCall | '(' expression ')' | unaryOp term */
+    fn write_term(self: &mut Self, term: &Term) {
+        self.open("term");
+        match term {
+            Term::IntConst(val) => self.terminal("integerConstant", &val.to_string()),
+            Term::StrConst(val) => self.terminal("stringConstant", val),
+            Term::CharConst(val) => self.terminal("charConstant", &val.to_string()),
+            Term::KeywordConst(val, _) => self.keyword(val),
+            Term::VarName(var_name, _) => self.identifier(var_name),
+            Term::ClassConst { class_name, name, .. } => {
+                self.identifier(class_name);
+                self.symbol(".");
+                self.identifier(name);
+            }
+            Term::ArrayAccess { var_name, exp, .. } => {
+                self.identifier(var_name);
+                self.symbol("[");
+                self.write_expression(exp);
+                self.symbol("]");
+            }
+            Term::Expression(exp) => {
+                self.symbol("(");
+                self.write_expression(exp);
+                self.symbol(")");
+            }
+            Term::Unary { op, term } => {
+                self.symbol(&op.to_string());
+                self.write_term(term);
+            }
+            Term::Binary { .. } => {
+                self.open("expression");
+                self.write_operands(term);
+                self.close("expression");
+            }
+            Term::Call(call) => self.write_subroutine_call(call),
+        }
+        self.close("term");
+    }
+
+    /* subroutineName '(' expressionList ')' | (className|varName) '.' subroutineName '(' expressionList ')' */
+    fn write_subroutine_call(self: &mut Self, call: &SubroutineCall) {
+        if let Some(callee) = &call.callee {
+            self.identifier(callee);
+            self.symbol(".");
+        }
+        self.identifier(&call.routine_name);
+
+        self.symbol("(");
+        self.open("expressionList");
+        call.expressions.iter().enumerate().for_each(|(i, exp)| {
+            if i > 0 {
+                self.symbol(",");
+            }
+            self.write_expression(exp);
+        });
+        self.close("expressionList");
+        self.symbol(")");
+    }
+
+    fn keyword(self: &mut Self, val: &str) {
+        self.terminal("keyword", val);
+    }
+
+    fn symbol(self: &mut Self, val: &str) {
+        self.terminal("symbol", val);
+    }
+
+    fn identifier(self: &mut Self, val: &str) {
+        self.terminal("identifier", val);
+    }
+
+    fn terminal(self: &mut Self, tag: &str, val: &str) {
+        self.lines.push(format!("{}{}", self.indentation(), terminal(tag, val)));
+    }
+
+    /* non-terminal nodes span several lines, even when empty */
+    fn open(self: &mut Self, tag: &str) {
+        self.lines.push(format!("{}<{tag}>", self.indentation()));
+        self.depth += 1;
+    }
+
+    fn close(self: &mut Self, tag: &str) {
+        self.depth -= 1;
+        self.lines.push(format!("{}</{tag}>", self.indentation()));
+    }
+
+    fn indentation(self: &Self) -> String {
+        "  ".repeat(self.depth)
+    }
+}
+
+fn terminal(tag: &str, val: &str) -> String {
+    format!("<{tag}> {} </{tag}>", escape(val))
+}
+
+/// Text -> the same text, `&`, `<`, `>` and `"` written as XML and HTML entities
+pub fn escape(val: &str) -> String {
+    val.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::syntax_analyzer::{parser, tokenizer};
+    use super::*;
+
+    #[test]
+    fn emit_tokens_with_escaping() {
+        let source = r#"if (x < 0) { let s = "a & b"; }"#;
+        let tokens = tokenizer::tokenize(source, "Main.jack").unwrap();
+
+        assert_eq!(
+            vec![
+                "<tokens>",
+                "<keyword> if </keyword>",
+                "<symbol> ( </symbol>",
+                "<identifier> x </identifier>",
+                "<symbol> &lt; </symbol>",
+                "<integerConstant> 0 </integerConstant>",
+                "<symbol> ) </symbol>",
+                "<symbol> { </symbol>",
+                "<keyword> let </keyword>",
+                "<identifier> s </identifier>",
+                "<symbol> = </symbol>",
+                "<stringConstant> a &amp; b </stringConstant>",
+                "<symbol> ; </symbol>",
+                "<symbol> } </symbol>",
+                "</tokens>",
+            ],
+            super::tokens(&tokens)
+        );
+    }
+
+    #[test]
+    fn emit_parse_tree() {
+        let source = "class Main {
+  field int x, y;
+  field int size;
+  method void run(int n) {
+    var Array a;
+    let a[n] = -x;
+    do draw();
+    return;
+  }
+}";
+        let tokens = tokenizer::tokenize(source, "Main.jack").unwrap();
+        let tree = parser::parse(tokens).unwrap();
+
+        assert_eq!(
+            vec![
+                "<class>",
+                "  <keyword> class </keyword>",
+                "  <identifier> Main </identifier>",
+                "  <symbol> { </symbol>",
+                "  <classVarDec>",
+                "    <keyword> field </keyword>",
+                "    <keyword> int </keyword>",
+                "    <identifier> x </identifier>",
+                "    <symbol> , </symbol>",
+                "    <identifier> y </identifier>",
+                "    <symbol> ; </symbol>",
+                "  </classVarDec>",
+                "  <classVarDec>",
+                "    <keyword> field </keyword>",
+                "    <keyword> int </keyword>",
+                "    <identifier> size </identifier>",
+                "    <symbol> ; </symbol>",
+                "  </classVarDec>",
+                "  <subroutineDec>",
+                "    <keyword> method </keyword>",
+                "    <keyword> void </keyword>",
+                "    <identifier> run </identifier>",
+                "    <symbol> ( </symbol>",
+                "    <parameterList>",
+                "      <keyword> int </keyword>",
+                "      <identifier> n </identifier>",
+                "    </parameterList>",
+                "    <symbol> ) </symbol>",
+                "    <subroutineBody>",
+                "      <symbol> { </symbol>",
+                "      <varDec>",
+                "        <keyword> var </keyword>",
+                "        <identifier> Array </identifier>",
+                "        <identifier> a </identifier>",
+                "        <symbol> ; </symbol>",
+                "      </varDec>",
+                "      <statements>",
+                "        <letStatement>",
+                "          <keyword> let </keyword>",
+                "          <identifier> a </identifier>",
+                "          <symbol> [ </symbol>",
+                "          <expression>",
+                "            <term>",
+                "              <identifier> n </identifier>",
+                "            </term>",
+                "          </expression>",
+                "          <symbol> ] </symbol>",
+                "          <symbol> = </symbol>",
+                "          <expression>",
+                "            <term>",
+                "              <symbol> - </symbol>",
+                "              <term>",
+                "                <identifier> x </identifier>",
+                "              </term>",
+                "            </term>",
+                "          </expression>",
+                "          <symbol> ; </symbol>",
+                "        </letStatement>",
+                "        <doStatement>",
+                "          <keyword> do </keyword>",
+                "          <identifier> draw </identifier>",
+                "          <symbol> ( </symbol>",
+                "          <expressionList>",
+                "          </expressionList>",
+                "          <symbol> ) </symbol>",
+                "          <symbol> ; </symbol>",
+                "        </doStatement>",
+                "        <returnStatement>",
+                "          <keyword> return </keyword>",
+                "          <symbol> ; </symbol>",
+                "        </returnStatement>",
+                "      </statements>",
+                "      <symbol> } </symbol>",
+                "    </subroutineBody>",
+                "  </subroutineDec>",
+                "  <symbol> } </symbol>",
+                "</class>",
+            ],
+            class(&tree)
+        );
+    }
+
+    #[test]
+    fn emit_precedence_groups() {
+        let source = "// pragma precedence
+class Main {
+  static int a = 1 * 2 + 3 * 4;
+}";
+        let tokens = tokenizer::tokenize(source, "Main.jack").unwrap();
+        let tree = parser::parse(tokens).unwrap();
+
+        assert_eq!(
+            vec![
+                "<class>",
+                "  <keyword> class </keyword>",
+                "  <identifier> Main </identifier>",
+                "  <symbol> { </symbol>",
+                "  <classVarDec>",
+                "    <keyword> static </keyword>",
+                "    <keyword> int </keyword>",
+                "    <identifier> a </identifier>",
+                "    <symbol> = </symbol>",
+                "    <expression>",
+                "      <term>",
+                "        <integerConstant> 1 </integerConstant>",
+                "      </term>",
+                "      <symbol> * </symbol>",
+                "      <term>",
+                "        <integerConstant> 2 </integerConstant>",
+                "      </term>",
+                "      <symbol> + </symbol>",
+                "      <term>",
+                "        <expression>",
+                "          <term>",
+                "            <integerConstant> 3 </integerConstant>",
+                "          </term>",
+                "          <symbol> * </symbol>",
+                "          <term>",
+                "            <integerConstant> 4 </integerConstant>",
+                "          </term>",
+                "        </expression>",
+                "      </term>",
+                "    </expression>",
+                "    <symbol> ; </symbol>",
+                "  </classVarDec>",
+                "  <symbol> } </symbol>",
+                "</class>",
+            ],
+            class(&tree)
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use std::{env, fs, path::PathBuf, process::ExitCode};
+
+use jack_to_vm::DocFormat;
+
+fn main() -> ExitCode {
+    let path = PathBuf::from(env::args().nth(1).expect("No jack file or directory provided!"));
+    let format =
+        if env::args().any(|arg| arg == "--html") { DocFormat::Html } else { DocFormat::Markdown };
+    /* The pages are written to `docs` next to the classes unless --out=DIR is given */
+    let out = env::args()
+        .find_map(|arg| arg.strip_prefix("--out=").map(PathBuf::from))
+        .unwrap_or(if path.is_file() { path.with_file_name("docs") } else { path.join("docs") });
+
+    let pages = match jack_to_vm::docs(&path, format) {
+        Ok(pages) => pages,
+        Err(errors) => {
+            errors.iter().for_each(|err| eprintln!("{err}"));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    fs::create_dir_all(&out).expect("Creating the output directory failed");
+    for (name, content) in pages {
+        fs::write(out.join(name), content).expect("Writing documentation page failed");
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,55 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+fn main() -> ExitCode {
+    let path = PathBuf::from(env::args().nth(1).expect("No jack file or directory provided!"));
+    let check = env::args().any(|arg| arg == "--check");
+    let mut success = true;
+
+    for file in jack_files(&path) {
+        let source = fs::read_to_string(&file).expect("Reading .jack file failed");
+        let formatted = match jack_to_vm::format(&source, &file.to_string_lossy()) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                errors.iter().for_each(|err| eprintln!("{err}"));
+                success = false;
+                continue;
+            }
+        };
+
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            println!("{} is not formatted", file.display());
+            success = false;
+        } else {
+            fs::write(&file, formatted).expect("Writing .jack file failed");
+        }
+    }
+
+    if success { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
+
+/* The file itself, or the .jack files of the directory, sorted */
+fn jack_files(path: &Path) -> Vec<PathBuf> {
+    if path.is_file() {
+        return vec![path.to_path_buf()];
+    }
+
+    let mut files: Vec<PathBuf> = path
+        .read_dir()
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "jack"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+
+    files
+}
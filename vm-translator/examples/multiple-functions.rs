@@ -8,7 +8,8 @@ fn main() {
     };
 
     let vm_program = vm_translator::fetch_vm_program(&PathBuf::from(vm_file_path));
-    let asm = vm_translator::compile(vm_program);
+    let asm = vm_translator::compile(vm_program, &vm_translator::Options::default())
+        .expect("Vm program verification failed");
 
     fs::write("source.asm", asm.join("\n")).expect("Writing asm to file failed");
 }
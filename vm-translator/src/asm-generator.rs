@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use crate::Options;
 use crate::command::{
     Command,
     branching::BranchingArgs,
@@ -7,20 +8,30 @@ use crate::command::{
     operation::{MemorySegment, OperationArgs},
 };
 
-pub fn compile(vm_commands: Vec<Command>) -> Vec<String> {
-    AsmGenerator::generate(vm_commands)
+/* Sys.error codes reported by the debug instrumentation */
+const STACK_OVERFLOW_ERROR: u16 = 100;
+
+/* Last RAM address of the stack, the heap starts right after it */
+const STACK_END: u16 = 2047;
+
+/* Functions with more local vars initialize them in a loop rather than unrolling it */
+const UNROLLED_LOCALS_LIMIT: u16 = 8;
+
+pub fn compile(vm_commands: Vec<Command>, options: &Options) -> Vec<String> {
+    AsmGenerator::generate(vm_commands, options.debug)
 }
 
 struct AsmGenerator {
     instructions: Vec<String>,
     function_calls: HashMap<String, usize>,
     counter: u16,
+    debug: bool,
 }
 
 impl AsmGenerator {
-    fn generate(vm_commands: Vec<Command>) -> Vec<String> {
+    fn generate(vm_commands: Vec<Command>, debug: bool) -> Vec<String> {
         let mut asm_generator =
-            Self { instructions: vec![], function_calls: HashMap::new(), counter: 0 };
+            Self { instructions: vec![], function_calls: HashMap::new(), counter: 0, debug };
 
         // Set SP to 256 as first bootstrapping step
         #[cfg(not(test))]
@@ -165,13 +176,21 @@ impl AsmGenerator {
         match args {
             FunctionArgs::Function(fn_name, n_local_vars) => {
                 self.add(format!("({})", fn_name).as_str());
-                for i in 0..*n_local_vars {
-                    self.add(format!("@{}", i).as_str());
-                    self.add("D=A");
-                    self.add("@LCL");
-                    self.add("A=D+M");
-                    self.add("M=0");
-                    self.incr_stack_pointer();
+                if *n_local_vars > UNROLLED_LOCALS_LIMIT {
+                    self.init_local_vars_in_loop(fn_name, *n_local_vars);
+                } else {
+                    for i in 0..*n_local_vars {
+                        self.add(format!("@{}", i).as_str());
+                        self.add("D=A");
+                        self.add("@LCL");
+                        self.add("A=D+M");
+                        self.add("M=0");
+                        self.incr_stack_pointer();
+                    }
+                }
+
+                if self.debug {
+                    self.check_stack_overflow(fn_name);
                 }
             }
             FunctionArgs::Call(fn_name, n_caller_args) => {
@@ -222,6 +241,53 @@ impl AsmGenerator {
         }
     }
 
+    /* LCL equals SP on function entry: push n zeros, counting down on R13 */
+    fn init_local_vars_in_loop(self: &mut Self, fn_name: &String, n_local_vars: u16) {
+        self.add(format!("@{}", n_local_vars).as_str());
+        self.add("D=A");
+        self.add("@R13");
+        self.add("M=D");
+        self.add(format!("({}$INIT_LOCALS)", fn_name).as_str());
+        self.add("@SP");
+        self.add("A=M");
+        self.add("M=0");
+        self.incr_stack_pointer();
+        self.add("@R13");
+        self.add("MD=M-1");
+        self.add(format!("@{}$INIT_LOCALS", fn_name).as_str());
+        self.add("D;JGT");
+    }
+
+    /* Once the local vars are in place the stack must still end before the heap.
+     * On overflow the stack is discarded, so that the functions called by Sys.error
+     * do not overflow it again: the program halts right after reporting anyway. */
+    fn check_stack_overflow(self: &mut Self, fn_name: &String) {
+        self.add("@SP");
+        self.add("D=M");
+        self.add(format!("@{}", STACK_END).as_str());
+        self.add("D=D-A");
+        self.add(format!("@{}$STACK_OK", fn_name).as_str());
+        self.add("D;JLE");
+        self.add("@256");
+        self.add("D=A");
+        self.add("@SP");
+        self.add("M=D");
+        self.report_error(fn_name, STACK_OVERFLOW_ERROR);
+        self.add(format!("({}$STACK_OK)", fn_name).as_str());
+    }
+
+    fn report_error(self: &mut Self, fn_name: &str, error_code: u16) {
+        let filename = fn_name.split_once('.').map_or(fn_name, |(f, _)| f).to_string();
+
+        self.generate_operation_asm(&OperationArgs::Push(
+            MemorySegment::Constant,
+            error_code as i16,
+            filename.clone(),
+        ));
+        self.generate_function_asm(&FunctionArgs::Call("Sys.error".into(), 1));
+        self.generate_operation_asm(&OperationArgs::Pop(MemorySegment::Temp, 0, filename));
+    }
+
     fn generate_operation_asm(self: &mut Self, args: &OperationArgs) {
         match args {
             OperationArgs::Push(mem_segment, val, filename) => {
@@ -373,12 +439,12 @@ impl AsmGenerator {
 mod tests {
     use super::*;
 
-    const FILENAME: &'static str = "Filename";
+    const FILENAME: &str = "Filename";
 
     fn assert_commands_eq(vm_commands: Vec<Command>, expected_asm: Vec<Vec<&str>>) {
         let expected: Vec<&str> = expected_asm.into_iter().flat_map(|asm| asm).collect();
 
-        let actual = AsmGenerator::generate(vm_commands);
+        let actual = AsmGenerator::generate(vm_commands, false);
 
         assert_eq!(expected, actual);
     }
@@ -979,6 +1045,31 @@ mod tests {
         assert_commands_eq(vec![cmd], expected_asm);
     }
 
+    #[test]
+    fn define_function_signature_with_many_local_vars() {
+        let cmd = Command::Function(FunctionArgs::Function("TestFunc".to_string(), 300));
+
+        let expected_asm = vec![vec![
+            "(TestFunc)",
+            "@300",
+            "D=A",
+            "@R13",
+            "M=D",
+            "(TestFunc$INIT_LOCALS)",
+            "@SP",
+            "A=M",
+            "M=0",
+            "@SP",
+            "M=M+1",
+            "@R13",
+            "MD=M-1",
+            "@TestFunc$INIT_LOCALS",
+            "D;JGT",
+        ]];
+
+        assert_commands_eq(vec![cmd], expected_asm);
+    }
+
     #[test]
     fn call_sum_function_with_no_local_vars() {
         let call_fn_command = Command::Function(FunctionArgs::Call("Sum".to_string(), 2));
@@ -1068,8 +1159,12 @@ mod tests {
 
     #[test]
     fn global_return_is_created_correctly() {
-        let mut asm_generator =
-            AsmGenerator { counter: 0, function_calls: HashMap::new(), instructions: vec![] };
+        let mut asm_generator = AsmGenerator {
+            counter: 0,
+            function_calls: HashMap::new(),
+            instructions: vec![],
+            debug: false,
+        };
 
         asm_generator.inject_global_return();
 
@@ -1133,6 +1228,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn debug_mode_checks_stack_overflow_on_function_entry() {
+        let vm_commands = vec![Command::Function(FunctionArgs::Function("Main.main".into(), 0))];
+
+        let asm_commands = AsmGenerator::generate(vm_commands, true);
+
+        assert_eq!(
+            vec!["(Main.main)", "@SP", "D=M", "@2047", "D=D-A", "@Main.main$STACK_OK", "D;JLE"],
+            asm_commands[..7]
+        );
+        assert!(asm_commands.contains(&"@100".to_string()));
+        assert!(asm_commands.contains(&"@Sys.error".to_string()));
+        assert_eq!(Some(&"(Main.main$STACK_OK)".to_string()), asm_commands.last());
+    }
+
     #[test]
     fn recursive_function_calls_assigns_labels_properly() {
         let vm_commands: Vec<Command> = vec![
@@ -1142,7 +1252,7 @@ mod tests {
             Command::Function(FunctionArgs::Return),
         ];
 
-        let asm_commands = AsmGenerator::generate(vm_commands);
+        let asm_commands = AsmGenerator::generate(vm_commands, false);
 
         assert!(asm_commands.iter().filter(|cmd| *cmd == "(Test$ret.0)").count() == 1);
         assert!(asm_commands.iter().filter(|cmd| *cmd == "@Test$ret.0").count() == 1);
@@ -2,7 +2,7 @@ pub mod branching;
 pub mod function;
 pub mod operation;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Command {
     Branching(branching::BranchingArgs),
     Function(function::FunctionArgs),
@@ -1,7 +1,7 @@
 #[derive(Debug, PartialEq, Clone)]
 pub enum FunctionArgs {
-    Function(String, u8),
-    Call(String, u8),
+    Function(String, u16),
+    Call(String, u16),
     Return,
 }
 
@@ -14,11 +14,14 @@ impl TryFrom<String> for FunctionArgs {
         match tokens[0] {
             "function" => Ok(FunctionArgs::Function(
                 tokens[1].to_string(),
-                tokens[2].parse::<u8>().expect("Missing function nVars"),
+                tokens
+                    .get(2)
+                    .and_then(|n| n.parse::<u16>().ok())
+                    .ok_or("Invalid function nVars")?,
             )),
             "call" => Ok(FunctionArgs::Call(
                 tokens[1].to_string(),
-                tokens[2].parse::<u8>().expect("Missing call nArgs"),
+                tokens.get(2).and_then(|n| n.parse::<u16>().ok()).ok_or("Invalid call nArgs")?,
             )),
             "return" => Ok(FunctionArgs::Return),
             _ => Err("Cannot parse vm operation"),
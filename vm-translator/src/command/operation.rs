@@ -1,4 +1,4 @@
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum OperationArgs {
     Push(MemorySegment, i16, String),
     Pop(MemorySegment, i16, String),
@@ -43,7 +43,7 @@ impl OperationArgs {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum MemorySegment {
     Constant,
     Local,
@@ -0,0 +1,334 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::Options;
+use crate::command::{
+    Command,
+    function::FunctionArgs,
+    operation::{MemorySegment, OperationArgs},
+};
+
+/* Number of registers making up the temp segment, R5..R12 */
+const TEMP_SIZE: i16 = 8;
+
+/// Substitutes calls to small functions with their bodies.
+///
+/// A `call` followed by a `return` costs dozens of asm instructions, more than what
+/// getters such as `Memory.peek` or `String.length` actually do. A function is inlined when:
+/// - its body, `return` excluded, has at most `options.inline_threshold` commands
+/// - it is a leaf: no `call` (hence no recursion), no branching, a single final `return`
+/// - it leaves exactly its return value on the stack
+/// - it is not listed in `options.no_inline`
+///
+/// Arguments and local vars are moved to the temp registers not used by the body itself,
+/// and `pointer 0/1` are saved and restored around the body whenever it sets them,
+/// just like the caller frame would. A threshold of 0 disables inlining.
+pub fn inline(vm_commands: Vec<Command>, options: &Options) -> Vec<Command> {
+    if options.inline_threshold == 0 {
+        return vm_commands;
+    }
+
+    let candidates = collect_candidates(&vm_commands, options);
+    let mut commands: Vec<Command> = vec![];
+
+    vm_commands.into_iter().for_each(|vm_command| match &vm_command {
+        Command::Function(FunctionArgs::Call(fn_name, n_args)) => {
+            match candidates.get(fn_name).and_then(|candidate| candidate.expand(*n_args)) {
+                Some(expansion) => commands.extend(expansion),
+                None => commands.push(vm_command),
+            }
+        }
+        _ => commands.push(vm_command),
+    });
+
+    commands
+}
+
+struct Candidate {
+    filename: String,
+    n_locals: u16,
+    n_args_used: u16,
+    saved_pointers: Vec<i16>,
+    free_temps: Vec<i16>,
+    body: Vec<Command>,
+}
+
+impl Candidate {
+    fn from(fn_name: &str, n_locals: u16, body: &[Command], threshold: usize) -> Option<Self> {
+        let (last, statements) = body.split_last()?;
+
+        if *last != Command::Function(FunctionArgs::Return) || statements.len() > threshold {
+            return None;
+        }
+
+        let mut depth: i32 = 0;
+        let mut n_args_used: u16 = 0;
+        let mut used_temps: HashSet<i16> = HashSet::new();
+        let mut saved_pointers: Vec<i16> = vec![];
+
+        for vm_command in statements {
+            let Command::Operation(args) = vm_command else {
+                return None;
+            };
+
+            match args {
+                OperationArgs::Push(segment, index, _) | OperationArgs::Pop(segment, index, _) => {
+                    match segment {
+                        MemorySegment::Argument => n_args_used = n_args_used.max(*index as u16 + 1),
+                        MemorySegment::Local if *index as u16 >= n_locals => return None,
+                        MemorySegment::Temp => {
+                            used_temps.insert(*index);
+                        }
+                        MemorySegment::Pointer => {
+                            if let OperationArgs::Pop(_, _, _) = args
+                                && !saved_pointers.contains(index)
+                            {
+                                saved_pointers.push(*index);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+
+            depth += match args {
+                OperationArgs::Push(_, _, _) => 1,
+                OperationArgs::Neg | OperationArgs::Not => 0,
+                _ => -1,
+            };
+
+            if depth < 0 {
+                return None;
+            }
+        }
+
+        if depth != 1 {
+            return None;
+        }
+
+        let free_temps: Vec<i16> = (0..TEMP_SIZE).filter(|t| !used_temps.contains(t)).collect();
+        let filename = fn_name.split_once('.').map_or(fn_name, |(f, _)| f).to_string();
+
+        Some(Self {
+            filename,
+            n_locals,
+            n_args_used,
+            saved_pointers,
+            free_temps,
+            body: statements.to_vec(),
+        })
+    }
+
+    /* None when the call site does not provide the arguments used by the body
+     * or when there are not enough free temp registers */
+    fn expand(self: &Self, n_args: u16) -> Option<Vec<Command>> {
+        let n_slots = (n_args + self.n_locals) as usize + self.saved_pointers.len();
+        if n_args < self.n_args_used || n_slots > self.free_temps.len() {
+            return None;
+        }
+
+        let (arg_temps, rest) = self.free_temps.split_at(n_args as usize);
+        let (local_temps, rest) = rest.split_at(self.n_locals as usize);
+        let pointer_temps = &rest[..self.saved_pointers.len()];
+
+        let mut expansion: Vec<Command> = vec![];
+
+        arg_temps.iter().rev().for_each(|t| expansion.push(self.pop(MemorySegment::Temp, *t)));
+
+        self.saved_pointers.iter().zip(pointer_temps).for_each(|(pointer, t)| {
+            expansion.push(self.push(MemorySegment::Pointer, *pointer));
+            expansion.push(self.pop(MemorySegment::Temp, *t));
+        });
+
+        local_temps.iter().for_each(|t| {
+            expansion.push(self.push(MemorySegment::Constant, 0));
+            expansion.push(self.pop(MemorySegment::Temp, *t));
+        });
+
+        self.body.iter().for_each(|vm_command| {
+            expansion.push(match vm_command {
+                Command::Operation(OperationArgs::Push(MemorySegment::Argument, i, _)) => {
+                    self.push(MemorySegment::Temp, arg_temps[*i as usize])
+                }
+                Command::Operation(OperationArgs::Pop(MemorySegment::Argument, i, _)) => {
+                    self.pop(MemorySegment::Temp, arg_temps[*i as usize])
+                }
+                Command::Operation(OperationArgs::Push(MemorySegment::Local, i, _)) => {
+                    self.push(MemorySegment::Temp, local_temps[*i as usize])
+                }
+                Command::Operation(OperationArgs::Pop(MemorySegment::Local, i, _)) => {
+                    self.pop(MemorySegment::Temp, local_temps[*i as usize])
+                }
+                _ => vm_command.clone(),
+            })
+        });
+
+        /* the return value stays on top of the stack */
+        self.saved_pointers.iter().zip(pointer_temps).for_each(|(pointer, t)| {
+            expansion.push(self.push(MemorySegment::Temp, *t));
+            expansion.push(self.pop(MemorySegment::Pointer, *pointer));
+        });
+
+        Some(expansion)
+    }
+
+    fn push(self: &Self, segment: MemorySegment, index: i16) -> Command {
+        Command::Operation(OperationArgs::Push(segment, index, self.filename.clone()))
+    }
+
+    fn pop(self: &Self, segment: MemorySegment, index: i16) -> Command {
+        Command::Operation(OperationArgs::Pop(segment, index, self.filename.clone()))
+    }
+}
+
+fn collect_candidates(vm_commands: &[Command], options: &Options) -> HashMap<String, Candidate> {
+    let mut candidates: HashMap<String, Candidate> = HashMap::new();
+    let headers: Vec<usize> = vm_commands
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| matches!(c, Command::Function(FunctionArgs::Function(_, _))))
+        .map(|(index, _)| index)
+        .collect();
+
+    for (i, start) in headers.iter().enumerate() {
+        let end = headers.get(i + 1).copied().unwrap_or(vm_commands.len());
+
+        if let Command::Function(FunctionArgs::Function(fn_name, n_locals)) = &vm_commands[*start]
+            && !options.no_inline.contains(fn_name)
+            && let Some(candidate) = Candidate::from(
+                fn_name,
+                *n_locals,
+                &vm_commands[start + 1..end],
+                options.inline_threshold,
+            )
+        {
+            candidates.insert(fn_name.clone(), candidate);
+        }
+    }
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    fn parse_vm(vm: Vec<&str>) -> Vec<Command> {
+        parser::parse(vm.into_iter().map(|l| l.to_string()).collect())
+    }
+
+    /* Parses commands as if they belonged to `fn_name`, which sets the filename of each command */
+    fn parse_vm_within(fn_name: &str, vm: Vec<&str>) -> Vec<Command> {
+        let header = format!("function {} 0", fn_name);
+        parse_vm([vec![header.as_str()], vm].concat()).split_off(1)
+    }
+
+    fn options(inline_threshold: usize) -> Options {
+        Options { inline_threshold, ..Options::default() }
+    }
+
+    const PEEK: [&str; 7] = [
+        "function Memory.peek 0",
+        "push static 0",
+        "push argument 0",
+        "add",
+        "pop pointer 1",
+        "push that 0",
+        "return",
+    ];
+
+    #[test]
+    fn inline_leaf_function_saving_pointers() {
+        let mut vm = vec!["function Main.main 0", "push constant 8000", "call Memory.peek 1"];
+        vm.extend(["pop temp 0", "push constant 0", "return"]);
+        vm.extend(PEEK);
+
+        let expected = [
+            parse_vm(vec!["function Main.main 0", "push constant 8000"]),
+            parse_vm_within(
+                "Memory.peek",
+                vec![
+                    "pop temp 0",
+                    "push pointer 1",
+                    "pop temp 1",
+                    "push static 0",
+                    "push temp 0",
+                    "add",
+                    "pop pointer 1",
+                    "push that 0",
+                    "push temp 1",
+                    "pop pointer 1",
+                ],
+            ),
+            parse_vm_within("Main.main", vec!["pop temp 0", "push constant 0", "return"]),
+            parse_vm(PEEK.to_vec()),
+        ]
+        .concat();
+
+        assert_eq!(expected, inline(parse_vm(vm), &options(8)));
+    }
+
+    #[test]
+    fn map_locals_to_temp_registers_not_used_by_the_body() {
+        let vm = vec![
+            "function Main.main 0",
+            "push constant 3",
+            "call Main.twice 1",
+            "return",
+            "function Main.twice 1",
+            "push argument 0",
+            "pop temp 0",
+            "push temp 0",
+            "push temp 0",
+            "add",
+            "pop local 0",
+            "push local 0",
+            "return",
+        ];
+
+        let expected = parse_vm(vec![
+            "function Main.main 0",
+            "push constant 3",
+            "pop temp 1",
+            "push constant 0",
+            "pop temp 2",
+            "push temp 1",
+            "pop temp 0",
+            "push temp 0",
+            "push temp 0",
+            "add",
+            "pop temp 2",
+            "push temp 2",
+            "return",
+        ]);
+
+        assert_eq!(expected, inline(parse_vm(vm), &options(8))[..13]);
+    }
+
+    #[test]
+    fn skip_functions_above_threshold_opted_out_or_not_leaves() {
+        let vm = vec![
+            "function Main.main 0",
+            "call Main.getter 0",
+            "call Main.caller 0",
+            "add",
+            "return",
+            "function Main.getter 0",
+            "push static 0",
+            "return",
+            "function Main.caller 0",
+            "call Main.getter 0",
+            "return",
+        ];
+
+        assert_eq!(parse_vm(vm.clone()), inline(parse_vm(vm.clone()), &options(0)));
+
+        let opted_out = Options { no_inline: vec!["Main.getter".into()], ..options(8) };
+        assert_eq!(parse_vm(vm.clone()), inline(parse_vm(vm.clone()), &opted_out));
+
+        let inlined = inline(parse_vm(vm), &options(8));
+        assert_eq!(parse_vm(vec!["function Main.main 0", "push static 0"]), inlined[..2]);
+        assert_eq!(parse_vm(vec!["call Main.caller 0"]), inlined[2..3]);
+    }
+}
@@ -3,15 +3,36 @@ use std::{fs::read_to_string, path::PathBuf};
 #[path = "asm-generator.rs"]
 mod asm_generator;
 mod command;
+mod inliner;
 mod parser;
+mod verifier;
 
-pub fn compile(vm_instructions: Vec<Vec<String>>) -> Vec<String> {
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// Instrument the generated asm with runtime checks reporting failures through `Sys.error`
+    pub debug: bool,
+    /// Largest function body, in vm commands, substituted at its call sites: 0 disables inlining
+    pub inline_threshold: usize,
+    /// Functions never inlined, e.g. `Memory.alloc`
+    pub no_inline: Vec<String>,
+}
+
+pub fn compile(
+    vm_instructions: Vec<Vec<String>>,
+    options: &Options,
+) -> Result<Vec<String>, String> {
     let bootstrap_instructions = vec!["call Sys.init 0".to_string()];
 
     let instructions: Vec<String> = vm_instructions.into_iter().flat_map(|vm| vm).collect();
     let commands = parser::parse(bootstrap_instructions.into_iter().chain(instructions).collect());
 
-    asm_generator::compile(commands)
+    if let Err(errors) = verifier::verify(&commands) {
+        return Err(errors.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n"));
+    }
+
+    let commands = inliner::inline(commands, options);
+
+    Ok(asm_generator::compile(commands, options))
 }
 
 fn read_vm_program_from_path(vm_program_path: &PathBuf) -> Vec<String> {
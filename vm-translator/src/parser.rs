@@ -183,6 +183,15 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn call_function_with_more_than_255_args() {
+        let expected = vec![Command::Function(FunctionArgs::Call("TestFunc".to_string(), 300))];
+
+        let actual = parse(vec!["call TestFunc 300".to_string()]);
+
+        assert_eq!(expected, actual);
+    }
+
     #[test]
     fn return_from_function() {
         let expected = vec![Command::Function(FunctionArgs::Return)];
@@ -0,0 +1,455 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::command::{
+    Command,
+    branching::BranchingArgs,
+    function::FunctionArgs,
+    operation::{MemorySegment, OperationArgs},
+};
+
+/* The stack spans RAM[256..2047] */
+const STACK_SIZE: u16 = 2048 - 256;
+
+/// Static checks over parsed vm commands, run before any asm is generated.
+///
+/// Each function is verified on its own: the stack depth is tracked along every
+/// control flow path starting from an empty stack right after the `function` command.
+/// Branch targets are resolved against the labels of the same function, since
+/// labels are scoped to the function declaring them.
+///
+/// Commands appearing before the first `function` (e.g. the bootstrap `call Sys.init 0`)
+/// are verified as one more anonymous block.
+pub fn verify(vm_commands: &[Command]) -> Result<(), Vec<VerificationError>> {
+    let errors: Vec<VerificationError> =
+        split_functions(vm_commands).into_iter().flat_map(|block| block.verify()).collect();
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum VerificationError {
+    StackUnderflow { function: String, position: usize },
+    StackMismatch { function: String, label: String, expected: usize, found: usize },
+    UndefinedLabel { function: String, label: String },
+    DuplicateLabel { function: String, label: String },
+    PopConstant { function: String, position: usize },
+    InvalidPointer { function: String, position: usize, index: i16 },
+    FrameTooLarge { function: String, position: usize, size: u16 },
+}
+
+impl Display for VerificationError {
+    fn fmt(self: &Self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StackUnderflow { function, position } => {
+                write!(f, "{function}: stack underflow at command {position}")
+            }
+            Self::StackMismatch { function, label, expected, found } => write!(
+                f,
+                "{function}: label {label} is reached with stack depths {expected} and {found}"
+            ),
+            Self::UndefinedLabel { function, label } => {
+                write!(f, "{function}: jump to undefined label {label}")
+            }
+            Self::DuplicateLabel { function, label } => {
+                write!(f, "{function}: label {label} is defined more than once")
+            }
+            Self::PopConstant { function, position } => {
+                write!(f, "{function}: cannot pop to the constant segment at command {position}")
+            }
+            Self::InvalidPointer { function, position, index } => write!(
+                f,
+                "{function}: pointer segment allows indices 0 or 1, found {index} at command {position}"
+            ),
+            Self::FrameTooLarge { function, position, size } => write!(
+                f,
+                "{function}: {size} local vars or arguments at command {position} exceed the stack size"
+            ),
+        }
+    }
+}
+
+struct FunctionBlock<'a> {
+    name: String,
+    commands: &'a [Command],
+}
+
+/* Positions are relative to the start of the block, the `function` command being 0 */
+fn split_functions(vm_commands: &[Command]) -> Vec<FunctionBlock<'_>> {
+    let mut blocks: Vec<FunctionBlock> = vec![];
+    let mut start: usize = 0;
+
+    for (index, vm_command) in vm_commands.iter().enumerate() {
+        if let Command::Function(FunctionArgs::Function(_, _)) = vm_command
+            && index > 0
+        {
+            blocks.push(FunctionBlock::new(&vm_commands[start..index]));
+            start = index;
+        }
+    }
+
+    if start < vm_commands.len() {
+        blocks.push(FunctionBlock::new(&vm_commands[start..]));
+    }
+
+    blocks
+}
+
+impl<'a> FunctionBlock<'a> {
+    fn new(commands: &'a [Command]) -> Self {
+        let name = match commands.first() {
+            Some(Command::Function(args @ FunctionArgs::Function(_, _))) => args.fn_name(),
+            _ => "".to_string(),
+        };
+
+        Self { name, commands }
+    }
+
+    fn verify(self: &Self) -> Vec<VerificationError> {
+        let mut errors: Vec<VerificationError> = vec![];
+        let labels = self.collect_labels(&mut errors);
+
+        self.check_segments(&mut errors);
+        self.check_jump_targets(&labels, &mut errors);
+        self.check_stack_depth(&labels, &mut errors);
+
+        errors
+    }
+
+    fn collect_labels(self: &Self, errors: &mut Vec<VerificationError>) -> HashMap<&'a str, usize> {
+        let mut labels: HashMap<&str, usize> = HashMap::new();
+
+        for (position, vm_command) in self.commands.iter().enumerate() {
+            if let Command::Branching(BranchingArgs::Label(label, _)) = vm_command
+                && labels.insert(label.as_str(), position).is_some()
+            {
+                errors.push(VerificationError::DuplicateLabel {
+                    function: self.name.clone(),
+                    label: label.clone(),
+                });
+            }
+        }
+
+        labels
+    }
+
+    fn check_segments(self: &Self, errors: &mut Vec<VerificationError>) {
+        for (position, vm_command) in self.commands.iter().enumerate() {
+            match vm_command {
+                Command::Operation(OperationArgs::Pop(MemorySegment::Constant, _, _)) => {
+                    errors.push(VerificationError::PopConstant {
+                        function: self.name.clone(),
+                        position,
+                    });
+                }
+                Command::Operation(
+                    OperationArgs::Push(MemorySegment::Pointer, index, _)
+                    | OperationArgs::Pop(MemorySegment::Pointer, index, _),
+                ) if *index != 0 && *index != 1 => {
+                    errors.push(VerificationError::InvalidPointer {
+                        function: self.name.clone(),
+                        position,
+                        index: *index,
+                    });
+                }
+                Command::Function(
+                    FunctionArgs::Function(_, size) | FunctionArgs::Call(_, size),
+                ) if *size > STACK_SIZE => {
+                    errors.push(VerificationError::FrameTooLarge {
+                        function: self.name.clone(),
+                        position,
+                        size: *size,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_jump_targets(
+        self: &Self,
+        labels: &HashMap<&str, usize>,
+        errors: &mut Vec<VerificationError>,
+    ) {
+        self.commands.iter().for_each(|vm_command| match vm_command {
+            Command::Branching(BranchingArgs::Goto(label, _) | BranchingArgs::IfGoto(label, _))
+                if !labels.contains_key(label.as_str()) =>
+            {
+                errors.push(VerificationError::UndefinedLabel {
+                    function: self.name.clone(),
+                    label: label.clone(),
+                });
+            }
+            _ => {}
+        });
+    }
+
+    /* Worklist over the control flow graph: every reachable command is assigned the stack
+     * depth it is entered with, and a second path entering it with another depth is a mismatch.
+     * Unreachable commands (e.g. a `goto` right after `return`) are never visited. */
+    fn check_stack_depth(
+        self: &Self,
+        labels: &HashMap<&str, usize>,
+        errors: &mut Vec<VerificationError>,
+    ) {
+        if self.commands.is_empty() {
+            return;
+        }
+
+        let mut depths: Vec<Option<usize>> = vec![None; self.commands.len()];
+        let mut mismatches: HashSet<usize> = HashSet::new();
+        let mut worklist: Vec<usize> = vec![0];
+        depths[0] = Some(0);
+
+        while let Some(position) = worklist.pop() {
+            let depth = depths[position].unwrap();
+            let vm_command = &self.commands[position];
+            let (pops, pushes) = stack_effect(vm_command);
+
+            if depth < pops {
+                errors.push(VerificationError::StackUnderflow {
+                    function: self.name.clone(),
+                    position,
+                });
+                continue;
+            }
+
+            let next_depth = depth - pops + pushes;
+
+            for successor in self.successors(position, labels) {
+                match depths[successor] {
+                    None => {
+                        depths[successor] = Some(next_depth);
+                        worklist.push(successor);
+                    }
+                    Some(expected) if expected != next_depth && mismatches.insert(successor) => {
+                        errors.push(VerificationError::StackMismatch {
+                            function: self.name.clone(),
+                            label: self.label_at(successor),
+                            expected,
+                            found: next_depth,
+                        });
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
+    fn successors(self: &Self, position: usize, labels: &HashMap<&str, usize>) -> Vec<usize> {
+        let fallthrough =
+            if position + 1 < self.commands.len() { Some(position + 1) } else { None };
+
+        match &self.commands[position] {
+            Command::Branching(BranchingArgs::Goto(label, _)) => {
+                labels.get(label.as_str()).copied().into_iter().collect()
+            }
+            Command::Branching(BranchingArgs::IfGoto(label, _)) => {
+                fallthrough.into_iter().chain(labels.get(label.as_str()).copied()).collect()
+            }
+            Command::Function(FunctionArgs::Return) => vec![],
+            _ => fallthrough.into_iter().collect(),
+        }
+    }
+
+    fn label_at(self: &Self, position: usize) -> String {
+        match &self.commands[position] {
+            Command::Branching(BranchingArgs::Label(label, _)) => label.clone(),
+            _ => format!("at command {position}"),
+        }
+    }
+}
+
+/* (values popped, values pushed) */
+fn stack_effect(vm_command: &Command) -> (usize, usize) {
+    match vm_command {
+        Command::Operation(args) => match args {
+            OperationArgs::Push(_, _, _) => (0, 1),
+            OperationArgs::Pop(_, _, _) => (1, 0),
+            OperationArgs::Neg | OperationArgs::Not => (1, 1),
+            OperationArgs::Add
+            | OperationArgs::Sub
+            | OperationArgs::Gt
+            | OperationArgs::Lt
+            | OperationArgs::Eq
+            | OperationArgs::And
+            | OperationArgs::Or => (2, 1),
+        },
+        Command::Branching(BranchingArgs::IfGoto(_, _)) => (1, 0),
+        Command::Branching(_) => (0, 0),
+        Command::Function(args) => match args {
+            FunctionArgs::Function(_, _) => (0, 0),
+            FunctionArgs::Call(_, n_args) => (*n_args as usize, 1),
+            FunctionArgs::Return => (1, 0),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    fn verify_vm(vm: Vec<&str>) -> Result<(), Vec<VerificationError>> {
+        verify(&parser::parse(vm.into_iter().map(|l| l.to_string()).collect()))
+    }
+
+    #[test]
+    fn balanced_function_passes() {
+        let vm = vec![
+            "function Main.main 1",
+            "push constant 0",
+            "pop local 0",
+            "label Loop",
+            "push local 0",
+            "push constant 10",
+            "lt",
+            "not",
+            "if-goto Done",
+            "push local 0",
+            "push constant 1",
+            "add",
+            "pop local 0",
+            "goto Loop",
+            "label Done",
+            "push constant 0",
+            "return",
+        ];
+
+        assert_eq!(Ok(()), verify_vm(vm));
+    }
+
+    #[test]
+    fn detect_stack_underflow() {
+        let vm = vec!["function Main.main 0", "push constant 1", "add", "return"];
+
+        assert_eq!(
+            Err(vec![VerificationError::StackUnderflow {
+                function: "Main.main".into(),
+                position: 2,
+            }]),
+            verify_vm(vm)
+        );
+    }
+
+    #[test]
+    fn detect_call_underflow() {
+        let vm = vec!["function Main.main 0", "push constant 1", "call Math.multiply 2", "return"];
+
+        assert_eq!(
+            Err(vec![VerificationError::StackUnderflow {
+                function: "Main.main".into(),
+                position: 2,
+            }]),
+            verify_vm(vm)
+        );
+    }
+
+    #[test]
+    fn detect_mismatched_depth_at_merge_point() {
+        let vm = vec![
+            "function Main.main 0",
+            "push constant 1",
+            "if-goto Skip",
+            "push constant 2",
+            "label Skip",
+            "push constant 0",
+            "return",
+        ];
+
+        assert_eq!(
+            Err(vec![VerificationError::StackMismatch {
+                function: "Main.main".into(),
+                label: "Skip".into(),
+                expected: 0,
+                found: 1,
+            }]),
+            verify_vm(vm)
+        );
+    }
+
+    #[test]
+    fn detect_undefined_and_duplicate_labels() {
+        let vm = vec![
+            "function Main.main 0",
+            "label Twice",
+            "label Twice",
+            "goto Missing",
+            "function Main.other 0",
+            "goto Twice",
+        ];
+
+        assert_eq!(
+            Err(vec![
+                VerificationError::DuplicateLabel {
+                    function: "Main.main".into(),
+                    label: "Twice".into(),
+                },
+                VerificationError::UndefinedLabel {
+                    function: "Main.main".into(),
+                    label: "Missing".into(),
+                },
+                VerificationError::UndefinedLabel {
+                    function: "Main.other".into(),
+                    label: "Twice".into(),
+                },
+            ]),
+            verify_vm(vm)
+        );
+    }
+
+    #[test]
+    fn detect_invalid_segment_usage() {
+        let vm = vec![
+            "function Main.main 0",
+            "push constant 1",
+            "pop constant 0",
+            "push pointer 2",
+            "pop pointer 1",
+            "push constant 0",
+            "return",
+        ];
+
+        assert_eq!(
+            Err(vec![
+                VerificationError::PopConstant { function: "Main.main".into(), position: 2 },
+                VerificationError::InvalidPointer {
+                    function: "Main.main".into(),
+                    position: 3,
+                    index: 2,
+                },
+            ]),
+            verify_vm(vm)
+        );
+    }
+
+    #[test]
+    fn detect_frames_exceeding_the_stack() {
+        let vm = vec!["function Main.main 2000", "push constant 0", "return"];
+
+        assert_eq!(
+            Err(vec![VerificationError::FrameTooLarge {
+                function: "Main.main".into(),
+                position: 0,
+                size: 2000,
+            }]),
+            verify_vm(vm)
+        );
+    }
+
+    #[test]
+    fn unreachable_commands_are_not_checked_for_depth() {
+        let vm = vec![
+            "function Main.main 0",
+            "push constant 0",
+            "return",
+            "goto End",
+            "add",
+            "label End",
+        ];
+
+        assert_eq!(Ok(()), verify_vm(vm));
+    }
+}